@@ -1,6 +1,21 @@
-use alloc::{collections::BTreeMap, rc::Rc, string::String, vec::Vec};
-use core::fmt::{Debug, Error, Formatter};
-
+use crate::{
+    check::{infer, termination},
+    code::{UNKNOWN_CONSTRUCTOR, UNPROVEN_TERMINATION, UNRESOLVED_CASE},
+    diagnostic::{Diagnostic, Diagnostics, LintLevel, Span},
+    types::Type,
+};
+use alloc::{
+    collections::BTreeMap,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{Debug, Display, Error, Formatter},
+    ops::Deref,
+};
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub struct AST {
     imports: Vec<Import>,
@@ -8,6 +23,10 @@ pub struct AST {
     cons: Vec<Constructor>,
     data: Vec<Data>,
     functions: Vec<Function>,
+    signatures: Vec<Signature>,
+    classes: Vec<Class>,
+    instances: Vec<Instance>,
+    fixities: Vec<Fixity>,
 }
 
 impl AST {
@@ -17,6 +36,7 @@ impl AST {
         cons: Vec<Constructor>,
         data: Vec<Data>,
         functions: Vec<Function>,
+        signatures: Vec<Signature>,
     ) -> Self {
         Self {
             imports,
@@ -24,9 +44,26 @@ impl AST {
             cons,
             data,
             functions,
+            signatures,
+            classes: Vec::new(),
+            instances: Vec::new(),
+            fixities: Vec::new(),
         }
     }
 
+    /// Declare type classes, and the instances of them
+    pub fn with_classes(mut self, classes: Vec<Class>, instances: Vec<Instance>) -> Self {
+        self.classes = classes;
+        self.instances = instances;
+        self
+    }
+
+    /// Declare the precedence and associativity of infix operators
+    pub fn with_fixities(mut self, fixities: Vec<Fixity>) -> Self {
+        self.fixities = fixities;
+        self
+    }
+
     pub fn get_data(&self) -> &Vec<Data> {
         &self.data
     }
@@ -39,6 +76,91 @@ impl AST {
         &self.functions
     }
 
+    pub fn get_imports(&self) -> &Vec<Import> {
+        &self.imports
+    }
+
+    /// The type annotations written for functions and constants
+    pub fn get_signatures(&self) -> &Vec<Signature> {
+        &self.signatures
+    }
+
+    /// The declared precedence and associativity of infix operators
+    pub fn get_fixities(&self) -> &Vec<Fixity> {
+        &self.fixities
+    }
+
+    /// The constructors declared with `type`, which do not belong to a `data` declaration
+    pub fn get_constructors(&self) -> &Vec<Constructor> {
+        &self.cons
+    }
+
+    /// The constructors with named fields, from both `type` and `data` declarations
+    pub fn get_records(&self) -> Vec<&Constructor> {
+        self.cons
+            .iter()
+            .chain(self.data.iter().flat_map(|data| data.cons.iter()))
+            .filter(|cons| cons.is_record())
+            .collect()
+    }
+
+    /// The classes declared by the program, followed by the builtin classes
+    pub fn get_classes(&self) -> Vec<Class> {
+        let mut classes = self.classes.clone();
+        classes.extend(Class::builtins());
+        classes
+    }
+
+    pub fn get_instances(&self) -> &Vec<Instance> {
+        &self.instances
+    }
+
+    /// The functions defined by every instance
+    pub fn get_instance_methods(&self) -> Vec<&Function> {
+        self.instances
+            .iter()
+            .flat_map(|instance| instance.get_methods())
+            .collect()
+    }
+
+    /// Add an instance that was not written in the program, such as a derived instance
+    pub(crate) fn add_instance(&mut self, instance: Instance) {
+        self.instances.push(instance)
+    }
+
+    /// Add a function generated by lowering the program
+    pub(crate) fn add_function(&mut self, function: Function) {
+        self.functions.push(function)
+    }
+
+    pub(crate) fn get_functions_mut(&mut self) -> &mut Vec<Function> {
+        &mut self.functions
+    }
+
+    pub(crate) fn get_constants_mut(&mut self) -> &mut Vec<Constant> {
+        &mut self.constants
+    }
+
+    /// Decide whether each dotted expression reads a field or is a lambda.
+    /// `p.x` reads a field when `p` is a variable in scope or a constant, and is a lambda otherwise.
+    pub(crate) fn resolve_dotted(&mut self) {
+        let constants = self
+            .constants
+            .iter()
+            .map(|constant| constant.name.clone())
+            .collect::<Vec<_>>();
+        for constant in &mut self.constants {
+            constant.value = (*constant.value.resolve_dotted(&mut constants.clone())).clone();
+        }
+        let methods = self
+            .instances
+            .iter_mut()
+            .flat_map(|instance| instance.methods.iter_mut());
+        for function in self.functions.iter_mut().chain(methods) {
+            function.body = (*function.body.resolve_dotted(&mut constants.clone())).clone();
+        }
+    }
+
     pub fn inline_functions(&mut self) {
         self.replace_constants();
         for _ in 0..self.functions.len() {
@@ -52,19 +174,65 @@ impl AST {
         self.replace_constants();
     }
 
-    pub fn resolve_types(&mut self) {
+    /// Run the passes that lower the parsed program into a form that can be reduced
+    pub fn lower(&mut self, diagnostics: &mut Diagnostics) {
+        self.elaborate(diagnostics);
+        self.resolve_tailcalls(diagnostics);
+        self.replace_constructors();
+        self.resolve_types(diagnostics);
+        self.inline_functions();
+    }
+
+    pub fn resolve_types(&mut self, diagnostics: &mut Diagnostics) {
         for f in &mut self.functions {
-            f.resolve_types(&self.data, &self.cons);
+            f.resolve_types(&self.data, &self.cons, diagnostics);
         }
 
         for c in &mut self.constants {
-            c.resolve_types(&self.data, &self.cons);
+            c.resolve_types(&self.data, &self.cons, diagnostics);
         }
     }
 
-    pub fn resolve_tailcalls(&mut self) {
+    pub fn resolve_tailcalls(&mut self, diagnostics: &mut Diagnostics) {
+        self.resolve_recursion(diagnostics);
         for f in &mut self.functions {
-            f.resolve_tailcall();
+            f.resolve_tailcall(diagnostics);
+        }
+    }
+
+    /// Find the functions that call themselves or each other by name, which would otherwise be
+    /// inlined into themselves forever. A function that calls itself is given `rec` calls instead,
+    /// and a group of functions that call each other share one fixpoint.
+    fn resolve_recursion(&mut self, diagnostics: &mut Diagnostics) {
+        let dependencies = self
+            .functions
+            .iter()
+            .map(|f| (&f.name, &f.body))
+            .collect::<Vec<_>>();
+        let groups = infer::dependency_order(&dependencies);
+
+        for group in groups {
+            if let [i] = group[..] {
+                let f = &mut self.functions[i];
+                f.body = termination::self_calls(&f.name, &f.body);
+                continue;
+            }
+
+            let members = group
+                .iter()
+                .map(|&i| (&self.functions[i].name, &self.functions[i].body))
+                .collect::<Vec<_>>();
+            let bodies = if let Some(limits) = termination::limits(&members) {
+                termination::group_fixpoint(&members, &limits)
+            } else {
+                for &i in &group {
+                    self.functions[i].warn_unproven_termination(diagnostics);
+                }
+                termination::group_recursion(&members)
+            };
+            for (i, body) in group.into_iter().zip(bodies) {
+                self.functions[i].body = body;
+            }
         }
     }
 
@@ -83,32 +251,20 @@ impl AST {
         for f in &mut self.functions {
             for data in &self.data {
                 for cons in &data.cons {
-                    f.replace_constructors(&cons);
+                    f.replace_constructors(cons);
                     for constant in &mut self.constants {
-                        constant.replace_constructors(&cons);
+                        constant.replace_constructors(cons);
                     }
                 }
             }
 
             for cons in &self.cons {
-                f.replace_constructors(&cons);
+                f.replace_constructors(cons);
                 for constant in &mut self.constants {
-                    constant.replace_constructors(&cons);
-                }
-            }
-        }
-    }
-
-    pub fn has_conflicting_datatypes(&self) -> bool {
-        for a in &self.data {
-            for b in &self.data {
-                if a == b {
-                    return true;
+                    constant.replace_constructors(cons);
                 }
             }
         }
-
-        false
     }
 }
 
@@ -123,6 +279,287 @@ impl Import {
     pub fn new(file: String, items: Vec<String>) -> Self {
         Self { file, items }
     }
+
+    pub fn get_items(&self) -> &Vec<String> {
+        &self.items
+    }
+}
+
+// let add : Num -> Num -> Num
+// let show_twice : Show a => a -> String
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Signature {
+    name: String,
+    constraints: Vec<(String, String)>,
+    annotation: Type,
+    span: Option<Span>,
+}
+
+impl Signature {
+    pub fn new(name: String, annotation: Type) -> Self {
+        Self {
+            name,
+            constraints: Vec::new(),
+            annotation,
+            span: None,
+        }
+    }
+
+    /// Require type variables of the annotation to have instances of classes,
+    /// given as the name of the class and the variable
+    pub fn with_constraints(mut self, constraints: Vec<(String, String)>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Record where the signature was written
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_constraints(&self) -> &Vec<(String, String)> {
+        &self.constraints
+    }
+
+    pub fn get_annotation(&self) -> &Type {
+        &self.annotation
+    }
+
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
+/// Which side an infix operator groups on when it is written more than once in a row
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd)]
+pub enum Associativity {
+    /// a - b - c  ==>  (a - b) - c
+    Left,
+    /// a && b && c  ==>  a && (b && c)
+    Right,
+    /// a == b == c is not allowed
+    Neither,
+}
+
+// infixl 6 <+>
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Fixity {
+    operator: String,
+    associativity: Associativity,
+    /// From 0 to 9, where operators with a higher precedence group more tightly
+    precedence: u8,
+    span: Option<Span>,
+}
+
+impl Fixity {
+    pub fn new(operator: String, associativity: Associativity, precedence: u8) -> Self {
+        Self {
+            operator,
+            associativity,
+            precedence,
+            span: None,
+        }
+    }
+
+    /// Record where the fixity was declared
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn get_operator(&self) -> &String {
+        &self.operator
+    }
+
+    pub fn get_associativity(&self) -> Associativity {
+        self.associativity
+    }
+
+    pub fn get_precedence(&self) -> u8 {
+        self.precedence
+    }
+
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
+// class Show a where show : a -> String
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Class {
+    name: String,
+    parameter: String,
+    methods: Vec<Signature>,
+    span: Option<Span>,
+}
+
+impl Class {
+    /// The class of `+`, which is the method `plus`
+    pub const ADD: &'static str = "Add";
+    /// The class of `==` and `!=`, which use the method `equals`
+    pub const EQ: &'static str = "Eq";
+    /// The class of values that can be turned into strings with the method `show`
+    pub const SHOW: &'static str = "Show";
+    /// The class of values that can be ordered with the method `compare`
+    pub const ORD: &'static str = "Ord";
+    pub const PLUS: &'static str = "plus";
+    pub const EQUALS: &'static str = "equals";
+    pub const SHOW_METHOD: &'static str = "show";
+    pub const COMPARE: &'static str = "compare";
+
+    /// The classes that data declarations can derive instances of
+    pub const DERIVABLE: &'static [&'static str] = &[Self::SHOW, Self::EQ, Self::ORD];
+
+    pub fn new(name: String, parameter: String, methods: Vec<Signature>) -> Self {
+        Self {
+            name,
+            parameter,
+            methods,
+            span: None,
+        }
+    }
+
+    /// The classes of the overloaded operators, and of showing and ordering values.
+    /// Types without an instance of these use the builtin operators on numbers and strings.
+    pub fn builtins() -> Vec<Self> {
+        let a = || Type::named("a");
+        vec![
+            Self::new(
+                String::from(Self::ADD),
+                String::from("a"),
+                vec![Signature::new(
+                    String::from(Self::PLUS),
+                    Type::function(a(), Type::function(a(), a())),
+                )],
+            ),
+            Self::new(
+                String::from(Self::EQ),
+                String::from("a"),
+                vec![Signature::new(
+                    String::from(Self::EQUALS),
+                    Type::function(a(), Type::function(a(), Type::named("Bool"))),
+                )],
+            ),
+            Self::new(
+                String::from(Self::SHOW),
+                String::from("a"),
+                vec![Signature::new(
+                    String::from(Self::SHOW_METHOD),
+                    Type::function(a(), Type::string()),
+                )],
+            ),
+            // Orderings are -1 for less, 0 for equal, and 1 for greater
+            Self::new(
+                String::from(Self::ORD),
+                String::from("a"),
+                vec![Signature::new(
+                    String::from(Self::COMPARE),
+                    Type::function(a(), Type::function(a(), Type::number())),
+                )],
+            ),
+        ]
+    }
+
+    /// Record where the class was declared
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// The type variable that instances of the class replace
+    pub fn get_parameter(&self) -> &String {
+        &self.parameter
+    }
+
+    pub fn get_methods(&self) -> &Vec<Signature> {
+        &self.methods
+    }
+
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Check if the class is one of the builtin classes
+    pub fn is_builtin(&self) -> bool {
+        [Self::ADD, Self::EQ, Self::SHOW, Self::ORD].contains(&self.name.as_str())
+    }
+}
+
+// instance Show Shape where
+//     let show s = "shape"
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Instance {
+    class: String,
+    constraints: Vec<(String, String)>,
+    instance_type: Type,
+    methods: Vec<Function>,
+    span: Option<Span>,
+}
+
+impl Instance {
+    pub fn new(class: String, instance_type: Type, methods: Vec<Function>) -> Self {
+        Self {
+            class,
+            constraints: Vec::new(),
+            instance_type,
+            methods,
+            span: None,
+        }
+    }
+
+    /// Require parameters of the instance type to have instances of classes,
+    /// given as the name of the class and the parameter
+    pub fn with_constraints(mut self, constraints: Vec<(String, String)>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Record where the instance was declared
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn get_class(&self) -> &String {
+        &self.class
+    }
+
+    pub fn get_constraints(&self) -> &Vec<(String, String)> {
+        &self.constraints
+    }
+
+    pub fn get_type(&self) -> &Type {
+        &self.instance_type
+    }
+
+    /// The name of the type the instance is for, without its parameters
+    pub fn get_type_name(&self) -> &str {
+        match &self.instance_type {
+            Type::Named(name, _) => name,
+            _ => "",
+        }
+    }
+
+    pub fn get_methods(&self) -> &Vec<Function> {
+        &self.methods
+    }
+
+    pub(crate) fn get_methods_mut(&mut self) -> &mut Vec<Function> {
+        &mut self.methods
+    }
+
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
 }
 
 // const True = (x y -> x)
@@ -131,27 +568,63 @@ impl Import {
 pub struct Constant {
     name: String,
     value: Expression,
+    span: Option<Span>,
+    lint_levels: Vec<(String, LintLevel)>,
 }
 
 impl Constant {
     pub fn new(name: String, value: Expression) -> Self {
-        Self { name, value }
+        Self {
+            name,
+            value,
+            span: None,
+            lint_levels: Vec::new(),
+        }
+    }
+
+    /// Record where the constant was declared
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Set the levels of lints within the constant, from `@allow(...)` style attributes
+    pub fn with_lint_levels(mut self, lint_levels: Vec<(String, LintLevel)>) -> Self {
+        self.lint_levels = lint_levels;
+        self
     }
 
     pub fn get_name(&self) -> &String {
         &self.name
     }
 
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+
+    pub fn get_lint_levels(&self) -> &Vec<(String, LintLevel)> {
+        &self.lint_levels
+    }
+
     pub fn get_body(&self) -> &Expression {
         &self.value
     }
 
+    pub(crate) fn set_body(&mut self, body: Expression) {
+        self.value = body
+    }
+
     pub fn is_recursive(&self) -> bool {
         self.value.has_binding(&self.name)
     }
 
-    pub fn resolve_types(&mut self, data: &Vec<Data>, cons: &Vec<Constructor>) {
-        self.value = (*self.value.resolve_types(data, cons)).clone();
+    pub fn resolve_types(
+        &mut self,
+        data: &Vec<Data>,
+        cons: &Vec<Constructor>,
+        diagnostics: &mut Diagnostics,
+    ) {
+        self.value = (*self.value.resolve_types(data, cons, diagnostics)).clone();
     }
 
     pub fn replace_constant(&mut self, constant: &Constant) {
@@ -165,22 +638,77 @@ impl Constant {
     }
 }
 
-// data Result = Ok(x) | Err(e)
+// data Result a e = Ok(a) | Err(e)
 #[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Data {
     name: String,
+    parameters: Vec<String>,
     pub cons: Vec<Constructor>,
+    deriving: Vec<(String, Span)>,
+    span: Option<Span>,
+    lint_levels: Vec<(String, LintLevel)>,
 }
 
 impl Data {
     pub fn new(name: String, cons: Vec<Constructor>) -> Self {
-        Self { name, cons }
+        Self {
+            name,
+            parameters: Vec::new(),
+            cons,
+            deriving: Vec::new(),
+            span: None,
+            lint_levels: Vec::new(),
+        }
+    }
+
+    /// Make the data type generic over some type parameters.
+    /// Members named after a parameter hold a value of that type.
+    pub fn with_parameters(mut self, parameters: Vec<String>) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Derive instances of some of the builtin classes,
+    /// along with where each class was named
+    ///
+    /// deriving (Show, Eq)
+    pub fn with_deriving(mut self, deriving: Vec<(String, Span)>) -> Self {
+        self.deriving = deriving;
+        self
+    }
+
+    /// Record where the data type was declared
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Set the levels of lints within the data type, from `@allow(...)` style attributes
+    pub fn with_lint_levels(mut self, lint_levels: Vec<(String, LintLevel)>) -> Self {
+        self.lint_levels = lint_levels;
+        self
     }
 
     pub fn get_name(&self) -> &String {
         &self.name
     }
 
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+
+    pub fn get_parameters(&self) -> &Vec<String> {
+        &self.parameters
+    }
+
+    pub fn get_deriving(&self) -> &Vec<(String, Span)> {
+        &self.deriving
+    }
+
+    pub fn get_lint_levels(&self) -> &Vec<(String, LintLevel)> {
+        &self.lint_levels
+    }
+
     pub fn get_constructors(&self) -> Vec<Constructor> {
         let mut copy = self.cons.clone();
         copy.sort();
@@ -193,20 +721,145 @@ impl Data {
 pub struct Constructor {
     name: String,
     members: Vec<String>,
+    /// The type of each member, in terms of the parameters of its data type
+    types: Vec<Type>,
+    record: bool,
+    span: Option<Span>,
 }
 
 impl Constructor {
+    /// A constructor whose members are named after the types they hold
+    ///
+    /// type Pair(a, b)
     pub fn new(name: String, members: Vec<String>) -> Self {
-        Self { name, members }
+        Self {
+            name,
+            types: members
+                .iter()
+                .map(|member| Type::Named(member.clone(), Vec::new()))
+                .collect(),
+            members,
+            record: false,
+            span: None,
+        }
+    }
+
+    /// A constructor whose members are only given by their types
+    ///
+    /// Cons(a, List a)
+    pub fn positional(name: String, types: Vec<Type>) -> Self {
+        Self {
+            name,
+            members: (0..types.len()).map(|i| format!("${}", i)).collect(),
+            types,
+            record: false,
+            span: None,
+        }
+    }
+
+    /// A constructor whose members are named fields, each with the type it holds
+    ///
+    /// type Point { x : Num, y : Num }
+    pub fn record(name: String, fields: Vec<(String, Type)>) -> Self {
+        let (members, types) = fields.into_iter().unzip();
+        Self {
+            name,
+            members,
+            types,
+            record: true,
+            span: None,
+        }
+    }
+
+    /// Record where the constructor was declared
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
     }
 
     pub fn get_name(&self) -> &str {
         &self.name
     }
 
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+
     pub fn get_members(&self) -> &Vec<String> {
         &self.members
     }
+
+    pub fn get_types(&self) -> &Vec<Type> {
+        &self.types
+    }
+
+    pub fn is_record(&self) -> bool {
+        self.record
+    }
+
+    /// The position of a field among the members of a record
+    pub fn get_field_index(&self, field: &str) -> Option<usize> {
+        if self.record {
+            self.members.iter().position(|member| member == field)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for Data {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "data {}", self.name)?;
+        for parameter in &self.parameters {
+            write!(f, " {}", parameter)?;
+        }
+        write!(f, " =")?;
+        for (i, cons) in self.cons.iter().enumerate() {
+            if i > 0 {
+                write!(f, " |")?;
+            }
+            write!(f, " {}", cons)?;
+        }
+        if !self.deriving.is_empty() {
+            write!(f, " deriving (")?;
+            for (i, (class, _)) in self.deriving.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", class)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Constructor {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.name)?;
+        let (open, close) = match (self.record, self.members.is_empty()) {
+            (_, true) => return Ok(()),
+            (true, false) => (" { ", " }"),
+            (false, false) => ("(", ")"),
+        };
+        write!(f, "{}", open)?;
+        for (i, (member, t)) in self.members.iter().zip(&self.types).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match t {
+                // A field named after the parameter it holds
+                Type::Named(name, parameters)
+                    if self.record && name == member && parameters.is_empty() =>
+                {
+                    write!(f, "{}", member)?
+                }
+                t if self.record => write!(f, "{} : {}", member, t)?,
+                t => write!(f, "{}", t)?,
+            }
+        }
+        write!(f, "{}", close)
+    }
 }
 
 // let mul a b = a * b
@@ -215,35 +868,100 @@ impl Constructor {
 pub struct Function {
     name: String,
     body: Expression,
+    span: Option<Span>,
+    lint_levels: Vec<(String, LintLevel)>,
 }
 
 impl Function {
     pub fn new(name: String, mut args: Vec<String>, mut body: Expression) -> Self {
         args.reverse();
         for arg in &args {
-            body = Expression::Lambda(arg.clone(), Rc::new(body));
+            body = Expression::Lambda(arg.clone().into(), Rc::new(body));
         }
 
-        Self { name, body }
+        Self {
+            name,
+            body,
+            span: None,
+            lint_levels: Vec::new(),
+        }
+    }
+
+    /// Record where the function was declared
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Set the levels of lints within the function, from `@allow(...)` style attributes
+    pub fn with_lint_levels(mut self, lint_levels: Vec<(String, LintLevel)>) -> Self {
+        self.lint_levels = lint_levels;
+        self
     }
 
     pub fn get_name(&self) -> &String {
         &self.name
     }
+
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+
+    pub fn get_lint_levels(&self) -> &Vec<(String, LintLevel)> {
+        &self.lint_levels
+    }
+
     pub fn get_body(&self) -> &Expression {
         &self.body
     }
 
+    pub(crate) fn set_body(&mut self, body: Expression) {
+        self.body = body
+    }
+
     pub fn inline_function(&mut self, f: &Self) {
         self.replace_constant(&Constant::new(f.get_name().clone(), f.get_body().clone()))
     }
 
-    pub fn resolve_tailcall(&mut self) {
-        self.body = (*self.body.resolve_tailcall(true)).clone();
+    /// Replace the recursive calls of a function with a fixpoint.
+    ///
+    /// Functions that are sure to terminate are evaluated while compiling.
+    /// The rest are left to recurse at runtime, because evaluating them could hang the compiler.
+    pub fn resolve_tailcall(&mut self, diagnostics: &mut Diagnostics) {
+        if !self.body.is_recursive() {
+            return;
+        }
+
+        if let Some(limits) = termination::limits(&[(&self.name, &self.body)]) {
+            self.body = termination::fixpoint(&self.body, &limits[0]);
+            return;
+        }
+
+        self.warn_unproven_termination(diagnostics);
+        self.body = termination::recursion(&self.body);
     }
 
-    pub fn resolve_types(&mut self, data: &Vec<Data>, cons: &Vec<Constructor>) {
-        self.body = (*self.body.resolve_types(data, cons)).clone();
+    fn warn_unproven_termination(&self, diagnostics: &mut Diagnostics) {
+        let mut warning = Diagnostic::warning(
+            UNPROVEN_TERMINATION,
+            format!(
+                "cannot prove that `{}` terminates, so it is left to run at runtime",
+                self.name
+            ),
+        );
+        if let Some(span) = self.span {
+            warning = warning.with_span(span);
+        }
+        diagnostics.push(warning);
+    }
+
+    pub fn resolve_types(
+        &mut self,
+        data: &Vec<Data>,
+        cons: &Vec<Constructor>,
+        diagnostics: &mut Diagnostics,
+    ) {
+        self.body = (*self.body.resolve_types(data, cons, diagnostics)).clone();
     }
 
     pub fn replace_constant(&mut self, constant: &Constant) {
@@ -255,6 +973,131 @@ impl Function {
     }
 }
 
+/// A variable bound by a lambda, a `let`, or a pattern, along with where its name was written
+///
+/// It can be used as the name it binds.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Binder {
+    name: String,
+    span: Option<Span>,
+}
+
+impl Binder {
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            span: None,
+        }
+    }
+
+    /// Record where the name was written
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
+impl Deref for Binder {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.name
+    }
+}
+
+impl From<String> for Binder {
+    fn from(name: String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl Display for Binder {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// An arm of a case or a clause of a function: a pattern for each value that is matched,
+/// the condition that must also hold for it to be taken, and its body
+///
+/// | Circle(r) if r > 10 => "big"
+/// let fact 0 = 1
+pub type Arm = (Vec<Pattern>, Option<Rc<Expression>>, Rc<Expression>);
+
+// Just(Ok(x)), s @ Circle(r), 0, _
+#[derive(Clone, PartialEq, PartialOrd)]
+pub enum Pattern {
+    // _
+    Wildcard,
+    // x
+    Variable(Binder),
+    // 0
+    Number(f64),
+    // "circle"
+    String(String),
+    // Just(Ok(x))
+    Constructor(String, Vec<Self>),
+    // s @ Circle(r)
+    As(Binder, Rc<Self>),
+}
+
+impl Pattern {
+    /// The variables that the pattern binds, from left to right
+    pub fn variables(&self) -> Vec<&Binder> {
+        match self {
+            Self::Variable(name) => vec![name],
+            Self::Constructor(_, members) => members.iter().flat_map(Self::variables).collect(),
+            Self::As(name, pattern) => {
+                let mut result = vec![name];
+                result.extend(pattern.variables());
+                result
+            }
+            Self::Wildcard | Self::Number(_) | Self::String(_) => Vec::new(),
+        }
+    }
+
+    /// A constructor applied to nothing but variables, which a plain `case` arm can match
+    pub fn is_flat(&self) -> bool {
+        match self {
+            Self::Constructor(_, members) => members
+                .iter()
+                .all(|member| matches!(member, Self::Variable(_) | Self::Wildcard)),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Self::Wildcard => write!(f, "_"),
+            Self::Variable(name) => write!(f, "{}", name),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::String(s) => write!(f, "{:?}", s),
+            Self::Constructor(name, members) if members.is_empty() => write!(f, "{}", name),
+            Self::Constructor(name, members) => {
+                write!(f, "{}(", name)?;
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", member)?;
+                }
+                write!(f, ")")
+            }
+            Self::As(name, pattern) => write!(f, "{} @ {}", name, pattern),
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Clone, PartialEq, PartialOrd)]
 pub enum Expression {
@@ -312,7 +1155,24 @@ pub enum Expression {
         ///  |     |         |
         ///  v v---/         v
         /// Ok(x) => print "You said: " x
-        cases: Vec<(String, Vec<String>, Rc<Self>)>,
+        cases: Vec<(String, Vec<Binder>, Rc<Self>)>,
+    },
+
+    // case shape of
+    //  | s @ Circle(0) => s
+    //  | Just(Ok(x)) if x > 0 => x
+    //  | _ => Nothing
+    //
+    // let fact 0 = 1
+    // let fact n = n * fact (n - 1)
+    //
+    // A case whose arms are not all flat constructor patterns, or that has guards,
+    // or the clauses of a function, which are compiled to a decision tree of simpler expressions
+    Match {
+        /// The values to match, which are the parameters of a function defined by clauses
+        values: Vec<Rc<Self>>,
+        /// The arms, tried in order
+        arms: Vec<Arm>,
     },
 
     // Point(1, 2)
@@ -336,18 +1196,47 @@ pub enum Expression {
         /// The name of the type being filled
         cons_name: String,
         /// The arguments to the data constructor
-        members: Vec<String>,
+        members: Vec<Binder>,
         /// The value to deconstruct
         value: Rc<Self>,
         /// The body of the let expression
         body: Rc<Self>,
     },
 
+    // let total = a + b in total * total
+    Let {
+        /// The name being bound
+        name: Binder,
+        /// The value bound to the name, which is evaluated once and shared by every use
+        value: Rc<Self>,
+        /// The body of the let expression
+        body: Rc<Self>,
+    },
+
+    // Point { x = 1, y = 2 }
+    Record(String, Vec<(String, Rc<Self>)>),
+    // p.x
+    Field(Rc<Self>, String),
+    // p { x = 3 }
+    Update(Rc<Self>, Vec<(String, Rc<Self>)>),
+    // p.x + 1, which reads the field `x` of `p` when `p` is in scope,
+    // and is the lambda `p. x + 1` otherwise. This is decided once the program is parsed.
+    Dotted {
+        field: Rc<Self>,
+        lambda: Rc<Self>,
+    },
+
     Identifier(String),
+    // ?todo
+    Hole(String),
+    // Any expression, along with where it was written
+    Located(Span, Rc<Self>),
+    // (x : Num).x
+    Annotated(Rc<Self>, Type),
     // x z (y z)
     Application(Rc<Self>, Rc<Self>),
-    // (x y -> x)
-    Lambda(String, Rc<Self>),
+    // \x -> x
+    Lambda(Binder, Rc<Self>),
     // rec n-1
     TailCall(Vec<Rc<Self>>),
 
@@ -363,6 +1252,15 @@ pub enum Expression {
 
 impl Expression {
     pub(crate) const RECURSION_ARGUMENT: &'static str = "rec";
+    /// The builtin that turns numbers and strings into strings, for the builtin `Show` class
+    pub(crate) const SHOW_PRIMITIVE: &'static str = "$show";
+    /// The fixpoint of a function that is left to recurse at runtime
+    pub(crate) const RUNTIME_RECURSION: &'static str = "$recurse";
+    /// Function composition, which `f << g` and `f >> g` apply, and which reduces to `B`
+    pub(crate) const COMPOSE: &'static str = "$compose";
+    /// The names that are always bound, and reduce to builtin combinators
+    pub(crate) const BUILTINS: &'static [&'static str] =
+        &["true", "false", "print", "println", "Y", Self::COMPOSE];
 
     pub fn y_combinator() -> Self {
         // Self::Lambda(
@@ -393,20 +1291,305 @@ impl Expression {
         Self::Identifier(String::from("Y"))
     }
 
+    /// The expression without any source locations wrapped around it
+    pub fn unlocated(&self) -> &Self {
+        match self {
+            Self::Located(_, a) => a.unlocated(),
+            otherwise => otherwise,
+        }
+    }
+
+    /// Where the expression was written, if it is wrapped in its location
+    pub fn get_span(&self) -> Option<Span> {
+        match self {
+            Self::Located(span, _) => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// The expressions directly contained in this expression
+    pub fn children(&self) -> Vec<&Rc<Self>> {
+        match self {
+            Self::And(a, b)
+            | Self::Or(a, b)
+            | Self::Equal(a, b)
+            | Self::NotEqual(a, b)
+            | Self::Greater(a, b)
+            | Self::GreaterEqual(a, b)
+            | Self::Less(a, b)
+            | Self::LessEqual(a, b)
+            | Self::Add(a, b)
+            | Self::Multiply(a, b)
+            | Self::Divide(a, b)
+            | Self::Subtract(a, b)
+            | Self::Application(a, b) => vec![a, b],
+
+            Self::Not(a)
+            | Self::Negate(a)
+            | Self::Lambda(_, a)
+            | Self::Located(_, a)
+            | Self::Annotated(a, _) => vec![a],
+
+            Self::IfThenElse {
+                condition,
+                then_case,
+                else_case,
+            } => vec![condition, then_case, else_case],
+            Self::CaseOf { value, cases, .. } => {
+                let mut result = vec![value];
+                for (_, _, case_body) in cases {
+                    result.push(case_body);
+                }
+                result
+            }
+            Self::Match { values, arms } => {
+                let mut result = values.iter().collect::<Vec<_>>();
+                for (_, guard, body) in arms {
+                    result.extend(guard);
+                    result.push(body);
+                }
+                result
+            }
+            Self::Construct { members, .. } => members.iter().collect(),
+            Self::Deconstruct { value, body, .. } | Self::Let { value, body, .. } => {
+                vec![value, body]
+            }
+
+            Self::Record(_, fields) => fields.iter().map(|(_, field)| field).collect(),
+            Self::Field(a, _) => vec![a],
+            Self::Update(a, fields) => {
+                let mut result = vec![a];
+                result.extend(fields.iter().map(|(_, field)| field));
+                result
+            }
+            Self::Dotted { field, lambda } => vec![field, lambda],
+
+            Self::Table(map) => map.values().collect(),
+            Self::List(items) | Self::TailCall(items) => items.iter().collect(),
+
+            Self::Identifier(_) | Self::Hole(_) | Self::Number(_) | Self::String(_) => Vec::new(),
+        }
+    }
+
+    /// The number of expressions in this expression, including itself
+    pub fn size(&self) -> usize {
+        1 + self
+            .children()
+            .iter()
+            .map(|child| child.size())
+            .sum::<usize>()
+    }
+
+    /// Rebuild the expression, with each of its direct children transformed by `f`
+    pub fn map_children(&self, mut f: impl FnMut(&Rc<Self>) -> Rc<Self>) -> Self {
+        match self {
+            Self::And(a, b) => Self::And(f(a), f(b)),
+            Self::Or(a, b) => Self::Or(f(a), f(b)),
+            Self::Not(a) => Self::Not(f(a)),
+
+            Self::Equal(a, b) => Self::Equal(f(a), f(b)),
+            Self::NotEqual(a, b) => Self::NotEqual(f(a), f(b)),
+            Self::Greater(a, b) => Self::Greater(f(a), f(b)),
+            Self::GreaterEqual(a, b) => Self::GreaterEqual(f(a), f(b)),
+            Self::Less(a, b) => Self::Less(f(a), f(b)),
+            Self::LessEqual(a, b) => Self::LessEqual(f(a), f(b)),
+
+            Self::Negate(a) => Self::Negate(f(a)),
+            Self::Add(a, b) => Self::Add(f(a), f(b)),
+            Self::Multiply(a, b) => Self::Multiply(f(a), f(b)),
+            Self::Divide(a, b) => Self::Divide(f(a), f(b)),
+            Self::Subtract(a, b) => Self::Subtract(f(a), f(b)),
+
+            Self::IfThenElse {
+                condition,
+                then_case,
+                else_case,
+            } => Self::IfThenElse {
+                condition: f(condition),
+                then_case: f(then_case),
+                else_case: f(else_case),
+            },
+            Self::CaseOf {
+                data_type,
+                value,
+                cases,
+            } => Self::CaseOf {
+                data_type: data_type.clone(),
+                value: f(value),
+                cases: cases
+                    .iter()
+                    .map(|(cons_name, members, case_body)| {
+                        (cons_name.clone(), members.clone(), f(case_body))
+                    })
+                    .collect(),
+            },
+            Self::Match { values, arms } => Self::Match {
+                values: values.iter().map(&mut f).collect(),
+                arms: arms
+                    .iter()
+                    .map(|(patterns, guard, body)| {
+                        (patterns.clone(), guard.as_ref().map(&mut f), f(body))
+                    })
+                    .collect(),
+            },
+            Self::Construct {
+                data_type,
+                cons_name,
+                members,
+            } => Self::Construct {
+                data_type: data_type.clone(),
+                cons_name: cons_name.clone(),
+                members: members.iter().map(f).collect(),
+            },
+            Self::Deconstruct {
+                data_type,
+                cons_name,
+                members,
+                value,
+                body,
+            } => Self::Deconstruct {
+                data_type: data_type.clone(),
+                cons_name: cons_name.clone(),
+                members: members.clone(),
+                value: f(value),
+                body: f(body),
+            },
+            Self::Let { name, value, body } => Self::Let {
+                name: name.clone(),
+                value: f(value),
+                body: f(body),
+            },
+
+            Self::Record(cons_name, fields) => Self::Record(
+                cons_name.clone(),
+                fields
+                    .iter()
+                    .map(|(field, value)| (field.clone(), f(value)))
+                    .collect(),
+            ),
+            Self::Field(a, field) => Self::Field(f(a), field.clone()),
+            Self::Update(a, fields) => {
+                let a = f(a);
+                Self::Update(
+                    a,
+                    fields
+                        .iter()
+                        .map(|(field, value)| (field.clone(), f(value)))
+                        .collect(),
+                )
+            }
+            Self::Dotted { field, lambda } => Self::Dotted {
+                field: f(field),
+                lambda: f(lambda),
+            },
+
+            Self::Located(span, a) => Self::Located(*span, f(a)),
+            Self::Annotated(a, t) => Self::Annotated(f(a), t.clone()),
+            Self::Application(a, b) => Self::Application(f(a), f(b)),
+            Self::Lambda(arg, body) => Self::Lambda(arg.clone(), f(body)),
+            Self::TailCall(args) => Self::TailCall(args.iter().map(f).collect()),
+
+            Self::Table(map) => Self::Table(map.iter().map(|(k, v)| (k.clone(), f(v))).collect()),
+            Self::List(items) => Self::List(items.iter().map(f).collect()),
+
+            Self::Identifier(_) | Self::Hole(_) | Self::Number(_) | Self::String(_) => self.clone(),
+        }
+    }
+
     pub fn number_of_arguments(&self) -> i32 {
         match self {
             Self::Lambda(_, b) => b.number_of_arguments() + 1,
             Self::Application(a, _) => {
-                if let Self::Lambda(_, _) = (**a).clone() {
+                if let Self::Lambda(_, _) = a.unlocated() {
                     a.number_of_arguments() - 1
                 } else {
                     0
                 }
             }
+            Self::Located(_, a) | Self::Annotated(a, _) => a.number_of_arguments(),
             _ => 0,
         }
     }
 
+    /// Decide whether each dotted expression reads a field or is a lambda,
+    /// given the variables in scope
+    pub fn resolve_dotted(&self, scope: &mut Vec<String>) -> Rc<Self> {
+        let mut within = |names: Vec<&String>, child: &Rc<Self>| {
+            let len = scope.len();
+            scope.extend(names.into_iter().cloned());
+            let result = child.resolve_dotted(scope);
+            scope.truncate(len);
+            result
+        };
+        Rc::new(match self {
+            Self::Dotted { field, lambda } => {
+                return match lambda.unlocated() {
+                    Self::Lambda(parameter, _) if scope.contains(parameter) => {
+                        field.resolve_dotted(scope)
+                    }
+                    _ => lambda.resolve_dotted(scope),
+                };
+            }
+            Self::Lambda(parameter, body) => {
+                Self::Lambda(parameter.clone(), within(vec![parameter], body))
+            }
+            Self::Let { name, value, body } => Self::Let {
+                name: name.clone(),
+                value: within(Vec::new(), value),
+                body: within(vec![name], body),
+            },
+            Self::Deconstruct {
+                data_type,
+                cons_name,
+                members,
+                value,
+                body,
+            } => Self::Deconstruct {
+                data_type: data_type.clone(),
+                cons_name: cons_name.clone(),
+                members: members.clone(),
+                value: within(Vec::new(), value),
+                body: within(members.iter().map(Binder::get_name).collect(), body),
+            },
+            Self::CaseOf {
+                data_type,
+                value,
+                cases,
+            } => Self::CaseOf {
+                data_type: data_type.clone(),
+                value: within(Vec::new(), value),
+                cases: cases
+                    .iter()
+                    .map(|(cons_name, members, body)| {
+                        let names = members.iter().map(Binder::get_name).collect();
+                        (cons_name.clone(), members.clone(), within(names, body))
+                    })
+                    .collect(),
+            },
+            Self::Match { values, arms } => Self::Match {
+                values: values
+                    .iter()
+                    .map(|value| within(Vec::new(), value))
+                    .collect(),
+                arms: arms
+                    .iter()
+                    .map(|(patterns, guard, body)| {
+                        let names = || {
+                            patterns
+                                .iter()
+                                .flat_map(Pattern::variables)
+                                .map(Binder::get_name)
+                                .collect()
+                        };
+                        let guard = guard.as_ref().map(|guard| within(names(), guard));
+                        (patterns.clone(), guard, within(names(), body))
+                    })
+                    .collect(),
+            },
+            other => other.map_children(|child| child.resolve_dotted(scope)),
+        })
+    }
+
     pub fn has_binding(&self, name: &String) -> bool {
         match self {
             Self::And(a, b) => a.has_binding(name) || b.has_binding(name),
@@ -437,13 +1620,28 @@ impl Expression {
                     || else_case.has_binding(name)
             }
             Self::CaseOf { value, cases, .. } => {
-                for (_, _, case_body) in (*cases).clone() {
-                    if case_body.has_binding(name) {
+                for (_, members, case_body) in cases {
+                    // Members of a case shadow the name in its body
+                    if !members.iter().any(|member| **member == *name)
+                        && case_body.has_binding(name)
+                    {
                         return true;
                     }
                 }
                 value.has_binding(name)
             }
+            Self::Match { values, arms } => {
+                values.iter().any(|value| value.has_binding(name))
+                    || arms.iter().any(|(patterns, guard, body)| {
+                        let shadowed = patterns
+                            .iter()
+                            .flat_map(Pattern::variables)
+                            .any(|variable| **variable == *name);
+                        !shadowed
+                            && (guard.as_ref().is_some_and(|guard| guard.has_binding(name))
+                                || body.has_binding(name))
+                    })
+            }
             Self::Construct { members, .. } => {
                 for member in (*members).clone() {
                     if member.has_binding(name) {
@@ -452,11 +1650,28 @@ impl Expression {
                 }
                 false
             }
-            Self::Deconstruct { value, body, .. } => {
-                body.has_binding(name) || value.has_binding(name)
+            Self::Deconstruct {
+                members,
+                value,
+                body,
+                ..
+            } => {
+                value.has_binding(name)
+                    || (!members.iter().any(|member| **member == *name) && body.has_binding(name))
+            }
+            Self::Let {
+                name: binder,
+                value,
+                body,
+            } => value.has_binding(name) || (**binder != *name && body.has_binding(name)),
+            Self::Record(_, fields) => fields.iter().any(|(_, field)| field.has_binding(name)),
+            Self::Field(a, _) => a.has_binding(name),
+            Self::Dotted { field, lambda } => field.has_binding(name) || lambda.has_binding(name),
+            Self::Update(a, fields) => {
+                a.has_binding(name) || fields.iter().any(|(_, field)| field.has_binding(name))
             }
 
-            Self::Lambda(_, b) => b.has_binding(name),
+            Self::Lambda(arg, b) => **arg != *name && b.has_binding(name),
             Self::Application(a, b) => a.has_binding(name) || b.has_binding(name),
 
             Self::Table(map) => {
@@ -478,6 +1693,8 @@ impl Expression {
             }
 
             Self::Identifier(n) => n == name,
+            Self::Located(_, a) => a.has_binding(name),
+            Self::Annotated(a, _) => a.has_binding(name),
             Self::TailCall(items) => {
                 for item in items {
                     if item.has_binding(name) {
@@ -491,148 +1708,124 @@ impl Expression {
         }
     }
 
-    pub fn resolve_tailcall(&self, is_head: bool) -> Rc<Self> {
+    /// Replace each `rec` call with an application of the `rec` variable,
+    /// which the fixpoint of the function is bound to
+    pub fn resolve_tailcall(&self) -> Rc<Self> {
         if !self.is_recursive() {
             return Rc::new(self.clone());
         }
 
-        if is_head {
-            let result = Rc::new(Self::Application(
-                Rc::new(Self::y_combinator()),
-                Rc::new(Self::Lambda(
-                    String::from(Self::RECURSION_ARGUMENT),
-                    self.resolve_tailcall(false),
-                )),
-            ));
-            result
-        } else {
-            println!("  => {:?}", self);
-            Rc::new(match self {
-                Self::And(a, b) => Self::And(a.resolve_tailcall(false), b.resolve_tailcall(false)),
-                Self::Or(a, b) => Self::Or(a.resolve_tailcall(false), b.resolve_tailcall(false)),
-                Self::Not(a) => Self::Not(a.resolve_tailcall(false)),
-                Self::Equal(a, b) => {
-                    Self::Equal(a.resolve_tailcall(false), b.resolve_tailcall(false))
-                }
-                Self::NotEqual(a, b) => {
-                    Self::NotEqual(a.resolve_tailcall(false), b.resolve_tailcall(false))
-                }
-                Self::Greater(a, b) => {
-                    Self::Greater(a.resolve_tailcall(false), b.resolve_tailcall(false))
-                }
-                Self::GreaterEqual(a, b) => {
-                    Self::GreaterEqual(a.resolve_tailcall(false), b.resolve_tailcall(false))
-                }
-                Self::Less(a, b) => {
-                    Self::Less(a.resolve_tailcall(false), b.resolve_tailcall(false))
-                }
-                Self::LessEqual(a, b) => {
-                    Self::LessEqual(a.resolve_tailcall(false), b.resolve_tailcall(false))
-                }
-                Self::Negate(a) => Self::Negate(a.resolve_tailcall(false)),
-                Self::Add(a, b) => Self::Add(a.resolve_tailcall(false), b.resolve_tailcall(false)),
-                Self::Multiply(a, b) => {
-                    Self::Multiply(a.resolve_tailcall(false), b.resolve_tailcall(false))
-                }
-                Self::Divide(a, b) => {
-                    Self::Divide(a.resolve_tailcall(false), b.resolve_tailcall(false))
-                }
-                Self::Subtract(a, b) => {
-                    Self::Subtract(a.resolve_tailcall(false), b.resolve_tailcall(false))
+        Rc::new(match self {
+            Self::And(a, b) => Self::And(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::Or(a, b) => Self::Or(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::Not(a) => Self::Not(a.resolve_tailcall()),
+            Self::Equal(a, b) => Self::Equal(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::NotEqual(a, b) => Self::NotEqual(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::Greater(a, b) => Self::Greater(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::GreaterEqual(a, b) => {
+                Self::GreaterEqual(a.resolve_tailcall(), b.resolve_tailcall())
+            }
+            Self::Less(a, b) => Self::Less(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::LessEqual(a, b) => Self::LessEqual(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::Negate(a) => Self::Negate(a.resolve_tailcall()),
+            Self::Add(a, b) => Self::Add(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::Multiply(a, b) => Self::Multiply(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::Divide(a, b) => Self::Divide(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::Subtract(a, b) => Self::Subtract(a.resolve_tailcall(), b.resolve_tailcall()),
+            Self::IfThenElse {
+                condition,
+                then_case,
+                else_case,
+            } => Self::IfThenElse {
+                condition: condition.resolve_tailcall(),
+                then_case: then_case.resolve_tailcall(),
+                else_case: else_case.resolve_tailcall(),
+            },
+            Self::CaseOf {
+                value: v,
+                cases,
+                data_type,
+            } => {
+                let mut result = Vec::new();
+                for (a, b, case_body) in (*cases).clone() {
+                    result.push((a, b, case_body.resolve_tailcall()));
                 }
-                Self::IfThenElse {
-                    condition,
-                    then_case,
-                    else_case,
-                } => Self::IfThenElse {
-                    condition: condition.resolve_tailcall(false),
-                    then_case: then_case.resolve_tailcall(false),
-                    else_case: else_case.resolve_tailcall(false),
-                },
                 Self::CaseOf {
-                    value: v,
-                    cases,
-                    data_type,
-                } => {
-                    let mut result = Vec::new();
-                    for (a, b, case_body) in (*cases).clone() {
-                        result.push((a, b, case_body.resolve_tailcall(false)));
-                    }
-                    Self::CaseOf {
-                        data_type: (*data_type).clone(),
-                        value: v.resolve_tailcall(false),
-                        cases: result,
-                    }
+                    data_type: (*data_type).clone(),
+                    value: v.resolve_tailcall(),
+                    cases: result,
                 }
-                Self::Construct {
-                    cons_name,
-                    members,
-                    data_type,
-                } => {
-                    let mut result = Vec::new();
-                    for member in (*members).clone() {
-                        result.push(member.resolve_tailcall(false));
-                    }
-                    Self::Construct {
-                        data_type: (*data_type).clone(),
-                        cons_name: cons_name.clone(),
-                        members: result,
-                    }
+            }
+            Self::Construct {
+                cons_name,
+                members,
+                data_type,
+            } => {
+                let mut result = Vec::new();
+                for member in (*members).clone() {
+                    result.push(member.resolve_tailcall());
                 }
-                Self::Deconstruct {
-                    cons_name,
-                    members,
-                    data_type,
-                    body,
-                    value,
-                } => Self::Deconstruct {
+                Self::Construct {
                     data_type: (*data_type).clone(),
                     cons_name: cons_name.clone(),
-                    members: members.clone(),
-                    value: value.resolve_tailcall(false),
-                    body: body.resolve_tailcall(false),
-                },
-                // Self::Lambda(_, _) => Self::Application(
-                //     Rc::new(Self::Identifier(String::from("Y"))),
-                //     Rc::new(Self::Lambda(
-                //         String::from(Self::RECURSION_ARGUMENT),
-                //         self.resolve_tailcall(false),
-                //     )),
-                // ),
-                Self::Lambda(a, b) => Self::Lambda(a.clone(), b.resolve_tailcall(false)),
-                Self::Application(a, b) => {
-                    Self::Application(a.resolve_tailcall(false), b.resolve_tailcall(false))
+                    members: result,
                 }
-                Self::Table(map) => {
-                    let mut t = BTreeMap::new();
-                    for (k, v) in (*map).clone() {
-                        t.insert(k, v.resolve_tailcall(false));
-                    }
-                    Self::Table(t)
+            }
+            Self::Deconstruct {
+                cons_name,
+                members,
+                data_type,
+                body,
+                value,
+            } => Self::Deconstruct {
+                data_type: (*data_type).clone(),
+                cons_name: cons_name.clone(),
+                members: members.clone(),
+                value: value.resolve_tailcall(),
+                body: body.resolve_tailcall(),
+            },
+            // Self::Lambda(_, _) => Self::Application(
+            //     Rc::new(Self::Identifier(String::from("Y"))),
+            //     Rc::new(Self::Lambda(
+            //         String::from(Self::RECURSION_ARGUMENT),
+            //         self.resolve_tailcall(),
+            //     )),
+            // ),
+            Self::Lambda(a, b) => Self::Lambda(a.clone(), b.resolve_tailcall()),
+            Self::Application(a, b) => {
+                Self::Application(a.resolve_tailcall(), b.resolve_tailcall())
+            }
+            Self::Located(span, a) => Self::Located(*span, a.resolve_tailcall()),
+            Self::Annotated(a, t) => Self::Annotated(a.resolve_tailcall(), t.clone()),
+            Self::Table(map) => {
+                let mut t = BTreeMap::new();
+                for (k, v) in (*map).clone() {
+                    t.insert(k, v.resolve_tailcall());
                 }
-                Self::List(list) => {
-                    let mut result = Vec::new();
-                    for item in list {
-                        result.push(item.resolve_tailcall(false));
-                    }
-                    Self::List(result)
+                Self::Table(t)
+            }
+            Self::List(list) => {
+                let mut result = Vec::new();
+                for item in list {
+                    result.push(item.resolve_tailcall());
+                }
+                Self::List(result)
+            }
+            Self::TailCall(items) => {
+                let mut args = Vec::new();
+                for item in items {
+                    args.push(item.resolve_tailcall());
                 }
-                Self::TailCall(items) => {
-                    let mut args = Vec::new();
-                    for item in items {
-                        args.push(item.resolve_tailcall(false));
-                    }
-
-                    let mut result = Self::Identifier(String::from(Self::RECURSION_ARGUMENT));
-                    for arg in args {
-                        result = Self::Application(Rc::new(result), arg);
-                    }
 
-                    result
+                let mut result = Self::Identifier(String::from(Self::RECURSION_ARGUMENT));
+                for arg in args {
+                    result = Self::Application(Rc::new(result), arg);
                 }
-                _ => self.clone(),
-            })
-        }
+
+                result
+            }
+            _ => self.clone(),
+        })
     }
 
     pub fn replace_constant(&self, constant: &Constant) -> Rc<Self> {
@@ -753,6 +1946,8 @@ impl Expression {
             }
 
             Self::Identifier(name) if name.clone() == constant.name => constant.value.clone(),
+            Self::Located(span, a) => Self::Located(*span, a.replace_constant(constant)),
+            Self::Annotated(a, t) => Self::Annotated(a.replace_constant(constant), t.clone()),
             Self::TailCall(items) => {
                 let mut result = Vec::new();
                 for item in items {
@@ -863,8 +2058,8 @@ impl Expression {
 
             Self::Lambda(a, b) => Self::Lambda(a.clone(), b.replace_constructors(cons)),
             Self::Application(a, b) => {
-                if let Self::Identifier(name) = (**a).clone() {
-                    if cons.name == name && cons.members.len() == 1 {
+                if let Self::Identifier(name) = a.unlocated() {
+                    if &cons.name == name && cons.members.len() == 1 {
                         return Rc::new(Self::Construct {
                             data_type: Some(cons.clone()),
                             cons_name: cons.name.clone(),
@@ -903,6 +2098,8 @@ impl Expression {
                 }
                 Self::Identifier(name.clone())
             }
+            Self::Located(span, a) => Self::Located(*span, a.replace_constructors(cons)),
+            Self::Annotated(a, t) => Self::Annotated(a.replace_constructors(cons), t.clone()),
             Self::TailCall(items) => {
                 let mut result = Vec::new();
                 for item in items {
@@ -916,61 +2113,94 @@ impl Expression {
         })
     }
 
-    pub fn resolve_types(&self, data: &Vec<Data>, cons: &Vec<Constructor>) -> Rc<Self> {
+    pub fn resolve_types(
+        &self,
+        data: &Vec<Data>,
+        cons: &Vec<Constructor>,
+        diagnostics: &mut Diagnostics,
+    ) -> Rc<Self> {
         Rc::new(match self {
-            Self::And(a, b) => Self::And(a.resolve_types(data, cons), b.resolve_types(data, cons)),
-            Self::Or(a, b) => Self::Or(a.resolve_types(data, cons), b.resolve_types(data, cons)),
+            Self::And(a, b) => Self::And(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::Or(a, b) => Self::Or(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
 
-            Self::Not(a) => Self::Not(a.resolve_types(data, cons)),
+            Self::Not(a) => Self::Not(a.resolve_types(data, cons, diagnostics)),
 
-            Self::Equal(a, b) => {
-                Self::Equal(a.resolve_types(data, cons), b.resolve_types(data, cons))
-            }
-            Self::NotEqual(a, b) => {
-                Self::NotEqual(a.resolve_types(data, cons), b.resolve_types(data, cons))
-            }
-            Self::Greater(a, b) => {
-                Self::Greater(a.resolve_types(data, cons), b.resolve_types(data, cons))
-            }
-            Self::GreaterEqual(a, b) => {
-                Self::GreaterEqual(a.resolve_types(data, cons), b.resolve_types(data, cons))
-            }
-            Self::Less(a, b) => {
-                Self::Less(a.resolve_types(data, cons), b.resolve_types(data, cons))
-            }
-            Self::LessEqual(a, b) => {
-                Self::LessEqual(a.resolve_types(data, cons), b.resolve_types(data, cons))
-            }
+            Self::Equal(a, b) => Self::Equal(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::NotEqual(a, b) => Self::NotEqual(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::Greater(a, b) => Self::Greater(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::GreaterEqual(a, b) => Self::GreaterEqual(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::Less(a, b) => Self::Less(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::LessEqual(a, b) => Self::LessEqual(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
 
-            Self::Negate(a) => Self::Negate(a.resolve_types(data, cons)),
-            Self::Add(a, b) => Self::Add(a.resolve_types(data, cons), b.resolve_types(data, cons)),
-            Self::Multiply(a, b) => {
-                Self::Multiply(a.resolve_types(data, cons), b.resolve_types(data, cons))
-            }
-            Self::Divide(a, b) => {
-                Self::Divide(a.resolve_types(data, cons), b.resolve_types(data, cons))
-            }
-            Self::Subtract(a, b) => {
-                Self::Subtract(a.resolve_types(data, cons), b.resolve_types(data, cons))
-            }
+            Self::Negate(a) => Self::Negate(a.resolve_types(data, cons, diagnostics)),
+            Self::Add(a, b) => Self::Add(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::Multiply(a, b) => Self::Multiply(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::Divide(a, b) => Self::Divide(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::Subtract(a, b) => Self::Subtract(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
 
             Self::IfThenElse {
                 condition,
                 then_case,
                 else_case,
             } => Self::IfThenElse {
-                condition: condition.resolve_types(data, cons),
-                then_case: then_case.resolve_types(data, cons),
-                else_case: else_case.resolve_types(data, cons),
+                condition: condition.resolve_types(data, cons, diagnostics),
+                then_case: then_case.resolve_types(data, cons, diagnostics),
+                else_case: else_case.resolve_types(data, cons, diagnostics),
             },
             Self::CaseOf { value, cases, .. } => {
+                let mut result = Vec::new();
+                for (a, b, case_body) in cases {
+                    result.push((
+                        a.clone(),
+                        b.clone(),
+                        case_body.resolve_types(data, cons, diagnostics),
+                    ));
+                }
+                let value = value.resolve_types(data, cons, diagnostics);
+
+                // The number of members in each arm is checked before types are resolved
                 for d in data {
                     let mut check = false;
-                    for (name, members, _) in cases {
-                        let num_members = members.len();
+                    for (name, _, _) in cases {
                         check = false;
                         for con in d.get_constructors() {
-                            if con.get_name() == name && con.get_members().len() == num_members {
+                            if con.get_name() == name {
                                 check = true;
                             }
                         }
@@ -981,47 +2211,68 @@ impl Expression {
 
                     if check {
                         return Rc::new(Self::CaseOf {
-                            value: value.clone(),
-                            cases: cases.clone(),
+                            value,
+                            cases: result,
                             data_type: Some(d.clone()),
                         });
                     }
                 }
-                self.clone()
-                // let mut result = Vec::new();
-                // for (a, b, case_body) in (*cases).clone() {
-                //     result.push((a, b, case_body.resolve_types(data, cons)));
-                // }
-                // Self::CaseOf {
-                //     data_type: (*data_type).clone(),
-                //     value: v.resolve_types(data, cons),
-                //     cases: result,
-                // }
+
+                let names = cases
+                    .iter()
+                    .map(|(name, _, _)| format!("`{}`", name))
+                    .collect::<Vec<String>>();
+                diagnostics.error(
+                    UNRESOLVED_CASE,
+                    format!(
+                        "no data type has constructors matching every case: {}",
+                        names.join(", ")
+                    ),
+                );
+                Self::CaseOf {
+                    value,
+                    cases: result,
+                    data_type: None,
+                }
             }
             Self::Construct {
                 members, cons_name, ..
             } => {
+                let mut result = Vec::new();
+                for member in members {
+                    result.push(member.resolve_types(data, cons, diagnostics));
+                }
+
                 for con in cons {
-                    if cons_name == con.get_name() && con.get_members().len() == members.len() {
+                    if cons_name == con.get_name() {
                         return Rc::new(Self::Construct {
                             data_type: Some(con.clone()),
-                            members: members.clone(),
+                            members: result,
                             cons_name: cons_name.clone(),
                         });
                     }
                 }
                 for d in data {
                     for con in d.get_constructors() {
-                        if cons_name == con.get_name() && con.get_members().len() == members.len() {
+                        if cons_name == con.get_name() {
                             return Rc::new(Self::Construct {
                                 data_type: Some(con.clone()),
-                                members: members.clone(),
+                                members: result,
                                 cons_name: cons_name.clone(),
                             });
                         }
                     }
                 }
-                self.clone()
+
+                diagnostics.error(
+                    UNKNOWN_CONSTRUCTOR,
+                    format!("no constructor named `{}`", cons_name),
+                );
+                Self::Construct {
+                    data_type: None,
+                    members: result,
+                    cons_name: cons_name.clone(),
+                }
             }
             Self::Deconstruct {
                 data_type,
@@ -1033,29 +2284,29 @@ impl Expression {
                 data_type: (*data_type).clone(),
                 cons_name: cons_name.clone(),
                 members: members.clone(),
-                value: value.resolve_types(data, cons),
-                body: body.resolve_types(data, cons),
+                value: value.resolve_types(data, cons, diagnostics),
+                body: body.resolve_types(data, cons, diagnostics),
             },
 
-            Self::Lambda(a, b) => Self::Lambda(a.clone(), b.resolve_types(data, cons)),
-            Self::Application(a, b) => {
-                // if let Self::Identifier(name) = (**a).clone() {
-                //     if cons.name == name && cons.members.len() == 1 {
-                //         return Rc::new(Self::Construct {
-                //             data_type: Some(cons.clone()),
-                //             cons_name: cons.name.clone(),
-                //             members: vec![b.clone()],
-                //         });
-                //     }
-                // }
-
-                Self::Application(a.resolve_types(data, cons), b.resolve_types(data, cons))
+            Self::Lambda(a, b) => Self::Lambda(a.clone(), b.resolve_types(data, cons, diagnostics)),
+            Self::Application(a, b) => Self::Application(
+                a.resolve_types(data, cons, diagnostics),
+                b.resolve_types(data, cons, diagnostics),
+            ),
+            Self::Located(span, a) => {
+                let from = diagnostics.len();
+                let result = a.resolve_types(data, cons, diagnostics);
+                diagnostics.place(from, *span);
+                Self::Located(*span, result)
+            }
+            Self::Annotated(a, t) => {
+                Self::Annotated(a.resolve_types(data, cons, diagnostics), t.clone())
             }
 
             Self::Table(map) => {
                 let mut t = BTreeMap::new();
                 for (k, v) in (*map).clone() {
-                    t.insert(k, v.resolve_types(data, cons));
+                    t.insert(k, v.resolve_types(data, cons, diagnostics));
                 }
                 Self::Table(t)
             }
@@ -1063,7 +2314,7 @@ impl Expression {
             Self::List(list) => {
                 let mut result = Vec::new();
                 for item in list {
-                    result.push(item.resolve_types(data, cons));
+                    result.push(item.resolve_types(data, cons, diagnostics));
                 }
 
                 Self::List(result)
@@ -1072,7 +2323,7 @@ impl Expression {
             Self::TailCall(items) => {
                 let mut result = Vec::new();
                 for item in items {
-                    result.push(item.resolve_types(data, cons));
+                    result.push(item.resolve_types(data, cons, diagnostics));
                 }
 
                 Self::TailCall(result)
@@ -1107,6 +2358,13 @@ impl Expression {
                 then_case,
                 else_case,
             } => condition.is_recursive() || then_case.is_recursive() || else_case.is_recursive(),
+            Self::Match { values, arms } => {
+                values.iter().any(|value| value.is_recursive())
+                    || arms.iter().any(|(_, guard, body)| {
+                        guard.as_ref().is_some_and(|guard| guard.is_recursive())
+                            || body.is_recursive()
+                    })
+            }
             Self::CaseOf { value, cases, .. } => {
                 for (_, _, case_body) in cases {
                     if case_body.is_recursive() {
@@ -1124,8 +2382,18 @@ impl Expression {
                 }
                 false
             }
-            Self::Deconstruct { value, body, .. } => value.is_recursive() || body.is_recursive(),
+            Self::Deconstruct { value, body, .. } | Self::Let { value, body, .. } => {
+                value.is_recursive() || body.is_recursive()
+            }
+            Self::Record(_, fields) => fields.iter().any(|(_, field)| field.is_recursive()),
+            Self::Field(a, _) => a.is_recursive(),
+            Self::Dotted { field, lambda } => field.is_recursive() || lambda.is_recursive(),
+            Self::Update(a, fields) => {
+                a.is_recursive() || fields.iter().any(|(_, field)| field.is_recursive())
+            }
             Self::Lambda(_, a) => a.is_recursive(),
+            Self::Located(_, a) => a.is_recursive(),
+            Self::Annotated(a, _) => a.is_recursive(),
             Self::Application(a, b) => a.is_recursive() || b.is_recursive(),
             Self::TailCall(_) => true,
             _ => false,
@@ -1136,7 +2404,9 @@ impl Expression {
 impl Debug for Expression {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         match self {
-            Self::Identifier(i) => write!(f, "{}", i),
+            Self::Identifier(i) | Self::Hole(i) => write!(f, "{}", i),
+            Self::Located(_, a) => write!(f, "{:?}", a),
+            Self::Annotated(a, t) => write!(f, "({:?} : {})", a, t),
 
             Self::And(a, b) => write!(f, "({:?} && {:?})", a, b),
             Self::Or(a, b) => write!(f, "({:?} || {:?})", a, b),
@@ -1176,6 +2446,24 @@ impl Debug for Expression {
                 }
                 Ok(())
             }
+            Self::Match { values, arms } => {
+                write!(f, "case")?;
+                for value in values {
+                    write!(f, " ({:?})", value)?;
+                }
+                write!(f, " of")?;
+                for (patterns, guard, body) in arms {
+                    write!(f, "\n\t|")?;
+                    for pattern in patterns {
+                        write!(f, " {}", pattern)?;
+                    }
+                    if let Some(guard) = guard {
+                        write!(f, " if {:?}", guard)?;
+                    }
+                    write!(f, " => {:?}", body)?;
+                }
+                Ok(())
+            }
 
             // Point(1, 2)
             Self::Construct {
@@ -1201,6 +2489,25 @@ impl Debug for Expression {
                 }
                 write!(f, ") = {:?} in ({:?})", value, body)
             }
+            Self::Let { name, value, body } => {
+                write!(f, "let {} = {:?} in ({:?})", name, value, body)
+            }
+            Self::Record(cons_name, fields) => {
+                write!(f, "{} {{ ", cons_name)?;
+                for (field, value) in fields {
+                    write!(f, "{} = {:?}, ", field, value)?;
+                }
+                write!(f, "}}")
+            }
+            Self::Field(a, field) => write!(f, "{:?}.{}", a, field),
+            Self::Dotted { lambda, .. } => write!(f, "{:?}", lambda),
+            Self::Update(a, fields) => {
+                write!(f, "{:?} {{ ", a)?;
+                for (field, value) in fields {
+                    write!(f, "{} = {:?}, ", field, value)?;
+                }
+                write!(f, "}}")
+            }
 
             Self::Lambda(a, b) => {
                 if let Self::Lambda(_, _) = (**b).clone() {
@@ -1,44 +1,145 @@
-use ramify::{parse_ast, Golang, Reduce, Target};
+use ramify::{parse_ast, Code, Diagnostics, Golang, Level, LintLevel, LintLevels, Reduce, Target};
 
 use comment::shell::strip;
 
 use std::{
     env::consts::EXE_SUFFIX,
     fs::{read_to_string, remove_file, write},
-    path::PathBuf,
-    process::Command,
+    path::{Path, PathBuf},
+    process::{exit, Command},
 };
 
 use asciicolor::Colorize;
-use clap::{clap_app, crate_authors, crate_version, AppSettings::ArgRequiredElseHelp};
+use clap::{clap_app, crate_authors, crate_version, AppSettings::ArgRequiredElseHelp, ArgMatches};
 
-const OUTPUT_FILE: &'static str = "OUTPUT.go";
+const OUTPUT_FILE: &str = "OUTPUT.go";
+const MANIFEST_FILE: &str = "ramify.toml";
+
+/// Find the project manifest in the directory of the input file, or in any of its parents
+fn find_manifest(input_file: &str) -> Option<PathBuf> {
+    let path = Path::new(input_file).canonicalize().ok()?;
+    path.ancestors()
+        .skip(1)
+        .map(|dir| dir.join(MANIFEST_FILE))
+        .find(|manifest| manifest.is_file())
+}
+
+/// Read the lint levels from the project manifest, overridden by the command line flags
+fn lint_levels(input_file: &str, matches: &ArgMatches) -> Result<LintLevels, String> {
+    let mut levels = LintLevels::new();
+    if let Some(manifest) = find_manifest(input_file) {
+        let contents = read_to_string(&manifest).map_err(|e| e.to_string())?;
+        levels = LintLevels::from_manifest(&contents)
+            .map_err(|e| format!("{}: {}", manifest.display(), e))?;
+    }
+
+    for (flag, level) in &[
+        ("ALLOW", LintLevel::Allow),
+        ("WARN", LintLevel::Warn),
+        ("DENY", LintLevel::Deny),
+    ] {
+        for lint in matches.values_of(flag).into_iter().flatten() {
+            levels.set(lint, *level)?;
+        }
+    }
+    if matches.is_present("DENY_WARNINGS") {
+        levels.set_deny_warnings(true);
+    }
+
+    Ok(levels)
+}
+
+/// Print every diagnostic collected while compiling a file
+fn report(path: &str, source: &str, diagnostics: &Diagnostics) {
+    for diagnostic in diagnostics.iter() {
+        let level = match diagnostic.get_level() {
+            Level::Warning => diagnostic.get_level().yellow().bold(),
+            Level::Error => diagnostic.get_level().red().bold(),
+        };
+        eprintln!(
+            "{}{}: {}\n{}",
+            level,
+            format!("[{}]", diagnostic.get_code()).bold(),
+            diagnostic.get_message().bold(),
+            diagnostic.snippet(path, source)
+        );
+    }
+}
 
 fn main() {
     let matches = clap_app!(ramify =>
         (version: crate_version!())
         (author: crate_authors!())
         (about: "Compiles the ramify programming language")
-        (@arg FILE: +required "The input file to use")
+        (@arg FILE: "The input file to use")
         (@arg DEBUG: -d "Enable debugging")
+        (@arg EXPLAIN: --explain [CODE] "Explain a diagnostic code, such as R0102")
+        (@arg EMIT: --emit [KIND] possible_values(&["types"]) "Print the inferred types of every declaration instead of compiling")
+        (@arg ALLOW: -A [LINT] ... number_of_values(1) "Allow a lint")
+        (@arg WARN: -W [LINT] ... number_of_values(1) "Warn about a lint")
+        (@arg DENY: -D [LINT] ... number_of_values(1) "Report a lint as an error")
+        (@arg DENY_WARNINGS: --("deny-warnings") "Report every warning as an error")
     )
     .setting(ArgRequiredElseHelp)
     .get_matches();
 
+    if let Some(name) = matches.value_of("EXPLAIN") {
+        match Code::lookup(name) {
+            Some(code) => {
+                println!("{}: {}", code.get_name().bold(), code.get_title());
+                if let Some(lint) = code.get_lint() {
+                    println!("lint: {}", lint);
+                }
+                println!("\n{}", code.get_explanation());
+            }
+            None => {
+                eprintln!("error: no diagnostic has the code \"{}\"", name);
+                exit(1);
+            }
+        }
+        return;
+    }
+
     // println!("Using input file: {}", matches.value_of("INPUT").unwrap());
     if let Some(input_file) = matches.value_of("FILE") {
+        let levels = match lint_levels(input_file, &matches) {
+            Ok(levels) => levels,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                exit(1);
+            }
+        };
+
         if let Ok(file_contents) = read_to_string(input_file) {
             let input = strip(file_contents).unwrap();
             // let ast = ast.unwrap().1;
             match parse_ast(&input) {
+                Ok((_, ast)) if matches.value_of("EMIT") == Some("types") => {
+                    let mut diagnostics = Diagnostics::with_levels(levels);
+                    let signatures = ast.infer_types(&mut diagnostics);
+                    report(input_file, &input, &diagnostics);
+                    for data in ast.get_data() {
+                        println!("{}", data);
+                    }
+                    for (name, scheme) in signatures {
+                        println!("{} : {}", name, scheme);
+                    }
+                    if diagnostics.has_errors() {
+                        exit(1);
+                    }
+                }
                 Ok((_, ast)) => {
-                    // let result = ast.reduce(&()).unwrap().to_combinator();
-                    match ast.reduce(&()) {
-                        Ok(result) => {
+                    let mut diagnostics = Diagnostics::with_levels(levels);
+                    let reduced = ast.reduce(&(), &mut diagnostics);
+                    report(input_file, &input, &diagnostics);
+
+                    match reduced {
+                        Ok(result) if !diagnostics.has_errors() => {
                             let result = result.to_combinator();
                             write(
                                 OUTPUT_FILE,
                                 String::from(include_str!("prelude.go"))
+                                    + "\t_ = "
                                     + &Golang.compile(result.clone()).unwrap()
                                     + "\n}",
                             )
@@ -48,9 +149,10 @@ fn main() {
                                 println!("compiled combinator: {}", result.green().underline());
                             }
                             let output_exe = &format!("main{}", EXE_SUFFIX);
-                            if let Err(_) = Command::new("go")
-                                .args(&["build", "-o", &output_exe, OUTPUT_FILE])
+                            if Command::new("go")
+                                .args(["build", "-o", output_exe, OUTPUT_FILE])
                                 .output()
+                                .is_err()
                             {
                                 eprintln!("error: go compiler not installed");
                             }
@@ -59,19 +161,32 @@ fn main() {
                             if PathBuf::from(output_exe).exists() {
                                 println!("compilation successful");
                             } else {
-                                eprintln!("compilation unsuccessful")
+                                eprintln!("compilation unsuccessful");
+                                exit(1);
                             }
                         }
-                        Err(err) => eprintln!("compilation error: \"{:?}\"", err),
+                        Err(err) if !diagnostics.has_errors() => {
+                            eprintln!("compilation error: \"{:?}\"", err);
+                            exit(1);
+                        }
+                        _ => {
+                            eprintln!("compilation unsuccessful");
+                            exit(1);
+                        }
                     }
                 }
-                Err(error) => eprintln!("syntax error: \"{}\"", error),
+                Err(error) => {
+                    eprintln!("syntax error: \"{}\"", error);
+                    exit(1);
+                }
             }
         } else {
             eprintln!("error: input file \"{}\" doesn't exist", input_file);
+            exit(1);
         }
     } else {
         eprintln!("error: no input file given");
+        exit(1);
     }
 
     // let f = Lambda::lambda(
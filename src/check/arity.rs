@@ -0,0 +1,193 @@
+use crate::{
+    ast::{Expression, Pattern, AST},
+    code::WRONG_MEMBER_COUNT,
+    diagnostic::{Diagnostic, Diagnostics},
+};
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+};
+
+/// Report every constructor that is constructed, deconstructed, or matched
+/// with a different number of members than it was declared with
+pub(crate) fn check_member_counts(ast: &AST, diagnostics: &mut Diagnostics) {
+    let mut constructors = BTreeMap::new();
+    for data in ast.get_data() {
+        for cons in &data.cons {
+            constructors.insert(cons.get_name().to_string(), cons.get_members().len());
+        }
+    }
+    for cons in ast.get_constructors() {
+        constructors.insert(cons.get_name().to_string(), cons.get_members().len());
+    }
+
+    for constant in ast.get_constants() {
+        check_expression(constant.get_body(), &constructors, diagnostics);
+    }
+    for function in ast.get_functions() {
+        check_expression(function.get_body(), &constructors, diagnostics);
+    }
+    for method in ast.get_instance_methods() {
+        check_expression(method.get_body(), &constructors, diagnostics);
+    }
+}
+
+/// Describe a number of members, such as "1 member" or "2 members"
+fn members(n: usize) -> String {
+    if n == 1 {
+        String::from("1 member")
+    } else {
+        format!("{} members", n)
+    }
+}
+
+/// Describe a constructor used with `found` members, if it is declared with a different number.
+/// Unknown constructors are reported when types are resolved.
+fn check_count(
+    cons_name: &str,
+    found: usize,
+    constructors: &BTreeMap<String, usize>,
+) -> Option<Diagnostic> {
+    let expected = *constructors.get(cons_name)?;
+    if expected == found {
+        return None;
+    }
+
+    let given = if found == 1 {
+        String::from("1 was")
+    } else {
+        format!("{} were", found)
+    };
+    Some(Diagnostic::error(
+        WRONG_MEMBER_COUNT,
+        format!(
+            "`{}` has {}, but {} given",
+            cons_name,
+            members(expected),
+            given
+        ),
+    ))
+}
+
+/// Report every constructor in a nested pattern with the wrong number of members
+fn check_pattern(
+    pattern: &Pattern,
+    body: &Expression,
+    constructors: &BTreeMap<String, usize>,
+    diagnostics: &mut Diagnostics,
+) {
+    match pattern {
+        Pattern::Constructor(cons_name, members) => {
+            if let Some(mut diagnostic) = check_count(cons_name, members.len(), constructors) {
+                if let Some(span) = body.get_span() {
+                    diagnostic = diagnostic.with_span(span);
+                }
+                diagnostics.push(diagnostic);
+            }
+            for member in members {
+                check_pattern(member, body, constructors, diagnostics);
+            }
+        }
+        Pattern::As(_, inner) => check_pattern(inner, body, constructors, diagnostics),
+        _ => {}
+    }
+}
+
+fn check_expression(
+    expr: &Expression,
+    constructors: &BTreeMap<String, usize>,
+    diagnostics: &mut Diagnostics,
+) {
+    match expr {
+        Expression::Construct {
+            cons_name, members, ..
+        } => {
+            if let Some(diagnostic) = check_count(cons_name, members.len(), constructors) {
+                diagnostics.push(diagnostic);
+            }
+        }
+        Expression::Deconstruct {
+            cons_name, members, ..
+        } => {
+            if let Some(diagnostic) = check_count(cons_name, members.len(), constructors) {
+                diagnostics.push(diagnostic);
+            }
+        }
+        Expression::CaseOf { cases, .. } => {
+            // Arms do not record where their pattern was written, so point at their body
+            for (cons_name, members, body) in cases {
+                if let Some(mut diagnostic) = check_count(cons_name, members.len(), constructors) {
+                    if let Some(span) = body.get_span() {
+                        diagnostic = diagnostic.with_span(span);
+                    }
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+        Expression::Match { arms, .. } => {
+            for (patterns, _, body) in arms {
+                for pattern in patterns {
+                    check_pattern(pattern, body, constructors, diagnostics);
+                }
+            }
+        }
+        Expression::Located(span, a) => {
+            let from = diagnostics.len();
+            check_expression(a, constructors, diagnostics);
+            diagnostics.place(from, *span);
+            return;
+        }
+        _ => {}
+    }
+
+    for child in expr.children() {
+        check_expression(child, constructors, diagnostics);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{check::testing::parse, diagnostic::Diagnostics};
+    use alloc::{string::String, vec::Vec};
+
+    /// The messages of the diagnostics reported for the member counts in a program
+    fn check(source: &str) -> Vec<String> {
+        let source = format!("data Shape = Circle(Num) | Rectangle(Num, Num)\n{}", source);
+        let mut diagnostics = Diagnostics::new();
+        super::check_member_counts(&parse(&source), &mut diagnostics);
+        diagnostics
+            .iter()
+            .map(|diagnostic| String::from(diagnostic.get_message()))
+            .collect()
+    }
+
+    #[test]
+    fn constructions() {
+        assert!(check("let main _ = print (Rectangle(1, 2))").is_empty());
+        assert_eq!(
+            check("let main _ = print (Rectangle(1))"),
+            ["`Rectangle` has 2 members, but 1 was given"]
+        );
+    }
+
+    #[test]
+    fn deconstructions() {
+        assert_eq!(
+            check("let width s = let Rectangle(w, h, d) = s in w"),
+            ["`Rectangle` has 2 members, but 3 were given"]
+        );
+    }
+
+    #[test]
+    fn case_arms() {
+        assert_eq!(
+            check(
+                "let area s = case s of\n\
+                 | Circle(r, extra) => r * r\n\
+                 | Rectangle(w, h) => w * h"
+            ),
+            ["`Circle` has 1 member, but 2 were given"]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    ast::AST,
+    code::{Code, UNKNOWN_LINT},
+    diagnostic::{Diagnostic, Diagnostics, LintLevel, Span},
+};
+
+use alloc::{string::String, vec::Vec};
+
+/// Apply the lint levels set by attributes to the declarations they are written on
+pub(crate) fn check_attributes(ast: &AST, diagnostics: &mut Diagnostics) {
+    for data in ast.get_data() {
+        set_levels(data.get_lint_levels(), data.get_span(), diagnostics);
+    }
+    for constant in ast.get_constants() {
+        set_levels(constant.get_lint_levels(), constant.get_span(), diagnostics);
+    }
+    for function in ast.get_functions() {
+        set_levels(function.get_lint_levels(), function.get_span(), diagnostics);
+    }
+    for method in ast.get_instance_methods() {
+        set_levels(method.get_lint_levels(), method.get_span(), diagnostics);
+    }
+}
+
+fn set_levels(
+    levels: &Vec<(String, LintLevel)>,
+    span: Option<Span>,
+    diagnostics: &mut Diagnostics,
+) {
+    for (lint, level) in levels {
+        match Code::lookup(lint) {
+            Some(code) if code.get_lint().is_some() => {
+                if let Some(span) = span {
+                    diagnostics.set_level_within(span, code, *level);
+                }
+            }
+            found => {
+                let message = match found {
+                    Some(code) => format!("`{}` is an error, and cannot be allowed", code),
+                    None => format!("unknown lint `{}`", lint),
+                };
+                let mut diagnostic = Diagnostic::error(UNKNOWN_LINT, message);
+                if let Some(span) = span {
+                    diagnostic = diagnostic.with_span(span);
+                }
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        check::testing::compile,
+        code::{UNKNOWN_LINT, UNUSED_VARIABLE},
+    };
+
+    #[test]
+    fn attributes_set_levels_on_their_declaration() {
+        let source = "let helper x = 1\nlet main y = print (helper 2)";
+        assert_eq!(
+            compile(source),
+            (
+                Some("K(print(1.0))".to_string()),
+                vec![UNUSED_VARIABLE.get_name(), UNUSED_VARIABLE.get_name()]
+            )
+        );
+
+        let allowed = source.replace("let helper", "@allow(unused-variables)\nlet helper");
+        assert_eq!(
+            compile(&allowed),
+            (
+                Some("K(print(1.0))".to_string()),
+                vec![UNUSED_VARIABLE.get_name()]
+            )
+        );
+
+        let denied = source.replace("let main", "@deny(R0301)\nlet main");
+        assert_eq!(
+            compile(&denied),
+            (
+                None,
+                vec![UNUSED_VARIABLE.get_name(), UNUSED_VARIABLE.get_name()]
+            )
+        );
+    }
+
+    #[test]
+    fn only_lints_have_levels() {
+        assert_eq!(
+            compile("@allow(unbound-variables)\nlet main _ = print 1"),
+            (None, vec![UNKNOWN_LINT.get_name()])
+        );
+        assert_eq!(
+            compile("@allow(R0001)\nlet main _ = print 1"),
+            (None, vec![UNKNOWN_LINT.get_name()])
+        );
+    }
+}
@@ -0,0 +1,370 @@
+use super::patterns::Patterns;
+use crate::{
+    ast::{Arm, Binder, Data, Expression, Pattern, AST},
+    code::{DUPLICATE_DEFINITION, NON_EXHAUSTIVE_CASE, UNKNOWN_CONSTRUCTOR, UNREACHABLE_ARM},
+    diagnostic::{Diagnostic, Diagnostics, Span},
+};
+
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Report `case` expressions that do not match every constructor of their data type exactly once
+///
+/// The arms of a case are applied positionally to the Scott encoded value, so a
+/// missing or repeated arm shifts every arm after it.
+/// A case with nested patterns, or the clauses of a function, must match every value,
+/// and every arm must match some value.
+pub(crate) fn check_case_arms(ast: &AST, diagnostics: &mut Diagnostics) {
+    let patterns = Patterns::new(ast);
+    // The clauses of a function are reported where the function is declared
+    let check = |body: &Expression, span: Option<Span>, diagnostics: &mut Diagnostics| {
+        let from = diagnostics.len();
+        check_expression(body, ast, &patterns, diagnostics);
+        if let Some(span) = span {
+            diagnostics.place(from, span);
+        }
+    };
+    for constant in ast.get_constants() {
+        check(constant.get_body(), constant.get_span(), diagnostics);
+    }
+    for function in ast.get_functions() {
+        check(function.get_body(), function.get_span(), diagnostics);
+    }
+    for method in ast.get_instance_methods() {
+        check(method.get_body(), method.get_span(), diagnostics);
+    }
+}
+
+fn check_expression(
+    expr: &Expression,
+    ast: &AST,
+    patterns: &Patterns,
+    diagnostics: &mut Diagnostics,
+) {
+    match expr {
+        Expression::CaseOf { value, cases, .. } => {
+            check_expression(value, ast, patterns, diagnostics);
+            for (_, _, body) in cases {
+                check_expression(body, ast, patterns, diagnostics);
+            }
+            check_arms(cases, ast.get_data(), diagnostics);
+        }
+        Expression::Match { values, arms } => {
+            for value in values {
+                check_expression(value, ast, patterns, diagnostics);
+            }
+            for (_, guard, body) in arms {
+                if let Some(guard) = guard {
+                    check_expression(guard, ast, patterns, diagnostics);
+                }
+                check_expression(body, ast, patterns, diagnostics);
+            }
+            check_patterns(values, arms, ast, patterns, diagnostics);
+        }
+        Expression::Located(span, a) => {
+            let from = diagnostics.len();
+            check_expression(a, ast, patterns, diagnostics);
+            diagnostics.place(from, *span);
+        }
+        otherwise => {
+            for child in otherwise.children() {
+                check_expression(child, ast, patterns, diagnostics);
+            }
+        }
+    }
+}
+
+/// Report the unknown constructors in a pattern, and the variables it binds more than once
+fn check_pattern<'a>(
+    pattern: &'a Pattern,
+    ast: &AST,
+    bound: &mut Vec<&'a Binder>,
+    diagnostics: &mut Diagnostics,
+) -> bool {
+    match pattern {
+        Pattern::Constructor(cons_name, members) => {
+            let is_known = ast
+                .get_data()
+                .iter()
+                .flat_map(|data| data.cons.iter())
+                .chain(ast.get_constructors())
+                .any(|cons| cons.get_name() == cons_name);
+            if !is_known {
+                diagnostics.error(
+                    UNKNOWN_CONSTRUCTOR,
+                    format!("no constructor named `{}`", cons_name),
+                );
+            }
+            members.iter().fold(is_known, |is_known, member| {
+                check_pattern(member, ast, bound, diagnostics) && is_known
+            })
+        }
+        Pattern::Variable(name) | Pattern::As(name, _) => {
+            if bound
+                .iter()
+                .any(|other| other.get_name() == name.get_name())
+            {
+                let mut diagnostic = Diagnostic::error(
+                    DUPLICATE_DEFINITION,
+                    format!("`{}` is bound more than once in the same pattern", name),
+                );
+                if let Some(span) = name.get_span() {
+                    diagnostic = diagnostic.with_span(span);
+                }
+                diagnostics.push(diagnostic);
+            }
+            bound.push(name);
+            match pattern {
+                Pattern::As(_, inner) => check_pattern(inner, ast, bound, diagnostics),
+                _ => true,
+            }
+        }
+        Pattern::Wildcard | Pattern::Number(_) | Pattern::String(_) => true,
+    }
+}
+
+/// Report a case with nested patterns, or the clauses of a function, that do not match every value,
+/// and the arms that only match values an earlier arm matches
+fn check_patterns(
+    values: &[Rc<Expression>],
+    arms: &[Arm],
+    ast: &AST,
+    patterns: &Patterns,
+    diagnostics: &mut Diagnostics,
+) {
+    // The clauses of a function match its parameters, which are named by the compiler
+    let is_clauses = values.iter().any(
+        |value| matches!(value.unlocated(), Expression::Identifier(name) if name.starts_with('$')),
+    );
+    let arm = if is_clauses { "clause" } else { "arm" };
+
+    let mut is_known = true;
+    for (patterns, _, body) in arms {
+        let from = diagnostics.len();
+        let mut bound = Vec::new();
+        for pattern in patterns {
+            is_known &= check_pattern(pattern, ast, &mut bound, diagnostics);
+        }
+        if let Some(span) = body.get_span() {
+            diagnostics.place(from, span);
+        }
+    }
+    // A pattern with an unknown constructor could be meant to match anything
+    if !is_known {
+        return;
+    }
+
+    let compiled = patterns.compile(values.to_vec(), arms);
+    for ((_, _, body), reached) in arms.iter().zip(compiled.reached) {
+        if !reached {
+            let mut diagnostic = Diagnostic::error(
+                UNREACHABLE_ARM,
+                format!(
+                    "unreachable {}: the {}s before it match every value it matches",
+                    arm, arm
+                ),
+            );
+            if let Some(span) = body.get_span() {
+                diagnostic = diagnostic.with_span(span);
+            }
+            diagnostics.push(diagnostic);
+        }
+    }
+    if let Some(missing) = compiled.missing {
+        let missing = missing
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let message = if is_clauses {
+            format!("non-exhaustive clauses: no clause matches `{}`", missing)
+        } else {
+            format!("non-exhaustive case: `{}` is not matched", missing)
+        };
+        diagnostics.error(NON_EXHAUSTIVE_CASE, message);
+    }
+}
+
+fn check_arms(
+    cases: &[(String, Vec<Binder>, Rc<Expression>)],
+    data: &[Data],
+    diagnostics: &mut Diagnostics,
+) {
+    // Cases whose arms do not all belong to one data type are reported when types are resolved
+    let data_type = match cases.first().and_then(|(first, _, _)| {
+        data.iter()
+            .find(|d| d.cons.iter().any(|cons| cons.get_name() == first))
+    }) {
+        Some(data_type) => data_type,
+        None => return,
+    };
+    let is_constructor = |name: &String| data_type.cons.iter().any(|c| c.get_name() == name);
+    if !cases.iter().all(|(name, _, _)| is_constructor(name)) {
+        return;
+    }
+
+    for (i, (name, _, body)) in cases.iter().enumerate() {
+        let first = cases[..i].iter().find(|(other, _, _)| other == name);
+        if let Some((_, _, first_body)) = first {
+            let mut diagnostic = Diagnostic::error(
+                UNREACHABLE_ARM,
+                format!("unreachable arm: `{}` is already matched", name),
+            )
+            .with_note(
+                format!("`{}` is first matched here", name),
+                first_body.get_span(),
+            );
+            if let Some(span) = body.get_span() {
+                diagnostic = diagnostic.with_span(span);
+            }
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    let missing = data_type
+        .cons
+        .iter()
+        .filter(|cons| !cases.iter().any(|(name, _, _)| name == cons.get_name()))
+        .map(|cons| format!("`{}`", cons.get_name()))
+        .collect::<Vec<String>>();
+    if !missing.is_empty() {
+        let arms = if missing.len() == 1 { "an arm" } else { "arms" };
+        diagnostics.error(
+            NON_EXHAUSTIVE_CASE,
+            format!(
+                "non-exhaustive case: missing {} for {}",
+                arms,
+                missing.join(", ")
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{check::testing::parse, diagnostic::Diagnostics};
+    use alloc::{string::String, vec::Vec};
+
+    const DATA: &str = "data Maybe a = Just(a) | Nothing\n\
+                        data Result a e = Ok(a) | Err(e)\n";
+
+    /// The messages of the diagnostics reported for the arms of the cases in a program
+    fn check(source: &str) -> Vec<String> {
+        let mut diagnostics = Diagnostics::new();
+        super::check_case_arms(&parse(&format!("{}{}", DATA, source)), &mut diagnostics);
+        diagnostics
+            .iter()
+            .map(|diagnostic| String::from(diagnostic.get_message()))
+            .collect()
+    }
+
+    #[test]
+    fn exhaustive_cases() {
+        assert!(check(
+            "let value m = case m of\n\
+             | Just(Ok(x)) => x\n\
+             | Just(Err(_)) => 0\n\
+             | Nothing => 0"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn missing_constructors_are_listed() {
+        assert_eq!(
+            check(
+                "data Shape = Circle(Num) | Square(Num) | Triangle(Num, Num, Num)\n\
+                 let area s = case s of | Square(w) => w * w"
+            ),
+            ["non-exhaustive case: missing arms for `Circle`, `Triangle`"]
+        );
+    }
+
+    #[test]
+    fn repeated_constructors_are_unreachable() {
+        let mut diagnostics = Diagnostics::new();
+        let source = format!(
+            "{}let value m = case m of | Just(x) => x | Nothing => 0 | Just(y) => y",
+            DATA
+        );
+        super::check_case_arms(&parse(&source), &mut diagnostics);
+        let reported = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.get_message(), diagnostic.get_notes().len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reported,
+            [("unreachable arm: `Just` is already matched", 1)]
+        );
+    }
+
+    #[test]
+    fn missing_nested_constructor() {
+        assert_eq!(
+            check(
+                "let value m = case m of\n\
+                 | Just(Ok(x)) => x\n\
+                 | Nothing => 0"
+            ),
+            ["non-exhaustive case: `Just(Err(_))` is not matched"]
+        );
+    }
+
+    #[test]
+    fn missing_flat_constructor() {
+        assert_eq!(
+            check("let value m = case m of | Just(x) => x"),
+            ["non-exhaustive case: missing an arm for `Nothing`"]
+        );
+    }
+
+    #[test]
+    fn guards_do_not_make_a_case_exhaustive() {
+        assert_eq!(
+            check(
+                "let value m = case m of\n\
+                 | Just(x) if x > 0 => x\n\
+                 | Nothing => 0"
+            ),
+            ["non-exhaustive case: `Just(_)` is not matched"]
+        );
+    }
+
+    #[test]
+    fn arms_after_a_guard_are_reachable() {
+        assert!(check(
+            "let value m = case m of\n\
+             | Just(x) if x > 0 => x\n\
+             | Just(_) => 0\n\
+             | Nothing => 0"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn literals_need_a_default() {
+        assert_eq!(
+            check("let name n = case n of | 0 => \"zero\" | 1 => \"one\""),
+            ["non-exhaustive case: `_` is not matched"]
+        );
+        assert_eq!(
+            check("let name 0 = \"zero\""),
+            ["non-exhaustive clauses: no clause matches `_`"]
+        );
+    }
+
+    #[test]
+    fn unreachable_arms() {
+        assert_eq!(
+            check(
+                "let value m = case m of\n\
+                 | _ => 0\n\
+                 | Nothing => 1"
+            ),
+            ["unreachable arm: the arms before it match every value it matches"]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use super::infer::parameter_name;
+use crate::{
+    ast::{Class, Expression, Function, AST},
+    code::{CANNOT_DERIVE, MISSING_METHOD, UNKNOWN_CLASS},
+    diagnostic::{Diagnostic, Diagnostics},
+    types::Type,
+};
+
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// The name of the function holding the methods of an instance
+pub(crate) fn dictionary_name(class: &str, type_name: &str) -> String {
+    format!("${}${}", class, type_name)
+}
+
+/// The name of the function a method of an instance is lowered into
+pub(crate) fn method_name(class: &str, type_name: &str, method: &str) -> String {
+    format!("${}${}${}", class, type_name, method)
+}
+
+/// The name of the function holding the builtin operators for a builtin class
+pub(crate) fn primitive_name(class: &str) -> String {
+    format!("${}", class)
+}
+
+/// Report instances of unknown classes, instances that are missing methods,
+/// and data types that derive classes which cannot be derived
+pub(crate) fn check_instances(ast: &AST, diagnostics: &mut Diagnostics) {
+    let classes = ast.get_classes();
+    for data in ast.get_data() {
+        for (class, span) in data.get_deriving() {
+            if Class::DERIVABLE.contains(&class.as_str()) {
+                continue;
+            }
+            let diagnostic = if classes.iter().any(|c| c.get_name() == class) {
+                Diagnostic::error(CANNOT_DERIVE, format!("`{}` cannot be derived", class))
+            } else {
+                Diagnostic::error(UNKNOWN_CLASS, format!("no class named `{}`", class))
+            };
+            diagnostics.push(diagnostic.with_span(*span));
+        }
+    }
+
+    for instance in ast.get_instances() {
+        let from = diagnostics.len();
+        match classes
+            .iter()
+            .find(|c| c.get_name() == instance.get_class())
+        {
+            Some(class) => {
+                for method in class.get_methods() {
+                    let defined = instance
+                        .get_methods()
+                        .iter()
+                        .any(|f| f.get_name() == method.get_name());
+                    if !defined {
+                        diagnostics.push(
+                            Diagnostic::error(
+                                MISSING_METHOD,
+                                format!(
+                                    "the instance `{} {}` is missing the method `{}`",
+                                    class.get_name(),
+                                    instance.get_type_name(),
+                                    method.get_name()
+                                ),
+                            )
+                            .with_note(
+                                format!("`{}` is declared here", method.get_name()),
+                                method.get_span(),
+                            ),
+                        );
+                    }
+                }
+            }
+            None => diagnostics.error(
+                UNKNOWN_CLASS,
+                format!("no class named `{}`", instance.get_class()),
+            ),
+        }
+        if let Some(span) = instance.get_span() {
+            diagnostics.place(from, span);
+        }
+    }
+}
+
+/// Lower every class and instance into functions.
+///
+/// Every instance becomes a function that applies its argument to the methods of the instance,
+/// and every method becomes a function that selects itself from such a dictionary.
+/// The bodies of the methods of instances are lowered by `lower`.
+pub(crate) fn class_functions(
+    ast: &AST,
+    lower: impl Fn(&str, &Expression) -> Expression,
+) -> Vec<Function> {
+    let classes = ast.get_classes();
+    let mut generated = Vec::new();
+    for instance in ast.get_instances() {
+        let class = match classes
+            .iter()
+            .find(|c| c.get_name() == instance.get_class())
+        {
+            Some(class) => class,
+            None => continue,
+        };
+
+        let parameters = instance
+            .get_constraints()
+            .iter()
+            .map(|(class, parameter)| parameter_name(class, &Type::named(parameter)))
+            .collect::<Vec<_>>();
+        let mut methods = Vec::new();
+        for method in class.get_methods() {
+            let name = method_name(
+                class.get_name(),
+                instance.get_type_name(),
+                method.get_name(),
+            );
+            let defined = instance
+                .get_methods()
+                .iter()
+                .find(|f| f.get_name() == method.get_name());
+            if let Some(function) = defined {
+                let mut lowered =
+                    Function::new(name.clone(), Vec::new(), lower(&name, function.get_body()));
+                if let Some(span) = function.get_span() {
+                    lowered = lowered.with_span(span);
+                }
+                generated.push(lowered);
+            }
+            // Each method takes the dictionaries of the instance's constraints
+            methods.push(parameters.iter().fold(
+                Expression::Identifier(name),
+                |method, parameter| {
+                    Expression::Application(
+                        Rc::new(method),
+                        Rc::new(Expression::Identifier(parameter.clone())),
+                    )
+                },
+            ));
+        }
+
+        let mut lowered = Function::new(
+            dictionary_name(class.get_name(), instance.get_type_name()),
+            parameters,
+            dictionary(methods),
+        );
+        if let Some(span) = instance.get_span() {
+            lowered = lowered.with_span(span);
+        }
+        generated.push(lowered);
+    }
+
+    for class in &classes {
+        let count = class.get_methods().len();
+        for (i, method) in class.get_methods().iter().enumerate() {
+            generated.push(selector(method.get_name(), i, count));
+        }
+
+        if class.is_builtin() {
+            generated.push(Function::new(
+                primitive_name(class.get_name()),
+                Vec::new(),
+                dictionary(vec![primitive_method(class.get_name())]),
+            ));
+        }
+    }
+    generated
+}
+
+/// A dictionary is a function that applies its argument to each method
+///
+/// methods.methods show describe
+fn dictionary(methods: Vec<Expression>) -> Expression {
+    let argument = String::from("$methods");
+    let mut body = Expression::Identifier(argument.clone());
+    for method in methods {
+        body = Expression::Application(Rc::new(body), Rc::new(method));
+    }
+    Expression::Lambda(argument.into(), Rc::new(body))
+}
+
+/// A method selects itself from a dictionary
+///
+/// let describe dictionary = dictionary (show.describe.describe)
+fn selector(name: &str, index: usize, count: usize) -> Function {
+    let argument = String::from("$dictionary");
+    let mut select = Expression::Identifier(format!("${}", index));
+    for i in (0..count).rev() {
+        select = Expression::Lambda(format!("${}", i).into(), Rc::new(select));
+    }
+
+    Function::new(
+        name.to_string(),
+        vec![argument.clone()],
+        Expression::Application(Rc::new(Expression::Identifier(argument)), Rc::new(select)),
+    )
+}
+
+/// The builtin operator behind the method of a builtin class
+fn primitive_method(class: &str) -> Expression {
+    let (a, b) = (String::from("$a"), String::from("$b"));
+    let operands = (
+        Rc::new(Expression::Identifier(a.clone())),
+        Rc::new(Expression::Identifier(b.clone())),
+    );
+    let body = match class {
+        Class::SHOW => return Expression::Identifier(Expression::SHOW_PRIMITIVE.to_string()),
+        Class::ADD => Expression::Add(operands.0, operands.1),
+        Class::ORD => Expression::IfThenElse {
+            condition: Rc::new(Expression::Less(operands.0.clone(), operands.1.clone())),
+            then_case: Rc::new(Expression::Number(-1.0)),
+            else_case: Rc::new(Expression::IfThenElse {
+                condition: Rc::new(Expression::Greater(operands.0, operands.1)),
+                then_case: Rc::new(Expression::Number(1.0)),
+                else_case: Rc::new(Expression::Number(0.0)),
+            }),
+        },
+        _ => Expression::Equal(operands.0, operands.1),
+    };
+    Expression::Lambda(
+        a.into(),
+        Rc::new(Expression::Lambda(b.into(), Rc::new(body))),
+    )
+}
@@ -0,0 +1,343 @@
+use crate::{
+    ast::{Binder, Class, Constructor, Data, Expression, Function, Instance, AST},
+    code::UNPROVEN_TERMINATION,
+    diagnostic::LintLevel,
+    types::Type,
+};
+
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+impl AST {
+    /// Add an instance for every class that a data declaration derives.
+    /// Classes that cannot be derived are reported when instances are checked.
+    pub(crate) fn derive_instances(&mut self) {
+        let mut derived = Vec::new();
+        for data in self.get_data() {
+            for (class, span) in data.get_deriving() {
+                let method = match class.as_str() {
+                    Class::SHOW => derive_show(data),
+                    Class::EQ => derive_equals(data),
+                    Class::ORD => derive_compare(data),
+                    _ => continue,
+                };
+                // Methods only recurse on the members of their arguments, which are smaller,
+                // but they do so through dictionaries that the termination check cannot follow
+                let method = method.with_span(*span).with_lint_levels(vec![(
+                    UNPROVEN_TERMINATION
+                        .get_lint()
+                        .unwrap_or_default()
+                        .to_string(),
+                    LintLevel::Allow,
+                )]);
+                // Members of the type's parameters are compared or shown
+                // with the instances of those parameters
+                let parameters = data.get_parameters();
+                derived.push(
+                    Instance::new(
+                        class.clone(),
+                        Type::Named(
+                            data.get_name().clone(),
+                            parameters.iter().map(Type::named).collect(),
+                        ),
+                        vec![method],
+                    )
+                    .with_constraints(
+                        parameters
+                            .iter()
+                            .map(|parameter| (class.clone(), parameter.clone()))
+                            .collect(),
+                    )
+                    .with_span(*span),
+                );
+            }
+        }
+
+        for instance in derived {
+            self.add_instance(instance);
+        }
+    }
+}
+
+fn identifier(name: &str) -> Rc<Expression> {
+    Rc::new(Expression::Identifier(name.to_string()))
+}
+
+fn string(s: &str) -> Rc<Expression> {
+    Rc::new(Expression::String(s.to_string()))
+}
+
+fn call(function: &str, arguments: &[&String]) -> Rc<Expression> {
+    arguments.iter().fold(identifier(function), |f, argument| {
+        Rc::new(Expression::Application(f, identifier(argument)))
+    })
+}
+
+/// The names that the members of a constructor are bound to, in one of the values being matched
+fn members(cons: &Constructor, value: &str) -> Vec<String> {
+    (0..cons.get_members().len())
+        .map(|i| format!("{}{}", value, i))
+        .collect()
+}
+
+/// Match on a value, with one arm for each constructor of its data type
+fn case_of(
+    data: &Data,
+    value: &str,
+    mut arm: impl FnMut(usize, &Constructor, &[String]) -> Rc<Expression>,
+) -> Rc<Expression> {
+    let constructors = data.get_constructors();
+    Rc::new(Expression::CaseOf {
+        data_type: None,
+        value: identifier(value),
+        cases: constructors
+            .iter()
+            .map(|cons| {
+                let members = members(cons, value);
+                // Constructors are ordered by where they are declared
+                let order = data.cons.iter().position(|c| c == cons).unwrap_or(0);
+                let body = arm(order, cons, &members);
+                let members = members.into_iter().map(Binder::from).collect();
+                (cons.get_name().to_string(), members, body)
+            })
+            .collect(),
+    })
+}
+
+/// Show a value the way its constructor would be written
+///
+/// let show $value = case $value of
+///     | Circle($value0) => "Circle(" + show $value0 + ")"
+///     | Point($value0, $value1) => "Point { x = " + show $value0 + ", y = " + show $value1 + " }"
+fn derive_show(data: &Data) -> Function {
+    let value = "$value";
+    let body = case_of(data, value, |_, cons, members| {
+        if members.is_empty() {
+            return string(cons.get_name());
+        }
+
+        let (open, close) = if cons.is_record() {
+            (" { ", " }")
+        } else {
+            ("(", ")")
+        };
+        let mut result = string(&(cons.get_name().to_string() + open));
+        for (i, member) in members.iter().enumerate() {
+            let mut prefix = String::new();
+            if i > 0 {
+                prefix += ", ";
+            }
+            if cons.is_record() {
+                prefix += &format!("{} = ", cons.get_members()[i]);
+            }
+            if !prefix.is_empty() {
+                result = Rc::new(Expression::Add(result, string(&prefix)));
+            }
+            result = Rc::new(Expression::Add(result, call(Class::SHOW_METHOD, &[member])));
+        }
+        Rc::new(Expression::Add(result, string(close)))
+    });
+
+    Function::new(
+        Class::SHOW_METHOD.to_string(),
+        vec![value.to_string()],
+        (*body).clone(),
+    )
+}
+
+/// Values are equal when they have the same constructor, and all of their members are equal
+///
+/// let equals $a $b = case $a of
+///     | Circle($a0) => case $b of
+///         | Circle($b0) => $a0 == $b0
+///         | Square($b0) => false
+///     | ...
+fn derive_equals(data: &Data) -> Function {
+    let (a, b) = ("$a", "$b");
+    let body = case_of(data, a, |i, _, left| {
+        case_of(data, b, |j, _, right| {
+            if i != j {
+                return identifier("false");
+            }
+            left.iter()
+                .zip(right)
+                .map(|(x, y)| Rc::new(Expression::Equal(identifier(x), identifier(y))))
+                .reduce(|all, equal| Rc::new(Expression::And(all, equal)))
+                .unwrap_or_else(|| identifier("true"))
+        })
+    });
+
+    Function::new(
+        Class::EQUALS.to_string(),
+        vec![a.to_string(), b.to_string()],
+        (*body).clone(),
+    )
+}
+
+/// Values are ordered by the order their constructors are declared in,
+/// and then by each of their members in turn
+///
+/// let compare $a $b = case $a of
+///     | Point($a0, $a1) => case $b of
+///         | Point($b0, $b1) => ($order0.if $order0 == 0 then compare $a1 $b1 else $order0) (compare $a0 $b0)
+fn derive_compare(data: &Data) -> Function {
+    let (a, b) = ("$a", "$b");
+    let body = case_of(data, a, |i, _, left| {
+        case_of(data, b, |j, _, right| {
+            if i < j {
+                return Rc::new(Expression::Number(-1.0));
+            } else if i > j {
+                return Rc::new(Expression::Number(1.0));
+            }
+
+            let comparisons = left
+                .iter()
+                .zip(right)
+                .map(|(x, y)| call(Class::COMPARE, &[x, y]))
+                .collect::<Vec<_>>();
+            let mut result = match comparisons.last() {
+                Some(last) => last.clone(),
+                None => return Rc::new(Expression::Number(0.0)),
+            };
+            for (k, comparison) in comparisons.iter().enumerate().rev().skip(1) {
+                let order = format!("$order{}", k);
+                let decide = Expression::IfThenElse {
+                    condition: Rc::new(Expression::Equal(
+                        identifier(&order),
+                        Rc::new(Expression::Number(0.0)),
+                    )),
+                    then_case: result,
+                    else_case: identifier(&order),
+                };
+                result = Rc::new(Expression::Application(
+                    Rc::new(Expression::Lambda(order.into(), Rc::new(decide))),
+                    comparison.clone(),
+                ));
+            }
+            result
+        })
+    });
+
+    Function::new(
+        Class::COMPARE.to_string(),
+        vec![a.to_string(), b.to_string()],
+        (*body).clone(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        check::testing::{compile, evaluate, parse, run},
+        code::{CANNOT_DERIVE, NO_INSTANCE},
+        diagnostic::Diagnostics,
+    };
+    use alloc::string::ToString;
+
+    const DATA: &str = "data Shape = Circle(Num) | Rectangle(Num, Num) deriving (Show, Eq, Ord)\n\
+                        data Maybe a = Just(a) | Nothing deriving (Show, Eq, Ord)\n\
+                        data Named = Named { name : String } deriving (Show)\n";
+
+    /// The strings a program prints, with the codes of the diagnostics reported for it
+    fn printed(main: &str) -> (Option<String>, Vec<&'static str>) {
+        compile(&format!("{}let main _ = print {}", DATA, main))
+    }
+
+    #[test]
+    fn show() {
+        assert_eq!(
+            printed(
+                "(show (Just(Rectangle(2, 3)))) \" \" (show Nothing) \" \" \
+                 (show Named { name = \"circle\" })"
+            ),
+            (
+                Some(
+                    r#"K(print("Just(Rectangle(2, 3))")(" ")("Nothing")(" ")("Named { name = \"circle\" }"))"#
+                        .to_string()
+                ),
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn parameters_use_their_own_instances() {
+        let source = format!(
+            "{}data List a = Cons(a, List a) | Nil deriving (Show)\n\
+             let describe x = show (Just(x))\n\
+             let main _ = print (show (Cons(Circle(1), Cons(Circle(2), Nil)))) \
+             (describe (Circle(3)))",
+            DATA
+        );
+        // Recursive instances are left to run at runtime
+        let (result, codes) = evaluate(&source);
+        assert!(codes.is_empty(), "{:?}", codes);
+        assert_eq!(
+            result.map(|result| run(&result).to_string()).as_deref(),
+            Some(r#"K(print("Cons(Circle(1), Cons(Circle(2), Nil))")("Just(Circle(3))"))"#)
+        );
+
+        let mut diagnostics = Diagnostics::new();
+        let signatures = parse(&source).infer_types(&mut diagnostics);
+        let describe = signatures
+            .iter()
+            .find(|(name, _)| name == "describe")
+            .map(|(_, scheme)| scheme.to_string());
+        assert_eq!(describe.as_deref(), Some("Show a => a -> String"));
+    }
+
+    #[test]
+    fn equality_is_structural() {
+        assert_eq!(
+            printed(
+                "(if Just(Circle(1)) == Just(Circle(1)) then \"equal\" else \"different\") \
+                 (if Circle(1) == Rectangle(1, 1) then \"equal\" else \"different\") \
+                 (if Rectangle(1, 2) != Rectangle(1, 3) then \"different\" else \"equal\")"
+            ),
+            (
+                Some(r#"K(print("equal")("different")("different"))"#.to_string()),
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn constructors_are_ordered_as_they_are_declared() {
+        assert_eq!(
+            printed(
+                "(compare (Circle(9)) (Rectangle(1, 1))) \
+                 (compare (Rectangle(2, 4)) (Rectangle(2, 3))) \
+                 (compare (Just(Circle(1))) (Just(Circle(1)))) \
+                 (if Just(Circle(1)) < Nothing then \"less\" else \"greater\")"
+            ),
+            (
+                Some(r#"K(print(-1.0)(1.0)(0.0)("less"))"#.to_string()),
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    fn only_builtin_classes_are_derived() {
+        assert_eq!(
+            compile("data Shape = Circle(Num) deriving (Add)\nlet main _ = print (Circle(1))").1,
+            [CANNOT_DERIVE.get_name()]
+        );
+    }
+
+    #[test]
+    fn members_need_instances() {
+        assert_eq!(
+            compile(
+                "data Shape = Circle(Num)\n\
+                 data Box = Box(Shape) deriving (Show)\n\
+                 let main _ = print (show (Box(Circle(1))))"
+            )
+            .1,
+            [NO_INSTANCE.get_name()]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    ast::AST,
+    code::DUPLICATE_DEFINITION,
+    diagnostic::{Diagnostic, Diagnostics, Span},
+};
+
+use alloc::{string::String, vec::Vec};
+use core::cmp::Reverse;
+
+/// Report every name that is declared more than once in the same namespace
+pub(crate) fn check_duplicates(ast: &AST, diagnostics: &mut Diagnostics) {
+    // Functions and constants are both inlined by name
+    let mut values = Vec::new();
+    for constant in ast.get_constants() {
+        values.push(("constant", constant.get_name().clone(), constant.get_span()));
+    }
+    for function in ast.get_functions() {
+        values.push(("function", function.get_name().clone(), function.get_span()));
+    }
+
+    // Methods are bound at the top level, and classes have their own namespace
+    let mut classes = Vec::new();
+    for class in ast.get_classes() {
+        classes.push(("class", class.get_name().clone(), class.get_span()));
+        for method in class.get_methods() {
+            values.push(("class method", method.get_name().clone(), method.get_span()));
+        }
+    }
+    report_duplicates(values, diagnostics);
+    report_duplicates(classes, diagnostics);
+
+    let mut instances = Vec::new();
+    for instance in ast.get_instances() {
+        let name = format!("{} {}", instance.get_class(), instance.get_type_name());
+        instances.push(("instance", name, instance.get_span()));
+
+        let mut methods = Vec::new();
+        for method in instance.get_methods() {
+            methods.push(("method", method.get_name().clone(), method.get_span()));
+        }
+        report_duplicates(methods, diagnostics);
+    }
+    report_duplicates(instances, diagnostics);
+
+    let mut signatures = Vec::new();
+    for signature in ast.get_signatures() {
+        signatures.push((
+            "type annotation",
+            signature.get_name().clone(),
+            signature.get_span(),
+        ));
+    }
+    report_duplicates(signatures, diagnostics);
+
+    let mut fixities = Vec::new();
+    for fixity in ast.get_fixities() {
+        fixities.push((
+            "fixity declaration",
+            fixity.get_operator().clone(),
+            fixity.get_span(),
+        ));
+    }
+    report_duplicates(fixities, diagnostics);
+
+    // A `type` declaration names both a type and its only constructor
+    let mut types = Vec::new();
+    let mut constructors = Vec::new();
+    for data in ast.get_data() {
+        types.push(("data type", data.get_name().clone(), data.get_span()));
+        for cons in &data.cons {
+            constructors.push((
+                "constructor",
+                String::from(cons.get_name()),
+                cons.get_span(),
+            ));
+        }
+    }
+    for cons in ast.get_constructors() {
+        types.push(("type", String::from(cons.get_name()), cons.get_span()));
+        constructors.push((
+            "constructor",
+            String::from(cons.get_name()),
+            cons.get_span(),
+        ));
+    }
+    report_duplicates(types, diagnostics);
+    report_duplicates(constructors, diagnostics);
+}
+
+/// Report every definition whose name was already defined earlier in `definitions`
+fn report_duplicates(
+    mut definitions: Vec<(&str, String, Option<Span>)>,
+    diagnostics: &mut Diagnostics,
+) {
+    // Spans count the input remaining after them, so earlier definitions have larger spans.
+    // Builtin definitions have no span, and come before all of them.
+    definitions.sort_by_key(|(_, _, span)| span.map(Reverse));
+
+    for (i, (kind, name, span)) in definitions.iter().enumerate() {
+        let first = definitions[..i].iter().find(|(_, other, _)| other == name);
+
+        if let Some((first_kind, _, first_span)) = first {
+            let mut diagnostic = Diagnostic::error(
+                DUPLICATE_DEFINITION,
+                format!("the {} `{}` is defined more than once", kind, name),
+            )
+            .with_note(
+                format!(
+                    "`{}` is first defined here as {} {}",
+                    name,
+                    article(first_kind),
+                    first_kind
+                ),
+                *first_span,
+            );
+            if let Some(span) = span {
+                diagnostic = diagnostic.with_span(*span);
+            }
+            diagnostics.push(diagnostic);
+        }
+    }
+}
+
+fn article(kind: &str) -> &'static str {
+    if kind.starts_with(|c| "aeiou".contains(c)) {
+        "an"
+    } else {
+        "a"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::testing::parse;
+
+    /// The messages of the duplicates reported for a program
+    fn duplicates(source: &str) -> Vec<String> {
+        let mut diagnostics = Diagnostics::new();
+        check_duplicates(&parse(source), &mut diagnostics);
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.get_message().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn values_share_a_namespace() {
+        assert_eq!(
+            duplicates("const area = 1\nlet area w h = w * h"),
+            ["the function `area` is defined more than once"]
+        );
+        assert_eq!(
+            duplicates("class Size a where size : a -> Num\nlet size x = 1"),
+            ["the function `size` is defined more than once"]
+        );
+    }
+
+    #[test]
+    fn clauses_are_not_duplicates() {
+        assert!(duplicates("let count 0 = \"none\"\nlet count n = \"some\"").is_empty());
+        assert_eq!(
+            duplicates("let area s = 1\nlet area w h = w * h"),
+            ["the function `area` is defined more than once"]
+        );
+    }
+
+    #[test]
+    fn types_and_constructors() {
+        assert_eq!(
+            duplicates(
+                "data Shape = Circle(Num) | Square(Num)\n\
+                 data Tile = Square(Num) | Empty"
+            ),
+            ["the constructor `Square` is defined more than once"]
+        );
+        assert_eq!(
+            duplicates("data Point = Origin\ntype Point(x, y)"),
+            ["the type `Point` is defined more than once"]
+        );
+    }
+
+    #[test]
+    fn the_first_definition_is_noted() {
+        let mut diagnostics = Diagnostics::new();
+        check_duplicates(
+            &parse("const area = 1\nlet area w h = w * h"),
+            &mut diagnostics,
+        );
+        let diagnostic = diagnostics.iter().next().expect("a duplicate");
+        assert_eq!(
+            diagnostic.get_notes()[0].0,
+            "`area` is first defined here as a constant"
+        );
+    }
+}
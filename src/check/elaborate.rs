@@ -0,0 +1,165 @@
+use super::{
+    classes,
+    infer::{self, Dictionary, Inferred},
+    patterns::Patterns,
+    records,
+};
+use crate::{
+    ast::{Class, Expression, AST},
+    diagnostic::Diagnostics,
+};
+
+use alloc::{rc::Rc, string::ToString, vec::Vec};
+
+impl AST {
+    /// Lower the parts of the program that depend on the types found by inference.
+    ///
+    /// Declarations with constraints take their dictionaries as parameters before their arguments,
+    /// and each use of an overloaded name or operator is given the dictionaries inference found for it.
+    /// Classes and instances become functions, and records are built and taken apart
+    /// with the constructors they were declared as. Cases with nested patterns become decision trees.
+    pub fn elaborate(&mut self, diagnostics: &mut Diagnostics) {
+        let inferred = infer::infer_program(self, diagnostics);
+        let patterns = Patterns::new(self);
+
+        let lower = |name: &str, body: &Expression| {
+            let mut result = elaborate(body, name, &inferred, &patterns);
+            if let Some(parameters) = inferred.parameters.get(name) {
+                for parameter in parameters.iter().rev() {
+                    result = Expression::Lambda(parameter.clone().into(), Rc::new(result));
+                }
+            }
+            result
+        };
+
+        for function in self.get_functions_mut() {
+            let body = lower(function.get_name(), function.get_body());
+            function.set_body(body);
+        }
+        for constant in self.get_constants_mut() {
+            let body = lower(constant.get_name(), constant.get_body());
+            constant.set_body(body);
+        }
+
+        for function in classes::class_functions(self, lower) {
+            self.add_function(function);
+        }
+    }
+}
+
+/// Give each use of an overloaded name or operator its dictionaries,
+/// replace each record expression with the constructor of its record,
+/// bind each local `let` by applying a lambda to its value,
+/// and compile each case with nested patterns to a decision tree
+fn elaborate(
+    expr: &Expression,
+    owner: &str,
+    inferred: &Inferred,
+    patterns: &Patterns,
+) -> Expression {
+    let mut position = 0;
+    elaborate_at(expr, owner, &mut position, inferred, patterns)
+}
+
+/// Elaborate an expression at a position in the body of a declaration.
+/// Children are elaborated before their parent, in the order that inference numbered them.
+fn elaborate_at(
+    expr: &Expression,
+    owner: &str,
+    position: &mut usize,
+    inferred: &Inferred,
+    patterns: &Patterns,
+) -> Expression {
+    let site = (owner.to_string(), *position);
+    *position += 1;
+    let found = inferred.dictionaries.get(&site).map(Vec::as_slice);
+    let record = inferred.fields.get(&site);
+    let expr = expr
+        .map_children(|child| Rc::new(elaborate_at(child, owner, position, inferred, patterns)));
+
+    match (&expr, found, record) {
+        (Expression::Identifier(_), Some(found), _) => {
+            found.iter().fold(expr.clone(), |f, dictionary| {
+                Expression::Application(Rc::new(f), Rc::new(dictionary.to_expression()))
+            })
+        }
+        (Expression::TailCall(args), Some(found), _) => Expression::TailCall(
+            found
+                .iter()
+                .map(|dictionary| Rc::new(dictionary.to_expression()))
+                .chain(args.iter().cloned())
+                .collect(),
+        ),
+
+        // Types without an instance of a builtin class keep the builtin operator
+        (Expression::Add(a, b), Some([dictionary]), _) if !dictionary.is_primitive() => {
+            method_call(Class::PLUS, dictionary, a.clone(), b.clone())
+        }
+        (Expression::Equal(a, b), Some([dictionary]), _) if !dictionary.is_primitive() => {
+            method_call(Class::EQUALS, dictionary, a.clone(), b.clone())
+        }
+        (Expression::NotEqual(a, b), Some([dictionary]), _) if !dictionary.is_primitive() => {
+            Expression::Not(Rc::new(method_call(
+                Class::EQUALS,
+                dictionary,
+                a.clone(),
+                b.clone(),
+            )))
+        }
+        // a < b  ==>  compare a b < 0
+        (
+            Expression::Greater(a, b)
+            | Expression::GreaterEqual(a, b)
+            | Expression::Less(a, b)
+            | Expression::LessEqual(a, b),
+            Some([dictionary]),
+            _,
+        ) if !dictionary.is_primitive() => {
+            let ordering = Rc::new(method_call(
+                Class::COMPARE,
+                dictionary,
+                a.clone(),
+                b.clone(),
+            ));
+            let zero = Rc::new(Expression::Number(0.0));
+            match expr {
+                Expression::Greater(..) => Expression::Greater(ordering, zero),
+                Expression::GreaterEqual(..) => Expression::GreaterEqual(ordering, zero),
+                Expression::Less(..) => Expression::Less(ordering, zero),
+                _ => Expression::LessEqual(ordering, zero),
+            }
+        }
+
+        (Expression::Record(_, fields), _, Some(record)) => records::build(record, fields),
+        (Expression::Field(value, field), _, Some(record)) => {
+            records::access(record, value.clone(), field)
+        }
+        (Expression::Update(value, fields), _, Some(record)) => {
+            records::update(record, value.clone(), fields)
+        }
+
+        // let x = v in body  ==>  (x. body) v
+        (Expression::Let { name, value, body }, _, _) => Expression::Application(
+            Rc::new(Expression::Lambda(name.clone(), body.clone())),
+            value.clone(),
+        ),
+
+        (Expression::Match { values, arms }, _, _) => patterns.compile(values.clone(), arms).tree,
+
+        _ => expr,
+    }
+}
+
+/// Call a method of two arguments with a dictionary
+fn method_call(
+    method: &str,
+    dictionary: &Dictionary,
+    a: Rc<Expression>,
+    b: Rc<Expression>,
+) -> Expression {
+    let method = Expression::Application(
+        Rc::new(Expression::Identifier(method.to_string())),
+        Rc::new(dictionary.to_expression()),
+    );
+    Expression::Application(Rc::new(Expression::Application(Rc::new(method), a)), b)
+}
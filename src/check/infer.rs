@@ -0,0 +1,2017 @@
+use super::classes::{dictionary_name, method_name, primitive_name};
+use crate::{
+    ast::{Binder, Class, Constructor, Data, Expression, Pattern, AST},
+    code::{
+        AMBIGUOUS_FIELD, AMBIGUOUS_INSTANCE, INFINITE_TYPE, MISMATCHED_TYPES, MISSING_DEFINITION,
+        NO_INSTANCE, TYPED_HOLE, UNKNOWN_FIELD, UNKNOWN_TYPE_PARAMETER,
+    },
+    diagnostic::{Diagnostic, Diagnostics, Span},
+    types::{display_all, display_pair, Scheme, Type},
+};
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::cmp::Reverse;
+
+/// Why two types could not be unified
+enum UnifyError {
+    Mismatch,
+    Infinite,
+}
+
+/// The methods given to an overloaded name or operator, for one of its constraints
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Dictionary {
+    /// The methods of an instance, given the dictionaries its constraints need
+    Instance(String, Vec<Dictionary>),
+    /// A dictionary passed to the enclosing declaration
+    Parameter(String),
+    /// The builtin operators on numbers and strings, for a builtin class
+    Primitive(String),
+}
+
+impl Dictionary {
+    pub(crate) fn to_expression(&self) -> Expression {
+        match self {
+            Self::Instance(name, arguments) => {
+                arguments
+                    .iter()
+                    .fold(Expression::Identifier(name.clone()), |f, argument| {
+                        Expression::Application(Rc::new(f), Rc::new(argument.to_expression()))
+                    })
+            }
+            Self::Parameter(name) => Expression::Identifier(name.clone()),
+            Self::Primitive(class) => Expression::Identifier(primitive_name(class)),
+        }
+    }
+
+    pub(crate) fn is_primitive(&self) -> bool {
+        matches!(self, Self::Primitive(_))
+    }
+}
+
+/// An expression of the program, identified by the declaration it is written in
+/// and its position in a preorder walk of the body of that declaration,
+/// so that the dictionaries it needs can be found after inference
+pub(crate) type Site = (String, usize);
+
+/// The name of the dictionary parameter for a constraint on a type variable,
+/// or on the named type an annotated variable is replaced by
+pub(crate) fn parameter_name(class: &str, t: &Type) -> String {
+    match t {
+        Type::Variable(v) => format!("$dictionary${}${}", class, v),
+        Type::Named(name, _) => format!("$dictionary${}${}", class, name),
+        Type::Function(_, _) => format!("$dictionary${}", class),
+    }
+}
+
+/// A constraint that must be given a dictionary where an overloaded name is used
+struct Wanted {
+    site: Site,
+    /// The declaration the name is used in
+    owner: String,
+    class: String,
+    t: Type,
+    span: Option<Span>,
+}
+
+/// The types found by inference, and the dictionaries needed to lower classes
+pub(crate) struct Inferred {
+    pub(crate) signatures: Vec<(String, Scheme)>,
+    /// The dictionaries to apply at each site, in order
+    pub(crate) dictionaries: BTreeMap<Site, Vec<Dictionary>>,
+    /// The dictionary parameters of each declaration, in order
+    pub(crate) parameters: BTreeMap<String, Vec<String>>,
+    /// The record that each field access and update is on
+    pub(crate) fields: BTreeMap<Site, Constructor>,
+}
+
+/// A hole in the program, with the type it must have and the variables in scope where it is written
+struct Hole {
+    name: String,
+    t: Type,
+    scope: Vec<(Binder, Type)>,
+    span: Option<Span>,
+}
+
+/// The state of Hindley-Milner type inference over a program
+struct Inference {
+    /// The type each type variable has been unified with, if any
+    bindings: Vec<Option<Type>>,
+    /// The types of builtins, constructors, and top level declarations
+    globals: BTreeMap<String, Scheme>,
+    /// The types of variables bound by lambdas, patterns, and `let`, innermost last.
+    /// Only `let` binds variables that are generic.
+    locals: Vec<(Binder, Scheme)>,
+    /// The type of each constructor, from its members to the data type it builds
+    constructors: BTreeMap<String, Scheme>,
+    /// The classes that fall back to the builtin operators
+    primitive: BTreeSet<String>,
+    /// Each class and the name of each type it has an instance for,
+    /// with the classes that the instance needs the parameters of the type at each position to have
+    instances: BTreeMap<(String, String), Vec<(String, usize)>>,
+    /// The data types declared by the program, which only have the instances they declare or derive
+    declared: BTreeSet<String>,
+    /// The declarations being inferred that are not annotated, and are not generic yet
+    group: Vec<String>,
+    /// The declaration being inferred
+    owner: String,
+    /// The position of the next expression to be inferred in the body of the declaration
+    position: usize,
+    /// Where the expression being inferred was written
+    span: Option<Span>,
+    /// The constraints of annotated declarations, with the parameters that satisfy them
+    given: BTreeMap<String, Vec<(String, Type, String)>>,
+    /// The constraints found in the group being inferred
+    wanted: Vec<Wanted>,
+    /// Uses of declarations from the group being inferred, which pass on their own dictionaries
+    recursive: Vec<(Site, String)>,
+    dictionaries: BTreeMap<Site, Vec<Dictionary>>,
+    parameters: BTreeMap<String, Vec<String>>,
+    /// Each record, and the name of the type it builds
+    records: Vec<(String, Constructor)>,
+    fields: BTreeMap<Site, Constructor>,
+    /// The holes found in the group being inferred
+    holes: Vec<Hole>,
+}
+
+impl Inference {
+    fn new() -> Self {
+        let mut result = Self {
+            bindings: Vec::new(),
+            globals: BTreeMap::new(),
+            locals: Vec::new(),
+            constructors: BTreeMap::new(),
+            primitive: BTreeSet::new(),
+            instances: BTreeMap::new(),
+            declared: BTreeSet::new(),
+            group: Vec::new(),
+            owner: String::new(),
+            position: 0,
+            span: None,
+            given: BTreeMap::new(),
+            wanted: Vec::new(),
+            recursive: Vec::new(),
+            dictionaries: BTreeMap::new(),
+            parameters: BTreeMap::new(),
+            records: Vec::new(),
+            fields: BTreeMap::new(),
+            holes: Vec::new(),
+        };
+
+        // Booleans are Church encoded, and choose between two values of any type
+        let choice = result.fresh();
+        let boolean = result.generalize(&Type::boolean(choice));
+        result.globals.insert("true".to_string(), boolean.clone());
+        result.globals.insert("false".to_string(), boolean);
+
+        // `print` takes any number of arguments of any type
+        let (a, b) = (result.fresh(), result.fresh());
+        let print = result.generalize(&Type::function(a, b));
+        result.globals.insert("print".to_string(), print.clone());
+        result.globals.insert("println".to_string(), print);
+
+        let a = result.fresh();
+        let y = result.generalize(&Type::function(Type::function(a.clone(), a.clone()), a));
+        result.globals.insert("Y".to_string(), y);
+
+        // (f << g) x  ==>  f (g x)
+        let (a, b, c) = (result.fresh(), result.fresh(), result.fresh());
+        let compose = result.generalize(&Type::function(
+            Type::function(b.clone(), c.clone()),
+            Type::function(Type::function(a.clone(), b), Type::function(a, c)),
+        ));
+        result
+            .globals
+            .insert(Expression::COMPOSE.to_string(), compose);
+
+        result
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.bindings.push(None);
+        Type::Variable(self.bindings.len() - 1)
+    }
+
+    /// Replace every bound type variable in a type with what it is bound to
+    fn resolve(&self, t: &Type) -> Type {
+        match t {
+            Type::Variable(v) => match &self.bindings[*v] {
+                Some(bound) => self.resolve(bound),
+                None => t.clone(),
+            },
+            Type::Named(name, parameters) => Type::Named(
+                name.clone(),
+                parameters.iter().map(|p| self.resolve(p)).collect(),
+            ),
+            Type::Function(a, b) => Type::function(self.resolve(a), self.resolve(b)),
+        }
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> Result<(), UnifyError> {
+        match (self.resolve(a), self.resolve(b)) {
+            (Type::Variable(v), Type::Variable(w)) if v == w => Ok(()),
+            (Type::Variable(v), t) | (t, Type::Variable(v)) => {
+                if t.has_variable(v) {
+                    return Err(UnifyError::Infinite);
+                }
+                self.bindings[v] = Some(t);
+                Ok(())
+            }
+            (Type::Named(m, p), Type::Named(n, q)) if m == n && p.len() == q.len() => {
+                for (p, q) in p.iter().zip(q.iter()) {
+                    self.unify(p, q)?;
+                }
+                Ok(())
+            }
+            (Type::Function(a, b), Type::Function(c, d)) => {
+                self.unify(&a, &c)?;
+                self.unify(&b, &d)
+            }
+            _ => Err(UnifyError::Mismatch),
+        }
+    }
+
+    /// Unify the type that was found with the type that was expected, or describe why they differ
+    fn mismatch(&mut self, expected: &Type, found: &Type) -> Option<Diagnostic> {
+        let error = match self.unify(expected, found) {
+            Ok(()) => return None,
+            Err(error) => error,
+        };
+
+        let (expected, found) = display_pair(&self.resolve(expected), &self.resolve(found));
+        Some(match error {
+            UnifyError::Mismatch => Diagnostic::error(
+                MISMATCHED_TYPES,
+                format!(
+                    "mismatched types: expected `{}`, found `{}`",
+                    expected, found
+                ),
+            ),
+            UnifyError::Infinite => Diagnostic::error(
+                INFINITE_TYPE,
+                format!(
+                    "infinite type: expected `{}`, found `{}`, which would have to contain itself",
+                    expected, found
+                ),
+            ),
+        })
+    }
+
+    /// Unify the type that was found with the type that was expected, or report why they differ
+    fn expect(
+        &mut self,
+        expected: &Type,
+        found: &Type,
+        span: Option<Span>,
+        diagnostics: &mut Diagnostics,
+    ) {
+        if let Some(diagnostic) = self.mismatch(expected, found) {
+            diagnostics.push(match span {
+                Some(span) => diagnostic.with_span(span),
+                None => diagnostic,
+            });
+        }
+    }
+
+    /// Make a copy of a scheme with fresh type variables
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        self.instantiate_constrained(scheme).0
+    }
+
+    /// Make a copy of a scheme with fresh type variables, along with its constraints
+    fn instantiate_constrained(&mut self, scheme: &Scheme) -> (Type, Vec<(String, Type)>) {
+        let mut substitution = BTreeMap::new();
+        for v in scheme.get_variables() {
+            substitution.insert(*v, self.fresh());
+        }
+        let constraints = scheme
+            .get_constraints()
+            .iter()
+            .map(|(class, v)| (class.clone(), Type::Variable(*v).substitute(&substitution)))
+            .collect();
+        (scheme.get_body().substitute(&substitution), constraints)
+    }
+
+    /// The site of the expression at a position in the declaration being inferred
+    fn site(&self, position: usize) -> Site {
+        (self.owner.clone(), position)
+    }
+
+    /// Require a dictionary for a class at an overloaded name or operator
+    fn want(&mut self, position: usize, class: &str, t: Type) {
+        self.wanted.push(Wanted {
+            site: self.site(position),
+            owner: self.owner.clone(),
+            class: class.to_string(),
+            t,
+            span: self.span,
+        });
+    }
+
+    fn is_declared(&self, t: &Type) -> bool {
+        matches!(t, Type::Named(name, _) if self.declared.contains(name))
+    }
+
+    /// Find the dictionary for every constraint wanted by the group that was just inferred.
+    /// Constraints on the group's type variables become dictionary parameters,
+    /// and are returned to be added to the group's schemes.
+    fn resolve_wanted(
+        &mut self,
+        variables: &[usize],
+        diagnostics: &mut Diagnostics,
+    ) -> Vec<(String, usize)> {
+        let mut constraints = Vec::new();
+        for wanted in core::mem::take(&mut self.wanted) {
+            let mut errors = Vec::new();
+            let dictionary = self.dictionary(
+                &wanted.class,
+                &wanted.t,
+                &wanted.owner,
+                variables,
+                &mut constraints,
+                &mut errors,
+            );
+
+            for mut diagnostic in errors {
+                if let Some(span) = wanted.span {
+                    diagnostic = diagnostic.with_span(span);
+                }
+                diagnostics.push(diagnostic);
+            }
+            self.dictionaries
+                .entry(wanted.site)
+                .or_default()
+                .push(dictionary);
+        }
+        constraints
+    }
+
+    /// Find the dictionary of a class for a type, where it is wanted by `owner`.
+    /// Instances with constraints are given the dictionaries of the parameters of the type.
+    fn dictionary(
+        &mut self,
+        class: &str,
+        t: &Type,
+        owner: &str,
+        variables: &[usize],
+        constraints: &mut Vec<(String, usize)>,
+        errors: &mut Vec<Diagnostic>,
+    ) -> Dictionary {
+        let t = self.resolve(t);
+        let given = self.given.get(owner).and_then(|given| {
+            given
+                .iter()
+                .find(|(other_class, other, _)| other_class == class && *other == t)
+        });
+        if let Some((_, _, parameter)) = given {
+            return Dictionary::Parameter(parameter.clone());
+        }
+
+        match &t {
+            Type::Named(name, parameters) => {
+                if let Some(context) = self.instances.get(&(class.to_string(), name.clone())) {
+                    let context = context.clone();
+                    let arguments = context
+                        .iter()
+                        .map(|(other_class, i)| {
+                            let parameter = match parameters.get(*i) {
+                                Some(parameter) => parameter.clone(),
+                                None => self.fresh(),
+                            };
+                            self.dictionary(
+                                other_class,
+                                &parameter,
+                                owner,
+                                variables,
+                                constraints,
+                                errors,
+                            )
+                        })
+                        .collect();
+                    return Dictionary::Instance(dictionary_name(class, name), arguments);
+                }
+            }
+            Type::Variable(v) if variables.contains(v) => {
+                if !constraints.contains(&(class.to_string(), *v)) {
+                    constraints.push((class.to_string(), *v));
+                }
+                return Dictionary::Parameter(parameter_name(class, &t));
+            }
+            _ => {}
+        }
+
+        // Data types only have the instances they declare or derive
+        if self.primitive.contains(class) && !self.is_declared(&t) {
+            return Dictionary::Primitive(class.to_string());
+        }
+        errors.push(match t {
+            Type::Variable(_) => Diagnostic::error(
+                AMBIGUOUS_INSTANCE,
+                format!(
+                    "ambiguous type: cannot tell which instance of `{}` to use",
+                    class
+                ),
+            ),
+            _ => Diagnostic::error(
+                NO_INSTANCE,
+                format!("no instance of `{}` for `{}`", class, t),
+            ),
+        });
+        Dictionary::Primitive(class.to_string())
+    }
+
+    /// Report the type of each hole found so far, and the types of the variables in scope there
+    fn report_holes(&mut self, diagnostics: &mut Diagnostics) {
+        for hole in core::mem::take(&mut self.holes) {
+            let mut types = vec![self.resolve(&hole.t)];
+            types.extend(hole.scope.iter().map(|(_, t)| self.resolve(t)));
+            let names = display_all(&types.iter().collect::<Vec<_>>());
+
+            let mut diagnostic = Diagnostic::error(
+                TYPED_HOLE,
+                format!("found the hole `{}`, of type `{}`", hole.name, names[0]),
+            );
+            for ((name, _), t) in hole.scope.iter().zip(&names[1..]) {
+                let note = format!("`{} : {}` is in scope", name, t);
+                diagnostic = diagnostic.with_note(note, name.get_span());
+            }
+            if let Some(span) = hole.span {
+                diagnostic = diagnostic.with_span(span);
+            }
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Make a type generic over every variable that is not used by a local variable
+    fn generalize(&self, t: &Type) -> Scheme {
+        let t = self.resolve(t);
+        let mut used = BTreeSet::new();
+        for (_, local) in &self.locals {
+            let generic = local.get_variables();
+            used.extend(
+                self.resolve(local.get_body())
+                    .variables()
+                    .into_iter()
+                    .filter(|v| !generic.contains(v)),
+            );
+        }
+
+        let variables = t
+            .variables()
+            .into_iter()
+            .filter(|v| !used.contains(v))
+            .collect();
+        Scheme::new(variables, t)
+    }
+
+    /// Make the type of a `let` binding generic, except over the variables of the declarations
+    /// being inferred, and over variables with a class constraint from the binding.
+    /// Constraints are only passed to declarations, so a constrained variable must stay the same
+    /// for every use of the binding.
+    fn generalize_local(&self, t: &Type, wanted: usize) -> Scheme {
+        let scheme = self.generalize(t);
+        let mut used = BTreeSet::new();
+        for name in &self.group {
+            if let Some(global) = self.globals.get(name) {
+                used.extend(self.resolve(global.get_body()).variables());
+            }
+        }
+        for constraint in &self.wanted[wanted..] {
+            used.extend(self.resolve(&constraint.t).variables());
+        }
+
+        let variables = scheme
+            .get_variables()
+            .iter()
+            .copied()
+            .filter(|v| !used.contains(v))
+            .collect();
+        Scheme::new(variables, scheme.get_body().clone())
+    }
+
+    fn lookup(&mut self, name: &str) -> Type {
+        for (local, scheme) in self.locals.iter().rev() {
+            if local.as_str() == name {
+                let scheme = scheme.clone();
+                return self.instantiate(&scheme);
+            }
+        }
+
+        match self.globals.get(name).cloned() {
+            Some(scheme) => self.instantiate(&scheme),
+            // Unbound variables are reported by the scope check
+            None => self.fresh(),
+        }
+    }
+
+    /// Apply a function to an argument, and get the type of the result
+    fn apply(
+        &mut self,
+        function: Type,
+        argument: &Expression,
+        diagnostics: &mut Diagnostics,
+    ) -> Type {
+        let argument_type = self.infer(argument, diagnostics);
+        let span = argument.get_span();
+
+        match self.resolve(&function) {
+            Type::Function(expected, result) => {
+                self.expect(&expected, &argument_type, span, diagnostics);
+                (*result).clone()
+            }
+            Type::Variable(_) => {
+                let result = self.fresh();
+                let found = Type::function(argument_type, result.clone());
+                self.expect(&function, &found, None, diagnostics);
+                result
+            }
+            other => {
+                diagnostics.error(
+                    MISMATCHED_TYPES,
+                    format!("a value of type `{}` is not a function", other),
+                );
+                self.fresh()
+            }
+        }
+    }
+
+    /// Bring the variables of a nested pattern into scope, given the type of the values it matches.
+    /// This returns the number of variables it brought into scope.
+    fn bind_pattern(
+        &mut self,
+        pattern: &Pattern,
+        expected: &Type,
+        diagnostics: &mut Diagnostics,
+    ) -> usize {
+        let found = match pattern {
+            Pattern::Wildcard => return 0,
+            Pattern::Variable(name) => {
+                let local = Scheme::monomorphic(expected.clone());
+                self.locals.push((name.clone(), local));
+                return 1;
+            }
+            Pattern::As(name, inner) => {
+                let local = Scheme::monomorphic(expected.clone());
+                self.locals.push((name.clone(), local));
+                return 1 + self.bind_pattern(inner, expected, diagnostics);
+            }
+            Pattern::Number(_) => Type::number(),
+            Pattern::String(_) => Type::string(),
+            Pattern::Constructor(cons_name, members) => {
+                let mut member_types = Vec::new();
+                let data_type = match self.constructors.get(cons_name).cloned() {
+                    Some(scheme) => {
+                        let mut t = self.instantiate(&scheme);
+                        while let Type::Function(member, result) = t {
+                            member_types.push((*member).clone());
+                            t = (*result).clone();
+                        }
+                        t
+                    }
+                    // Unknown constructors are reported when cases are checked
+                    None => self.fresh(),
+                };
+                self.expect(&data_type, expected, None, diagnostics);
+
+                let mut count = 0;
+                for (i, member) in members.iter().enumerate() {
+                    let t = match member_types.get(i) {
+                        Some(t) => t.clone(),
+                        None => self.fresh(),
+                    };
+                    count += self.bind_pattern(member, &t, diagnostics);
+                }
+                return count;
+            }
+        };
+        self.expect(&found, expected, None, diagnostics);
+        0
+    }
+
+    /// Infer the type of the body of a pattern, with the members of the pattern in scope.
+    /// This also returns the type of the values that the pattern matches, if the constructor exists.
+    fn infer_pattern(
+        &mut self,
+        cons_name: &str,
+        members: &[Binder],
+        body: &Expression,
+        diagnostics: &mut Diagnostics,
+    ) -> (Option<Type>, Type) {
+        let mut member_types = Vec::new();
+        let data_type = match self.constructors.get(cons_name).cloned() {
+            Some(scheme) => {
+                let mut t = self.instantiate(&scheme);
+                while let Type::Function(member, result) = t {
+                    member_types.push((*member).clone());
+                    t = (*result).clone();
+                }
+                Some(t)
+            }
+            None => None,
+        };
+
+        for (i, member) in members.iter().enumerate() {
+            let t = match member_types.get(i) {
+                Some(t) => t.clone(),
+                None => self.fresh(),
+            };
+            self.locals.push((member.clone(), Scheme::monomorphic(t)));
+        }
+        let result = self.infer(body, diagnostics);
+        self.locals.truncate(self.locals.len() - members.len());
+        (data_type, result)
+    }
+
+    /// Find the record that a field is taken from, by the type of the value,
+    /// or by the field alone when only one record has it
+    fn find_record(
+        &mut self,
+        t: &Type,
+        field: &str,
+        diagnostics: &mut Diagnostics,
+    ) -> Option<Constructor> {
+        let t = self.resolve(t);
+        let candidates = self
+            .records
+            .iter()
+            .filter(|(type_name, record)| {
+                record.get_field_index(field).is_some()
+                    && match &t {
+                        Type::Named(name, _) => name == type_name,
+                        _ => true,
+                    }
+            })
+            .map(|(_, record)| record.clone())
+            .collect::<Vec<_>>();
+
+        match (&t, candidates.as_slice()) {
+            (_, [record]) => Some(record.clone()),
+            (Type::Named(name, _), [])
+                if self
+                    .records
+                    .iter()
+                    .any(|(_, record)| record.get_field_index(field).is_some()) =>
+            {
+                diagnostics.error(
+                    UNKNOWN_FIELD,
+                    format!("`{}` has no field named `{}`", name, field),
+                );
+                None
+            }
+            // Fields that no record has are reported when records are checked
+            (_, []) => None,
+            _ => {
+                diagnostics.error(
+                    AMBIGUOUS_FIELD,
+                    format!(
+                        "ambiguous type: cannot tell which record the field `{}` belongs to",
+                        field
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    /// Make a copy of the type of a record's constructor,
+    /// as the types of its fields and the type it builds
+    fn instantiate_record(&mut self, record: &Constructor) -> (Vec<Type>, Type) {
+        let mut fields = Vec::new();
+        let mut t = self.lookup(record.get_name());
+        while let Type::Function(field, result) = t {
+            fields.push((*field).clone());
+            t = (*result).clone();
+        }
+        (fields, t)
+    }
+
+    fn infer(&mut self, expr: &Expression, diagnostics: &mut Diagnostics) -> Type {
+        // Expressions are inferred in the order of `Expression::children`
+        let position = self.position;
+        self.position += 1;
+        match expr {
+            Expression::Number(_) => Type::number(),
+            Expression::String(_) => Type::string(),
+            Expression::Identifier(name) => {
+                if self
+                    .locals
+                    .iter()
+                    .any(|(local, _)| local.get_name() == name)
+                {
+                    return self.lookup(name);
+                }
+                if self.group.contains(name) {
+                    self.recursive.push((self.site(position), name.clone()));
+                }
+                match self.globals.get(name).cloned() {
+                    Some(scheme) => {
+                        let (t, constraints) = self.instantiate_constrained(&scheme);
+                        for (class, constrained) in constraints {
+                            self.want(position, &class, constrained);
+                        }
+                        t
+                    }
+                    // Unbound variables are reported by the scope check
+                    None => self.fresh(),
+                }
+            }
+            Expression::Hole(name) => {
+                let t = self.fresh();
+                let mut scope: Vec<(Binder, Type)> = Vec::new();
+                for (local, local_type) in self.locals.iter().rev() {
+                    if !local.starts_with('$') && !scope.iter().any(|(other, _)| **other == **local)
+                    {
+                        scope.push((local.clone(), local_type.get_body().clone()));
+                    }
+                }
+                scope.reverse();
+                self.holes.push(Hole {
+                    name: name.clone(),
+                    t: t.clone(),
+                    scope,
+                    span: self.span,
+                });
+                t
+            }
+            Expression::Located(span, a) => {
+                let from = diagnostics.len();
+                let outer = self.span.replace(*span);
+                let result = self.infer(a, diagnostics);
+                self.span = outer;
+                diagnostics.place(from, *span);
+                result
+            }
+
+            Expression::Annotated(a, annotation) => {
+                let expected = self.instantiate(&Scheme::annotation(annotation));
+                match (a.unlocated(), self.resolve(&expected)) {
+                    // Check the body of an annotated lambda with the annotated parameter in scope,
+                    // so that mismatches are found where the parameter is used
+                    (Expression::Lambda(arg, body), Type::Function(parameter, result)) => {
+                        // Skip the lambda, along with any location around it
+                        self.position += a.size() - body.size();
+                        let parameter = Scheme::monomorphic((*parameter).clone());
+                        self.locals.push((arg.clone(), parameter));
+                        let found = self.infer(body, diagnostics);
+                        self.locals.pop();
+                        self.expect(&result, &found, body.get_span(), diagnostics);
+                    }
+                    _ => {
+                        let found = self.infer(a, diagnostics);
+                        self.expect(&expected, &found, a.get_span(), diagnostics);
+                    }
+                }
+                expected
+            }
+
+            Expression::Lambda(arg, body) => {
+                let t = self.fresh();
+                self.locals
+                    .push((arg.clone(), Scheme::monomorphic(t.clone())));
+                let result = self.infer(body, diagnostics);
+                self.locals.pop();
+                Type::function(t, result)
+            }
+            Expression::Application(f, x) => {
+                let function = self.infer(f, diagnostics);
+                let from = diagnostics.len();
+                let result = self.apply(function, x, diagnostics);
+                if let Some(name) = self_application(f, x) {
+                    diagnostics.note(
+                        from,
+                        INFINITE_TYPE,
+                        format!("`{}` is applied to itself", name),
+                    );
+                    diagnostics.note(
+                        from,
+                        INFINITE_TYPE,
+                        "encodings from the untyped lambda calculus can be allowed \
+                         with `@allow(infinite-types)`",
+                    );
+                }
+                result
+            }
+            Expression::Let { name, value, body } => {
+                let wanted = self.wanted.len();
+                let t = self.infer(value, diagnostics);
+                let scheme = self.generalize_local(&t, wanted);
+                self.locals.push((name.clone(), scheme));
+                let result = self.infer(body, diagnostics);
+                self.locals.pop();
+                result
+            }
+            Expression::TailCall(args) => {
+                self.recursive
+                    .push((self.site(position), self.owner.clone()));
+                let mut result = self.lookup(Expression::RECURSION_ARGUMENT);
+                for arg in args {
+                    result = self.apply(result, arg, diagnostics);
+                }
+                result
+            }
+
+            Expression::Add(a, b) => {
+                let t = self.infer(a, diagnostics);
+                let u = self.infer(b, diagnostics);
+                self.expect(&t, &u, b.get_span(), diagnostics);
+                self.want(position, Class::ADD, t.clone());
+                t
+            }
+            Expression::Subtract(a, b) | Expression::Multiply(a, b) | Expression::Divide(a, b) => {
+                for operand in &[a, b] {
+                    let t = self.infer(operand, diagnostics);
+                    self.expect(&Type::number(), &t, operand.get_span(), diagnostics);
+                }
+                Type::number()
+            }
+            Expression::Negate(a) => {
+                let t = self.infer(a, diagnostics);
+                self.expect(&Type::number(), &t, a.get_span(), diagnostics);
+                Type::number()
+            }
+            Expression::Greater(a, b)
+            | Expression::GreaterEqual(a, b)
+            | Expression::Less(a, b)
+            | Expression::LessEqual(a, b) => {
+                let t = self.infer(a, diagnostics);
+                let u = self.infer(b, diagnostics);
+                self.expect(&t, &u, b.get_span(), diagnostics);
+                self.want(position, Class::ORD, t);
+                let choice = self.fresh();
+                Type::boolean(choice)
+            }
+            Expression::Equal(a, b) | Expression::NotEqual(a, b) => {
+                let t = self.infer(a, diagnostics);
+                let u = self.infer(b, diagnostics);
+                self.expect(&t, &u, b.get_span(), diagnostics);
+                self.want(position, Class::EQ, t);
+                let choice = self.fresh();
+                Type::boolean(choice)
+            }
+            Expression::And(a, b) | Expression::Or(a, b) => {
+                let choice = self.fresh();
+                let boolean = Type::boolean(choice);
+                for operand in &[a, b] {
+                    let t = self.infer(operand, diagnostics);
+                    self.expect(&boolean, &t, operand.get_span(), diagnostics);
+                }
+                boolean
+            }
+            Expression::Not(a) => {
+                let choice = self.fresh();
+                let boolean = Type::boolean(choice);
+                let t = self.infer(a, diagnostics);
+                self.expect(&boolean, &t, a.get_span(), diagnostics);
+                boolean
+            }
+
+            Expression::IfThenElse {
+                condition,
+                then_case,
+                else_case,
+            } => {
+                let result = self.fresh();
+                let t = self.infer(condition, diagnostics);
+                let boolean = Type::boolean(result.clone());
+                self.expect(&boolean, &t, condition.get_span(), diagnostics);
+                let t = self.infer(then_case, diagnostics);
+                self.expect(&result, &t, then_case.get_span(), diagnostics);
+                let t = self.infer(else_case, diagnostics);
+                self.expect(&result, &t, else_case.get_span(), diagnostics);
+                result
+            }
+            Expression::CaseOf { value, cases, .. } => {
+                let value_type = self.infer(value, diagnostics);
+                let result = self.fresh();
+                for (cons_name, members, body) in cases {
+                    let (data_type, t) = self.infer_pattern(cons_name, members, body, diagnostics);
+                    if let Some(data_type) = data_type {
+                        self.expect(&data_type, &value_type, value.get_span(), diagnostics);
+                    }
+                    self.expect(&result, &t, body.get_span(), diagnostics);
+                }
+                result
+            }
+            Expression::Match { values, arms } => {
+                let value_types = values
+                    .iter()
+                    .map(|value| self.infer(value, diagnostics))
+                    .collect::<Vec<_>>();
+                let result = self.fresh();
+                for (patterns, guard, body) in arms {
+                    let mut count = 0;
+                    for (pattern, value_type) in patterns.iter().zip(&value_types) {
+                        count += self.bind_pattern(pattern, value_type, diagnostics);
+                    }
+                    if let Some(guard) = guard {
+                        let t = self.infer(guard, diagnostics);
+                        let boolean = Type::boolean(result.clone());
+                        self.expect(&boolean, &t, guard.get_span(), diagnostics);
+                    }
+                    let t = self.infer(body, diagnostics);
+                    self.locals.truncate(self.locals.len() - count);
+                    self.expect(&result, &t, body.get_span(), diagnostics);
+                }
+                result
+            }
+            Expression::Construct {
+                cons_name, members, ..
+            } => {
+                let mut result = self.lookup(cons_name);
+                for member in members {
+                    result = self.apply(result, member, diagnostics);
+                }
+                result
+            }
+            Expression::Deconstruct {
+                cons_name,
+                members,
+                value,
+                body,
+                ..
+            } => {
+                let value_type = self.infer(value, diagnostics);
+                let (data_type, result) = self.infer_pattern(cons_name, members, body, diagnostics);
+                if let Some(data_type) = data_type {
+                    self.expect(&data_type, &value_type, value.get_span(), diagnostics);
+                }
+                result
+            }
+
+            Expression::Record(cons_name, fields) => {
+                let record = self
+                    .records
+                    .iter()
+                    .find(|(_, record)| record.get_name() == cons_name)
+                    .map(|(_, record)| record.clone());
+                let (field_types, result) = match &record {
+                    Some(record) => {
+                        self.fields.insert(self.site(position), record.clone());
+                        self.instantiate_record(record)
+                    }
+                    // Constructors without fields are reported when records are checked
+                    None => (Vec::new(), self.fresh()),
+                };
+                for (field, value) in fields {
+                    let t = self.infer(value, diagnostics);
+                    let index = record.as_ref().and_then(|r| r.get_field_index(field));
+                    if let Some(expected) = index.and_then(|i| field_types.get(i)) {
+                        self.expect(expected, &t, value.get_span(), diagnostics);
+                    }
+                }
+                result
+            }
+            // Dotted expressions are resolved once the program is parsed
+            Expression::Dotted { .. } => unreachable!(),
+            Expression::Field(value, field) => {
+                let t = self.infer(value, diagnostics);
+                match self.find_record(&t, field, diagnostics) {
+                    Some(record) => {
+                        let (field_types, result) = self.instantiate_record(&record);
+                        self.expect(&result, &t, value.get_span(), diagnostics);
+                        let index = record.get_field_index(field).unwrap_or(0);
+                        self.fields.insert(self.site(position), record);
+                        field_types[index].clone()
+                    }
+                    None => self.fresh(),
+                }
+            }
+            Expression::Update(value, fields) => {
+                let t = self.infer(value, diagnostics);
+                let record = match fields.first() {
+                    Some((field, _)) => self.find_record(&t, field, diagnostics),
+                    None => None,
+                };
+                let field_types = match &record {
+                    Some(record) => {
+                        let (field_types, result) = self.instantiate_record(record);
+                        self.expect(&result, &t, value.get_span(), diagnostics);
+                        self.fields.insert(self.site(position), record.clone());
+                        field_types
+                    }
+                    None => Vec::new(),
+                };
+                for (field, value) in fields {
+                    let found = self.infer(value, diagnostics);
+                    let record = match &record {
+                        Some(record) => record,
+                        None => continue,
+                    };
+                    match record.get_field_index(field) {
+                        Some(i) => {
+                            self.expect(&field_types[i], &found, value.get_span(), diagnostics)
+                        }
+                        None => diagnostics.error(
+                            UNKNOWN_FIELD,
+                            format!("`{}` has no field named `{}`", record.get_name(), field),
+                        ),
+                    }
+                }
+                t
+            }
+
+            Expression::List(items) => {
+                let item_type = self.fresh();
+                for item in items {
+                    let t = self.infer(item, diagnostics);
+                    self.expect(&item_type, &t, item.get_span(), diagnostics);
+                }
+                Type::Named(Type::LIST.to_string(), vec![item_type])
+            }
+            Expression::Table(map) => {
+                let value_type = self.fresh();
+                for value in map.values() {
+                    let t = self.infer(value, diagnostics);
+                    self.expect(&value_type, &t, value.get_span(), diagnostics);
+                }
+                Type::Named(Type::TABLE.to_string(), vec![value_type])
+            }
+        }
+    }
+}
+
+/// Find groups of mutually recursive declarations, with each group after the groups it uses
+pub(crate) fn dependency_order(declarations: &[(&String, &Expression)]) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        declarations: &'a [(&'a String, &'a Expression)],
+        index: Vec<Option<usize>>,
+        lowlink: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        next: usize,
+        groups: Vec<Vec<usize>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, v: usize) {
+            self.index[v] = Some(self.next);
+            self.lowlink[v] = self.next;
+            self.next += 1;
+            self.stack.push(v);
+            self.on_stack[v] = true;
+
+            for w in 0..self.declarations.len() {
+                if !self.declarations[v].1.has_binding(self.declarations[w].0) {
+                    continue;
+                }
+                match self.index[w] {
+                    None => {
+                        self.visit(w);
+                        self.lowlink[v] = self.lowlink[v].min(self.lowlink[w]);
+                    }
+                    Some(index) if self.on_stack[w] => {
+                        self.lowlink[v] = self.lowlink[v].min(index);
+                    }
+                    _ => {}
+                }
+            }
+
+            if Some(self.lowlink[v]) == self.index[v] {
+                let mut group = Vec::new();
+                while let Some(w) = self.stack.pop() {
+                    self.on_stack[w] = false;
+                    group.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                self.groups.push(group);
+            }
+        }
+    }
+
+    let n = declarations.len();
+    let mut tarjan = Tarjan {
+        declarations,
+        index: vec![None; n],
+        lowlink: vec![0; n],
+        stack: Vec::new(),
+        on_stack: vec![false; n],
+        next: 0,
+        groups: Vec::new(),
+    };
+    for v in 0..n {
+        if tarjan.index[v].is_none() {
+            tarjan.visit(v);
+        }
+    }
+    tarjan.groups
+}
+
+/// Replace a named type without parameters everywhere in a type annotation
+fn replace_named(t: &Type, name: &str, replacement: &Type) -> Type {
+    match t {
+        Type::Named(other, parameters) if other == name && parameters.is_empty() => {
+            replacement.clone()
+        }
+        Type::Named(other, parameters) => Type::Named(
+            other.clone(),
+            parameters
+                .iter()
+                .map(|p| replace_named(p, name, replacement))
+                .collect(),
+        ),
+        Type::Function(a, b) => Type::function(
+            replace_named(a, name, replacement),
+            replace_named(b, name, replacement),
+        ),
+        Type::Variable(_) => t.clone(),
+    }
+}
+
+/// Infer the type of every constructor, constant, and function in a program
+pub(crate) fn infer_types(ast: &AST, diagnostics: &mut Diagnostics) -> Vec<(String, Scheme)> {
+    infer_program(ast, diagnostics)
+        .signatures
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with('$'))
+        .collect()
+}
+
+/// Infer the type of every declaration in a program, and find the dictionaries
+/// that each use of an overloaded name or operator is given
+pub(crate) fn infer_program(ast: &AST, diagnostics: &mut Diagnostics) -> Inferred {
+    let mut inference = Inference::new();
+    let mut signatures = Vec::new();
+
+    // Imported items are defined outside of the program, so they could be anything
+    for import in ast.get_imports() {
+        for item in import.get_items() {
+            let t = inference.fresh();
+            let scheme = inference.generalize(&t);
+            inference.globals.insert(item.clone(), scheme);
+        }
+    }
+
+    // A data type is generic over its parameters, and a `type` declaration over the
+    // type variables of its members
+    let mut constructors = Vec::new();
+    let mut arities = BTreeMap::new();
+    for data in ast.get_data() {
+        check_members(data, diagnostics);
+        arities.insert(data.get_name().as_str(), data.get_parameters().len());
+        for cons in &data.cons {
+            constructors.push((
+                cons,
+                data.get_name().as_str(),
+                data.get_parameters().clone(),
+            ));
+        }
+    }
+    for cons in ast.get_constructors() {
+        let mut parameters = Vec::new();
+        for t in cons.get_types() {
+            type_variables(t, &mut parameters);
+        }
+        arities.insert(cons.get_name(), parameters.len());
+        constructors.push((cons, cons.get_name(), parameters));
+    }
+    for (cons, name, parameters) in constructors {
+        let parameters = parameters
+            .iter()
+            .map(|parameter| (parameter, inference.fresh()))
+            .collect::<Vec<_>>();
+        let mut t = Type::Named(
+            name.to_string(),
+            parameters.iter().map(|(_, t)| t.clone()).collect(),
+        );
+        for member in cons.get_types().iter().rev() {
+            t = Type::function(member_type(member, &parameters), t);
+        }
+
+        inference.declared.insert(name.to_string());
+        if cons.is_record() {
+            inference.records.push((name.to_string(), cons.clone()));
+        }
+        let scheme = inference.generalize(&t);
+        let name = cons.get_name().to_string();
+        inference.constructors.insert(name.clone(), scheme.clone());
+        inference.globals.insert(name.clone(), scheme.clone());
+        signatures.push((cons.get_span(), name, scheme));
+    }
+
+    // Each method is generic over the class parameter, which must have an instance of the class
+    let classes = ast.get_classes();
+    for class in &classes {
+        if class.is_builtin() {
+            inference.primitive.insert(class.get_name().clone());
+        }
+        for method in class.get_methods() {
+            let constraint = (class.get_name().clone(), class.get_parameter().clone());
+            let scheme = Scheme::constrained_annotation(method.get_annotation(), &[constraint]);
+            inference
+                .globals
+                .insert(method.get_name().clone(), scheme.clone());
+            if !class.is_builtin() {
+                signatures.push((method.get_span(), method.get_name().clone(), scheme));
+            }
+        }
+    }
+    for instance in ast.get_instances() {
+        let parameters = match instance.get_type() {
+            Type::Named(_, parameters) => parameters.as_slice(),
+            _ => &[],
+        };
+        let context = instance
+            .get_constraints()
+            .iter()
+            .filter_map(|(class, parameter)| {
+                let i = parameters
+                    .iter()
+                    .position(|other| *other == Type::named(parameter))?;
+                Some((class.clone(), i))
+            })
+            .collect();
+        inference.instances.insert(
+            (
+                instance.get_class().clone(),
+                instance.get_type_name().to_string(),
+            ),
+            context,
+        );
+    }
+
+    let mut declarations = Vec::new();
+    for constant in ast.get_constants() {
+        declarations.push((
+            constant.get_name().clone(),
+            constant.get_body(),
+            constant.get_span(),
+            false,
+        ));
+    }
+    for function in ast.get_functions() {
+        declarations.push((
+            function.get_name().clone(),
+            function.get_body(),
+            function.get_span(),
+            true,
+        ));
+    }
+
+    let mut annotations = BTreeMap::new();
+    for signature in ast.get_signatures() {
+        let name = signature.get_name();
+        if !declarations.iter().any(|(other, _, _, _)| other == name) {
+            let mut diagnostic = Diagnostic::error(
+                MISSING_DEFINITION,
+                format!("`{}` is annotated, but never defined", name),
+            );
+            if let Some(span) = signature.get_span() {
+                diagnostic = diagnostic.with_span(span);
+            }
+            diagnostics.push(diagnostic);
+        }
+
+        // Duplicate signatures are reported with the other duplicate definitions
+        annotations.entry(name.clone()).or_insert((
+            Scheme::constrained_annotation(signature.get_annotation(), signature.get_constraints()),
+            signature.get_span(),
+            format!("the type of `{}` is annotated here", name),
+        ));
+    }
+
+    // The methods of an instance have the types of the class methods,
+    // with the class parameter replaced by the type of the instance
+    for instance in ast.get_instances() {
+        let class = match classes
+            .iter()
+            .find(|c| c.get_name() == instance.get_class())
+        {
+            Some(class) => class,
+            // Unknown classes are reported when instances are checked
+            None => continue,
+        };
+        // Parameters left off of the instance type can be anything, but are the same
+        // everywhere in a method, so they are given names that cannot be written
+        let mut instance_type = instance.get_type().clone();
+        if let Type::Named(name, parameters) = &mut instance_type {
+            let arity = arities.get(name.as_str()).copied().unwrap_or(0);
+            while parameters.len() < arity {
+                parameters.push(Type::named(format!("a${}", parameters.len())));
+            }
+        }
+        for function in instance.get_methods() {
+            let method = class
+                .get_methods()
+                .iter()
+                .find(|method| method.get_name() == function.get_name());
+            if let Some(method) = method {
+                let annotation = replace_named(
+                    method.get_annotation(),
+                    class.get_parameter(),
+                    &instance_type,
+                );
+                let name = method_name(
+                    class.get_name(),
+                    instance.get_type_name(),
+                    method.get_name(),
+                );
+                annotations.insert(
+                    name.clone(),
+                    (
+                        Scheme::constrained_annotation(&annotation, instance.get_constraints()),
+                        method.get_span(),
+                        format!(
+                            "the type of `{}` is declared by its class here",
+                            method.get_name()
+                        ),
+                    ),
+                );
+                declarations.push((name, function.get_body(), function.get_span(), true));
+            }
+        }
+    }
+
+    let dependencies = declarations
+        .iter()
+        .map(|(name, body, _, _)| (name, *body))
+        .collect::<Vec<_>>();
+    for group in dependency_order(&dependencies) {
+        // Declarations in the same group are not generic while their bodies are inferred,
+        // unless their type is annotated
+        inference.group.clear();
+        let mut types = Vec::new();
+        for i in &group {
+            let name = &declarations[*i].0;
+            let (scheme, t) = match annotations.get(name) {
+                Some((scheme, _, _)) => {
+                    // Annotated constraints are satisfied by dictionary parameters
+                    let given = scheme
+                        .skolemize_constraints()
+                        .into_iter()
+                        .map(|(class, t)| {
+                            let parameter = parameter_name(&class, &t);
+                            (class, t, parameter)
+                        })
+                        .collect::<Vec<_>>();
+                    inference.parameters.insert(
+                        name.clone(),
+                        given.iter().map(|(_, _, p)| p.clone()).collect(),
+                    );
+                    inference.given.insert(name.clone(), given);
+                    (scheme.clone(), scheme.skolemize())
+                }
+                None => {
+                    inference.group.push(name.clone());
+                    let t = inference.fresh();
+                    (Scheme::monomorphic(t.clone()), t)
+                }
+            };
+            inference.globals.insert(name.clone(), scheme);
+            types.push(t);
+        }
+
+        for (i, t) in group.iter().zip(&types) {
+            let (name, body, span, is_function) = &declarations[*i];
+            let from = diagnostics.len();
+            inference.owner = name.clone();
+            inference.position = 0;
+            // Only a function that calls itself has `rec` in scope
+            let recurses = *is_function
+                && (body.is_recursive()
+                    || body.has_binding(&String::from(Expression::RECURSION_ARGUMENT)));
+            if recurses {
+                // `rec` is bound by the declaration of the function
+                let mut rec = Binder::new(Expression::RECURSION_ARGUMENT);
+                if let Some(span) = span {
+                    rec = rec.with_span(*span);
+                }
+                inference.locals.push((rec, Scheme::monomorphic(t.clone())));
+            }
+            let found = inference.infer(body, diagnostics);
+            if let Some(mut diagnostic) = inference.mismatch(t, &found) {
+                if let Some((_, annotated, note)) = annotations.get(name) {
+                    diagnostic = diagnostic.with_note(note.clone(), *annotated);
+                }
+                diagnostics.push(diagnostic);
+            }
+            if recurses {
+                inference.locals.pop();
+            }
+            if let Some(span) = span {
+                diagnostics.place(from, *span);
+            }
+        }
+
+        // Constraints on the type variables of the group are passed to every declaration in it
+        let mut variables = Vec::new();
+        for name in &inference.group {
+            if let Some(i) = group.iter().position(|i| declarations[*i].0 == *name) {
+                variables.extend(inference.resolve(&types[i]).variables());
+            }
+        }
+        let constraints = inference.resolve_wanted(&variables, diagnostics);
+        inference.report_holes(diagnostics);
+        let parameters = constraints
+            .iter()
+            .map(|(class, v)| parameter_name(class, &Type::Variable(*v)))
+            .collect::<Vec<_>>();
+        for name in &inference.group {
+            inference
+                .parameters
+                .insert(name.clone(), parameters.clone());
+        }
+        for (site, name) in core::mem::take(&mut inference.recursive) {
+            let passed = inference.parameters.get(&name).cloned().unwrap_or_default();
+            if !passed.is_empty() {
+                inference.dictionaries.insert(
+                    site,
+                    passed.into_iter().map(Dictionary::Parameter).collect(),
+                );
+            }
+        }
+
+        for (i, t) in group.iter().zip(&types) {
+            let (name, _, span, _) = &declarations[*i];
+            let scheme = match annotations.get(name) {
+                Some((scheme, _, _)) => scheme.clone(),
+                None => {
+                    let scheme = inference.generalize(t);
+                    let mut variables = scheme.get_variables().clone();
+                    for (_, v) in &constraints {
+                        if !variables.contains(v) {
+                            variables.push(*v);
+                        }
+                    }
+                    Scheme::new(variables, scheme.get_body().clone())
+                        .with_constraints(constraints.clone())
+                }
+            };
+            inference.globals.insert(name.clone(), scheme.clone());
+            signatures.push((*span, name.clone(), scheme));
+        }
+    }
+
+    // Spans count the input remaining after them, so earlier declarations have larger spans
+    signatures.sort_by_key(|(span, _, _)| Reverse(*span));
+    Inferred {
+        signatures: signatures
+            .into_iter()
+            .map(|(_, name, scheme)| (name, scheme))
+            .collect(),
+        dictionaries: inference.dictionaries,
+        parameters: inference.parameters,
+        fields: inference.fields,
+    }
+}
+
+/// Whether a name in a type annotation stands for a type variable, rather than a named type
+fn is_type_variable(name: &str, parameters: &[Type]) -> bool {
+    parameters.is_empty() && name.starts_with(|c: char| c.is_lowercase() || c == '_')
+}
+
+/// The names of the type variables in a type annotation, in the order they first appear
+fn type_variables(t: &Type, names: &mut Vec<String>) {
+    match t {
+        Type::Named(name, parameters) if is_type_variable(name, parameters) => {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        Type::Named(_, parameters) => {
+            for parameter in parameters {
+                type_variables(parameter, names);
+            }
+        }
+        Type::Function(a, b) => {
+            type_variables(a, names);
+            type_variables(b, names);
+        }
+        Type::Variable(_) => {}
+    }
+}
+
+/// The type of a member of a constructor, with the parameters of its data type replaced by their types.
+/// Any other type variable was reported by `check_members`, and stays a type of its own.
+fn member_type(t: &Type, parameters: &[(&String, Type)]) -> Type {
+    match t {
+        Type::Named(name, arguments) if is_type_variable(name, arguments) => parameters
+            .iter()
+            .find(|(parameter, _)| *parameter == name)
+            .map(|(_, t)| t.clone())
+            .unwrap_or_else(|| t.clone()),
+        Type::Named(name, arguments) => Type::Named(
+            name.clone(),
+            arguments
+                .iter()
+                .map(|argument| member_type(argument, parameters))
+                .collect(),
+        ),
+        Type::Function(a, b) => {
+            Type::function(member_type(a, parameters), member_type(b, parameters))
+        }
+        Type::Variable(_) => t.clone(),
+    }
+}
+
+/// Report the type variables in the members of a data type's constructors that are not
+/// parameters of the data type, since nothing says what type of value they stand for
+fn check_members(data: &Data, diagnostics: &mut Diagnostics) {
+    let mut unknown = Vec::new();
+    for cons in &data.cons {
+        for t in cons.get_types() {
+            type_variables(t, &mut unknown);
+        }
+    }
+    unknown.retain(|name| !data.get_parameters().contains(name));
+    if unknown.is_empty() {
+        return;
+    }
+
+    let names = unknown
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>();
+    let (names, verb, declare) = match names.split_last() {
+        Some((last, [])) => (last.clone(), "is not a type parameter", "it as a parameter"),
+        Some((last, rest)) => (
+            format!("{} and {}", rest.join(", "), last),
+            "are not type parameters",
+            "them as parameters",
+        ),
+        None => unreachable!(),
+    };
+    let declared = data.clone().with_parameters(
+        data.get_parameters()
+            .iter()
+            .chain(&unknown)
+            .cloned()
+            .collect(),
+    );
+
+    let mut diagnostic = Diagnostic::error(
+        UNKNOWN_TYPE_PARAMETER,
+        format!("{} {} of `{}`", names, verb, data.get_name()),
+    )
+    .with_note(
+        format!(
+            "declare {}, or give the members types like `Num`: `{}`",
+            declare, declared
+        ),
+        None,
+    );
+    if let Some(span) = data.get_span() {
+        diagnostic = diagnostic.with_span(span);
+    }
+    diagnostics.push(diagnostic);
+}
+
+/// The name of the variable that an application applies to itself, as in `f x f`
+fn self_application<'a>(function: &Expression, argument: &'a Expression) -> Option<&'a String> {
+    let mut head = function.unlocated();
+    while let Expression::Application(f, _) = head {
+        head = f.unlocated();
+    }
+    match (head, argument.unlocated()) {
+        (Expression::Identifier(f), Expression::Identifier(x)) if f == x => Some(x),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::testing::{compile, parse};
+
+    /// The signature of every declaration in a program, with the codes of the diagnostics reported
+    fn signatures(source: &str) -> (Vec<String>, Vec<&'static str>) {
+        let mut diagnostics = Diagnostics::new();
+        let signatures = parse(source)
+            .infer_types(&mut diagnostics)
+            .into_iter()
+            .map(|(name, scheme)| format!("{} : {}", name, scheme))
+            .collect();
+        let codes = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.get_code().get_name())
+            .collect();
+        (signatures, codes)
+    }
+
+    const SIZE: &str = "class Size a where size : a -> Num\n\
+                        data Box a = Full(a) | Empty\n\
+                        instance Size Box where\n\
+                        \x20   let size b = case b of | Full(_a) => 1 | Empty => 0\n\
+                        let total a b = size a + size b\n";
+
+    #[test]
+    fn functions_are_generalized() {
+        let (signatures, codes) = signatures(
+            "let id x = x\n\
+             let compose f g x = f (g x)\n\
+             let twice f x = f (f x)\n\
+             let main _ = print (id 1) (id \"one\") (twice id 2)",
+        );
+        assert_eq!(
+            signatures,
+            [
+                "id : a -> a",
+                "compose : (a -> b) -> (c -> a) -> c -> b",
+                "twice : (a -> a) -> a -> a",
+                "main : a -> b",
+            ]
+        );
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
+
+    #[test]
+    fn overloaded_operators_are_constrained() {
+        let (signatures, _) = signatures("let double x = x + x\nlet main _ = print (double 2)");
+        assert_eq!(signatures[0], "double : Add a => a -> a");
+    }
+
+    #[test]
+    fn comparisons_are_ordered() {
+        let (inferred, codes) = signatures(
+            "let max a b = if a > b then a else b\n\
+             let main _ = print (max 1 2) (max \"a\" \"b\")",
+        );
+        assert_eq!(inferred[0], "max : Ord a => a -> a -> a");
+        assert!(codes.is_empty(), "{:?}", codes);
+
+        let (_, codes) = signatures("let main _ = print (if 1 < \"a\" then 1 else 2)");
+        assert_eq!(codes, [MISMATCHED_TYPES.get_name()]);
+    }
+
+    #[test]
+    fn comparisons_use_instances_of_ord() {
+        assert_eq!(
+            compile(
+                "data Size = Small | Large deriving (Ord)\n\
+                 let main _ = print (if Small < Large then \"smaller\" else \"larger\") \
+                 (if \"b\" <= \"a\" then \"before\" else \"after\")"
+            ),
+            (Some("K(print(\"smaller\")(\"after\"))".to_string()), vec![])
+        );
+    }
+
+    #[test]
+    fn infinite_types_are_warnings() {
+        let source = "const True = a._b.a\n\
+                      const False = _a.b.b\n\
+                      let and a b = a b a\n\
+                      let main _ = print (and True False \"true\" \"false\")";
+        assert_eq!(
+            compile(source),
+            (
+                Some("K(print(\"false\"))".to_string()),
+                vec![INFINITE_TYPE.get_name()]
+            )
+        );
+
+        let denied = source.replace("let and", "@deny(infinite-types)\nlet and");
+        assert_eq!(compile(&denied), (None, vec![INFINITE_TYPE.get_name()]));
+        let allowed = source.replace("let and", "@allow(infinite-types)\nlet and");
+        assert_eq!(
+            compile(&allowed),
+            (Some("K(print(\"false\"))".to_string()), vec![])
+        );
+    }
+
+    #[test]
+    fn annotations_are_checked() {
+        let (inferred, codes) = signatures(
+            "let add : Num -> Num -> Num\n\
+             let add a b = a + b\n\
+             const NAME : String\n\
+             const NAME = \"ramify\"\n\
+             let main _ = print (add 1 2) NAME",
+        );
+        assert_eq!(&inferred[..2], ["add : Num -> Num -> Num", "NAME : String"]);
+        assert!(codes.is_empty(), "{:?}", codes);
+
+        // Annotations can be more specific than the inferred type, but not more general
+        let (inferred, codes) =
+            signatures("let id : Num -> Num\nlet id x = x\nlet main _ = print (id 1)");
+        assert_eq!(inferred[0], "id : Num -> Num");
+        assert!(codes.is_empty(), "{:?}", codes);
+        let (_, codes) =
+            signatures("let twice : a -> a\nlet twice x = x + 1\nlet main _ = print (twice 1)");
+        assert_eq!(codes, [MISMATCHED_TYPES.get_name()]);
+    }
+
+    #[test]
+    fn mismatches_note_the_annotation() {
+        let mut diagnostics = Diagnostics::new();
+        parse("let name : String\nlet name _ = 1\nlet main _ = print (name 0)")
+            .infer_types(&mut diagnostics);
+        let notes = diagnostics
+            .iter()
+            .flat_map(|diagnostic| diagnostic.get_notes())
+            .map(|(note, _)| note.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(notes, ["the type of `name` is annotated here"]);
+    }
+
+    #[test]
+    fn annotated_parameters() {
+        let (inferred, codes) = signatures(
+            "let greet (name : String) = \"hello, \" + name\n\
+             let main _ = print (greet \"world\")",
+        );
+        assert_eq!(inferred[0], "greet : String -> String");
+        assert!(codes.is_empty(), "{:?}", codes);
+
+        let (_, codes) = signatures("let main _ = print (((n : Num). n) \"one\")");
+        assert_eq!(codes, [MISMATCHED_TYPES.get_name()]);
+    }
+
+    #[test]
+    fn annotations_without_definitions() {
+        let (_, codes) = signatures(
+            "let area : Num -> Num\n\
+             let aera r = 3 * r * r\n\
+             let main _ = print (aera 1)",
+        );
+        assert_eq!(codes, [MISSING_DEFINITION.get_name()]);
+    }
+
+    /// The message of each diagnostic reported while inferring a program, followed by its notes
+    fn messages(source: &str) -> Vec<String> {
+        let mut diagnostics = Diagnostics::new();
+        parse(source).infer_types(&mut diagnostics);
+        diagnostics
+            .iter()
+            .flat_map(|diagnostic| {
+                core::iter::once(diagnostic.get_message().to_string())
+                    .chain(diagnostic.get_notes().iter().map(|(note, _)| note.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn holes_report_their_type_and_scope() {
+        assert_eq!(
+            messages(
+                "data Shape = Circle(Num) | Rectangle(Num, Num)\n\
+                 let area shape = case shape of\n\
+                 | Circle(r) => 3.14 * r * r\n\
+                 | Rectangle(w, h) => ?todo\n\
+                 let main _ = print (area (Circle(1)))"
+            ),
+            [
+                "found the hole `?todo`, of type `Num`",
+                "`shape : Shape` is in scope",
+                "`w : Num` is in scope",
+                "`h : Num` is in scope",
+            ]
+        );
+    }
+
+    #[test]
+    fn holes_stop_the_program_where_they_are_allowed() {
+        let source = "let half n = if n > 0 then n / 2 else ?negative\n\
+                      let main _ = print (half 4)";
+        assert_eq!(compile(source), (None, vec![TYPED_HOLE.get_name()]));
+
+        let warned = source.replace("let half", "@warn(typed-holes)\nlet half");
+        assert_eq!(
+            compile(&warned),
+            (
+                Some("K(print(2.0))".to_string()),
+                vec![TYPED_HOLE.get_name()]
+            )
+        );
+        let reached = warned.replace("half 4", "half (0 - 4)");
+        assert_eq!(
+            compile(&reached),
+            (
+                Some("K(print(hole(\"?negative\")))".to_string()),
+                vec![TYPED_HOLE.get_name()]
+            )
+        );
+    }
+
+    #[test]
+    fn only_recursive_functions_have_rec_in_scope() {
+        assert_eq!(
+            messages("let count n = if n <= 0 then ?done else 1 + rec (n - 1)")[..3],
+            [
+                "found the hole `?done`, of type `Num`",
+                "`rec : Num -> Num` is in scope",
+                "`n : Num` is in scope",
+            ]
+        );
+        assert_eq!(
+            messages("let count n = ?done"),
+            ["found the hole `?done`, of type `a`", "`n : b` is in scope"]
+        );
+    }
+
+    #[test]
+    fn guards_are_booleans() {
+        assert_eq!(
+            messages("let sign n = case n of | k if k + 1 => 1 | _ => 0")[0],
+            "mismatched types: expected `a -> a -> a`, found `Num`"
+        );
+    }
+
+    #[test]
+    fn mismatched_types() {
+        let (_, codes) = signatures("let main _ = print (1 + \"a\")");
+        assert_eq!(codes, [MISMATCHED_TYPES.get_name()]);
+    }
+
+    #[test]
+    fn missing_instances() {
+        let (_, codes) = signatures(
+            "data Point x y = Point(x, y)\n\
+             let main _ = print (show (Point(1, 2)))",
+        );
+        assert_eq!(codes, [NO_INSTANCE.get_name()]);
+    }
+
+    #[test]
+    fn parameters_type_members() {
+        let (signatures, codes) = signatures(
+            "data Result a e = Ok(a) | Err(e)\n\
+             type Pair(a, b)\n\
+             let main _ = print 1",
+        );
+        assert_eq!(
+            &signatures[..3],
+            [
+                "Ok : a -> Result a b",
+                "Err : a -> Result b a",
+                "Pair : a -> b -> Pair a b",
+            ]
+        );
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
+
+    #[test]
+    fn data_declarations_are_displayed_with_their_parameters() {
+        let ast = parse(
+            "data Result a e = Ok(a) | Err(e) deriving (Show)\n\
+             data Point = Point { x : Num, y : Num } | Origin\n\
+             data List a = Cons(a, List a) | Nil\n\
+             data Pair a b = Pair { a, b } | Map(a -> b)\n\
+             let main _ = print 1",
+        );
+        let declarations = ast
+            .get_data()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            declarations,
+            [
+                "data Result a e = Ok(a) | Err(e) deriving (Show)",
+                "data Point = Point { x : Num, y : Num } | Origin",
+                "data List a = Cons(a, List a) | Nil",
+                "data Pair a b = Pair { a, b } | Map(a -> b)",
+            ]
+        );
+    }
+
+    #[test]
+    fn members_that_are_not_parameters() {
+        let ast = parse("data Result = Ok(x) | Err(e)\nlet main _ = print 1");
+        let mut diagnostics = Diagnostics::new();
+        let inferred = ast.infer_types(&mut diagnostics);
+        // `x` is not generalized, so an `Ok` made from a number cannot be taken apart as a string
+        assert_eq!(inferred[0].1.to_string(), "x -> Result");
+
+        let diagnostic = diagnostics.iter().next().expect("an error");
+        assert_eq!(diagnostic.get_code(), UNKNOWN_TYPE_PARAMETER);
+        assert_eq!(
+            diagnostic.to_string().lines().next(),
+            Some("error[R0211]: `x` and `e` are not type parameters of `Result`")
+        );
+
+        let (_, codes) = signatures("data Point = Point { x, y }\nlet main _ = print 1");
+        assert_eq!(codes, ["R0211"]);
+    }
+
+    #[test]
+    fn members_have_types() {
+        let (inferred, codes) = signatures(
+            "data List a = Cons(a, List a) | Nil\n\
+             data Shape = Circle(Num) | Named(String, Shape)\n\
+             data Point = Point { x : Num, y : Num }\n\
+             type Pair(a, b)",
+        );
+        assert!(codes.is_empty());
+        for signature in [
+            "Cons : a -> List a -> List a",
+            "Circle : Num -> Shape",
+            "Named : String -> Shape -> Shape",
+            "Point : Num -> Num -> Point",
+            "Pair : a -> b -> Pair a b",
+        ] {
+            assert!(
+                inferred.iter().any(|s| s == signature),
+                "{} in {:?}",
+                signature,
+                inferred
+            );
+        }
+
+        let (_, codes) = signatures(
+            "data List a = Cons(a, List a) | Nil\n\
+             let main _ = print (Cons(1, Cons(\"two\", Nil)))",
+        );
+        assert_eq!(codes, ["R0202"]);
+        let (_, codes) = signatures(
+            "data Shape = Circle(Num)\n\
+             let main _ = print (Circle(\"round\"))",
+        );
+        assert_eq!(codes, ["R0202"]);
+    }
+
+    #[test]
+    fn constrained_declarations_take_dictionaries() {
+        let source = format!(
+            "{}let main _ = print (total (Full(2)) (Full(\"two\")))",
+            SIZE
+        );
+        let ast = parse(&source);
+        let inferred = infer_program(&ast, &mut Diagnostics::new());
+
+        let signature = inferred
+            .signatures
+            .iter()
+            .find(|(name, _)| name == "total")
+            .map(|(_, scheme)| scheme.to_string());
+        assert_eq!(
+            signature.as_deref(),
+            Some("(Size a, Size b) => a -> b -> Num")
+        );
+        // One dictionary parameter for each constraint
+        let parameters = &inferred.parameters["total"];
+        assert_eq!(parameters.len(), 2);
+        assert!(parameters
+            .iter()
+            .all(|parameter| parameter.starts_with("$dictionary$Size$")));
+
+        // `main` uses `total` on boxes, so it gives `total` the instance for `Box` twice
+        let mut given = inferred.dictionaries.values().flatten().collect::<Vec<_>>();
+        given.retain(|dictionary| {
+            **dictionary == Dictionary::Instance("$Size$Box".to_string(), Vec::new())
+        });
+        assert_eq!(given.len(), 2);
+    }
+
+    #[test]
+    fn uses_are_given_their_dictionaries() {
+        let source = format!(
+            "{}let main _ = print (total (Full(2)) (Full(\"two\")))",
+            SIZE
+        );
+        let mut ast = parse(&source);
+        ast.elaborate(&mut Diagnostics::new());
+        let body = |name: &str| {
+            ast.get_functions()
+                .iter()
+                .find(|function| function.get_name() == name)
+                .map(|function| format!("{:?}", function.get_body()))
+        };
+        // `main` gives `total` the instance for `Box` for each of its arguments
+        assert_eq!(
+            body("main").as_deref(),
+            Some("_.(print(total($Size$Box)($Size$Box)(Full(2, ))(Full(two, ))))")
+        );
+        // `total` passes its own dictionaries on to `size`
+        assert_eq!(
+            body("total").as_deref(),
+            Some(
+                "$dictionary$Size$10.$dictionary$Size$11.a.b.\
+                 ((size($dictionary$Size$10)(a) + size($dictionary$Size$11)(b)))"
+            )
+        );
+
+        assert_eq!(
+            compile(&source),
+            (Some("K(print(2.0))".to_string()), vec![])
+        );
+    }
+
+    #[test]
+    fn annotated_lambdas_are_given_their_dictionaries() {
+        let source = format!(
+            "{}let main _ = print (((b : Box Num). size b) (Full(1))) (total Empty Empty)",
+            SIZE
+        );
+        assert_eq!(
+            compile(&source),
+            (Some("K(print(1.0)(0.0))".to_string()), vec![])
+        );
+    }
+
+    #[test]
+    fn builtin_classes_use_primitive_operators() {
+        assert_eq!(
+            compile(
+                "let double x = x + x\n\
+                 let main _ = print (double 2) (double \"ab\")"
+            ),
+            (Some("K(print(4.0)(\"abab\"))".to_string()), vec![])
+        );
+    }
+}
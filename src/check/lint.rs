@@ -0,0 +1,357 @@
+use crate::{
+    ast::{Binder, Class, Expression, Pattern, AST},
+    code::{SHADOWED_NAME, UNUSED_DECLARATION, UNUSED_VARIABLE},
+    diagnostic::{Diagnostic, Diagnostics, Span},
+};
+
+use alloc::{
+    collections::BTreeSet,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Warn about unused variables and declarations, and about shadowed names
+pub(crate) fn check_lints(ast: &AST, diagnostics: &mut Diagnostics) {
+    for constant in ast.get_constants() {
+        let from = diagnostics.len();
+        check_expression(constant.get_body(), &mut Vec::new(), diagnostics);
+        if let Some(span) = constant.get_span() {
+            diagnostics.place(from, span);
+        }
+    }
+
+    for function in ast.get_functions() {
+        let from = diagnostics.len();
+        check_expression(function.get_body(), &mut Vec::new(), diagnostics);
+        if let Some(span) = function.get_span() {
+            diagnostics.place(from, span);
+        }
+    }
+
+    for method in ast.get_instance_methods() {
+        let from = diagnostics.len();
+        check_expression(method.get_body(), &mut Vec::new(), diagnostics);
+        if let Some(span) = method.get_span() {
+            diagnostics.place(from, span);
+        }
+    }
+
+    check_unused_declarations(ast, diagnostics);
+}
+
+/// Variables starting with an underscore are allowed to be unused
+fn is_ignored(name: &str) -> bool {
+    // Names starting with `$` are made up by the compiler, such as for parameters taken apart
+    name.starts_with('_') || name.starts_with('$')
+}
+
+/// Warn about a new variable that hides one in `scope`, at the name of the new variable
+fn check_shadowing(binder: &Binder, scope: &[Binder], diagnostics: &mut Diagnostics) {
+    if is_ignored(binder) {
+        return;
+    }
+
+    if let Some(outer) = scope
+        .iter()
+        .rev()
+        .find(|outer| outer.get_name() == binder.get_name())
+    {
+        let mut diagnostic = Diagnostic::warning(
+            SHADOWED_NAME,
+            format!("`{}` shadows a variable of the same name", binder),
+        )
+        .with_note(
+            format!("`{}` is first bound here", binder),
+            outer.get_span(),
+        );
+        if let Some(span) = binder.get_span() {
+            diagnostic = diagnostic.with_span(span);
+        }
+        diagnostics.push(diagnostic);
+    }
+}
+
+/// Warn about a new variable that is unused in `body`, or that hides one in `scope`
+fn check_binding(
+    binder: &Binder,
+    body: &Expression,
+    scope: &[Binder],
+    diagnostics: &mut Diagnostics,
+) {
+    if is_ignored(binder) {
+        return;
+    }
+
+    check_shadowing(binder, scope, diagnostics);
+
+    if !body.has_binding(binder) {
+        let mut diagnostic =
+            Diagnostic::warning(UNUSED_VARIABLE, format!("unused variable `{}`", binder));
+        if let Some(span) = binder.get_span() {
+            diagnostic = diagnostic.with_span(span);
+        }
+        diagnostics.push(diagnostic);
+    }
+}
+
+fn check_expression(expr: &Expression, scope: &mut Vec<Binder>, diagnostics: &mut Diagnostics) {
+    match expr {
+        Expression::Lambda(arg, body) => {
+            check_binding(arg, body, scope, diagnostics);
+            scope.push(arg.clone());
+            check_expression(body, scope, diagnostics);
+            scope.pop();
+        }
+        Expression::Deconstruct {
+            members,
+            value,
+            body,
+            ..
+        } => {
+            check_expression(value, scope, diagnostics);
+            for member in members {
+                check_binding(member, body, scope, diagnostics);
+            }
+            scope.extend(members.iter().cloned());
+            check_expression(body, scope, diagnostics);
+            scope.truncate(scope.len() - members.len());
+        }
+        Expression::Let { name, value, body } => {
+            check_expression(value, scope, diagnostics);
+            check_binding(name, body, scope, diagnostics);
+            scope.push(name.clone());
+            check_expression(body, scope, diagnostics);
+            scope.pop();
+        }
+        Expression::Match { values, arms } => {
+            for value in values {
+                check_expression(value, scope, diagnostics);
+            }
+            for (patterns, guard, body) in arms {
+                let variables = patterns
+                    .iter()
+                    .flat_map(Pattern::variables)
+                    .collect::<Vec<_>>();
+                for variable in &variables {
+                    // A variable that is only used by the guard is still used
+                    let used_by = match guard {
+                        Some(guard) if guard.has_binding(variable) => guard,
+                        _ => body,
+                    };
+                    check_binding(variable, used_by, scope, diagnostics);
+                }
+                scope.extend(variables.iter().map(|binder| (*binder).clone()));
+                if let Some(guard) = guard {
+                    check_expression(guard, scope, diagnostics);
+                }
+                check_expression(body, scope, diagnostics);
+                scope.truncate(scope.len() - variables.len());
+            }
+        }
+        Expression::CaseOf { value, cases, .. } => {
+            check_expression(value, scope, diagnostics);
+            for (_, members, case_body) in cases {
+                for member in members {
+                    check_binding(member, case_body, scope, diagnostics);
+                }
+                scope.extend(members.iter().cloned());
+                check_expression(case_body, scope, diagnostics);
+                scope.truncate(scope.len() - members.len());
+            }
+        }
+        Expression::Located(span, a) => {
+            let from = diagnostics.len();
+            check_expression(a, scope, diagnostics);
+            diagnostics.place(from, *span);
+        }
+        otherwise => {
+            for child in otherwise.children() {
+                check_expression(child, scope, diagnostics);
+            }
+        }
+    }
+}
+
+/// Warn about the top level functions and constants that cannot be reached from `main`.
+///
+/// Only the declarations that no other declaration uses are reported. The rest of the
+/// unreachable declarations are only used through them, and are noted with them.
+fn check_unused_declarations(ast: &AST, diagnostics: &mut Diagnostics) {
+    let mut declarations = Vec::new();
+    for constant in ast.get_constants() {
+        declarations.push((
+            constant.get_name(),
+            constant.get_body(),
+            constant.get_span(),
+        ));
+    }
+    for function in ast.get_functions() {
+        declarations.push((
+            function.get_name(),
+            function.get_body(),
+            function.get_span(),
+        ));
+    }
+    let count = declarations.len();
+
+    // Using a method can use the method of any instance
+    for method in ast.get_instance_methods() {
+        declarations.push((method.get_name(), method.get_body(), method.get_span()));
+    }
+
+    // Without an entry point, there is nothing to be reachable from
+    let main = String::from("main");
+    if !declarations.iter().any(|(name, _, _)| **name == main) {
+        return;
+    }
+
+    // Operators can use the methods of the builtin classes anywhere
+    let mut roots = vec![main];
+    for class in Class::builtins() {
+        for method in class.get_methods() {
+            roots.push(method.get_name().clone());
+        }
+    }
+    let reachable = reachable_from(roots, &declarations);
+
+    let unused = declarations
+        .iter()
+        .take(count)
+        .filter(|(name, _, _)| !reachable.contains(*name) && !is_ignored(name))
+        .collect::<Vec<_>>();
+    for (name, _, span) in &unused {
+        let used_by_others = unused
+            .iter()
+            .any(|(other, body, _)| other != name && body.has_binding(name));
+        if used_by_others {
+            continue;
+        }
+
+        let mut diagnostic = Diagnostic::warning(
+            UNUSED_DECLARATION,
+            format!("`{}` is never used by `main`", name),
+        );
+        let uses = reachable_from(vec![name.to_string()], &declarations)
+            .into_iter()
+            .filter(|other| other != *name && unused.iter().any(|(unused, _, _)| *unused == other))
+            .map(|other| format!("`{}`", other))
+            .collect::<Vec<_>>();
+        let note = match uses.split_last() {
+            Some((last, [])) => Some(format!("{}, which is", last)),
+            Some((last, rest)) => Some(format!("{} and {}, which are", rest.join(", "), last)),
+            None => None,
+        };
+        if let Some(note) = note {
+            diagnostic =
+                diagnostic.with_note(format!("it uses {} not used by `main` either", note), None);
+        }
+        if let Some(span) = span {
+            diagnostic = diagnostic.with_span(*span);
+        }
+        diagnostics.push(diagnostic);
+    }
+}
+
+/// The names of the declarations that can be reached from some others, including themselves
+fn reachable_from(
+    mut unvisited: Vec<String>,
+    declarations: &[(&String, &Expression, Option<Span>)],
+) -> BTreeSet<String> {
+    let mut reachable = BTreeSet::new();
+    while let Some(name) = unvisited.pop() {
+        if !reachable.insert(name.clone()) {
+            continue;
+        }
+
+        for (declaration, body, _) in declarations {
+            if **declaration == name {
+                for (other, _, _) in declarations {
+                    if !reachable.contains(*other) && body.has_binding(other) {
+                        unvisited.push(other.to_string());
+                    }
+                }
+            }
+        }
+    }
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::testing::parse;
+
+    /// The messages of the lints reported for a program, and of their notes
+    fn lints(source: &str) -> Vec<String> {
+        let mut diagnostics = Diagnostics::new();
+        check_lints(&parse(source), &mut diagnostics);
+        diagnostics
+            .iter()
+            .flat_map(|diagnostic| {
+                core::iter::once(diagnostic.get_message().to_string())
+                    .chain(diagnostic.get_notes().iter().map(|(note, _)| note.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unused_variables() {
+        assert_eq!(lints("let main x y = print y"), ["unused variable `x`"]);
+        assert_eq!(
+            lints("let main p = let Point(x, y) = p in print x"),
+            ["unused variable `y`"]
+        );
+        assert_eq!(
+            lints(
+                "data Shape = Circle(Num) | Square(Num)\n\
+                 let main s = case s of | Circle(r) => print 1 | Square(w) => print w"
+            ),
+            ["unused variable `r`"]
+        );
+        assert!(lints("let main _x = let Point(_a, b) = _x in print b").is_empty());
+    }
+
+    #[test]
+    fn shadowed_names() {
+        assert_eq!(
+            lints("let main x = print ((x. x) x)"),
+            [
+                "`x` shadows a variable of the same name",
+                "`x` is first bound here"
+            ]
+        );
+        assert_eq!(
+            lints(
+                "data Maybe a = Just(a) | Nothing\n\
+                 let main x = case x of | Just(x) => print x | Nothing => print 0"
+            ),
+            [
+                "`x` shadows a variable of the same name",
+                "`x` is first bound here"
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_roots_of_unused_declarations_are_reported() {
+        assert_eq!(
+            lints(
+                "const ONE = 1\n\
+                 let double x = x + x\n\
+                 let two _ = double ONE\n\
+                 let four _ = double (double ONE)\n\
+                 let _helper x = x\n\
+                 let main _ = print 3"
+            ),
+            [
+                "`two` is never used by `main`",
+                "it uses `ONE` and `double`, which are not used by `main` either",
+                "`four` is never used by `main`",
+                "it uses `ONE` and `double`, which are not used by `main` either",
+            ]
+        );
+
+        // A program without `main` is a library, whose declarations are used elsewhere
+        assert!(lints("let double x = x + x").is_empty());
+    }
+}
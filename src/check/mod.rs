@@ -0,0 +1,124 @@
+pub(crate) mod arity;
+pub(crate) mod attributes;
+pub(crate) mod cases;
+pub(crate) mod classes;
+pub(crate) mod deriving;
+pub(crate) mod duplicates;
+pub(crate) mod elaborate;
+pub(crate) mod infer;
+pub(crate) mod lint;
+pub(crate) mod patterns;
+pub(crate) mod records;
+pub(crate) mod scope;
+pub(crate) mod termination;
+
+use crate::{ast::AST, diagnostic::Diagnostics, types::Scheme};
+
+use alloc::{string::String, vec::Vec};
+
+impl AST {
+    /// Run the analyses that report problems in a program before it is lowered
+    pub fn check(&self, diagnostics: &mut Diagnostics) {
+        attributes::check_attributes(self, diagnostics);
+        duplicates::check_duplicates(self, diagnostics);
+        classes::check_instances(self, diagnostics);
+        scope::check_unbound_variables(self, diagnostics);
+        arity::check_member_counts(self, diagnostics);
+        records::check_records(self, diagnostics);
+        cases::check_case_arms(self, diagnostics);
+        lint::check_lints(self, diagnostics);
+    }
+
+    /// Infer the type of every constructor, constant, and function, in the order they are declared
+    pub fn infer_types(&self, diagnostics: &mut Diagnostics) -> Vec<(String, Scheme)> {
+        // Attributes set the levels of the lints reported while inferring
+        attributes::check_attributes(self, diagnostics);
+        let signatures = infer::infer_types(self, diagnostics);
+        diagnostics.apply_levels();
+        signatures
+    }
+}
+
+/// Helpers for testing the passes on whole programs
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::{
+        ast::AST, diagnostic::Diagnostics, parse::statements::parse_ast, reduce::expression::y,
+        reduce::Reduce, Combinator,
+    };
+    use alloc::{rc::Rc, string::String, vec::Vec};
+
+    pub(crate) fn parse(source: &str) -> AST {
+        let (rest, ast) = parse_ast(source).expect("the program should parse");
+        assert!(rest.is_empty(), "the program should parse up to {:?}", rest);
+        ast
+    }
+
+    /// Compile a program, giving the combinator that its `main` function evaluates to,
+    /// along with the codes of the diagnostics reported for it
+    pub(crate) fn compile(source: &str) -> (Option<String>, Vec<&'static str>) {
+        let (result, codes) = evaluate(source);
+        (result.map(|result| format!("{}", result)), codes)
+    }
+
+    /// Compile a program, like `compile`, without showing the combinator
+    pub(crate) fn evaluate(source: &str) -> (Option<Combinator>, Vec<&'static str>) {
+        let mut diagnostics = Diagnostics::new();
+        let result = parse(source)
+            .reduce(&(), &mut diagnostics)
+            .ok()
+            .map(|result| result.to_combinator());
+        let codes = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.get_code().get_name())
+            .collect();
+        (result, codes)
+    }
+
+    /// Run the recursion that a compiled program leaves for runtime,
+    /// the way the prelude of its output does
+    pub(crate) fn run(combinator: &Combinator) -> Combinator {
+        let apply = |function: Combinator, arguments: &[Combinator]| {
+            arguments
+                .iter()
+                .fold(function, |function, arg| function.applied_to(run(arg)))
+        };
+        let run_some = |x: &Option<Rc<Combinator>>| x.as_ref().map(|x| Rc::new(run(x)));
+        match combinator {
+            Combinator::Foreign { name, arguments } if name == "recurse" => apply(y(), arguments),
+            Combinator::Foreign { name, arguments } => apply(crate::foreign(name), arguments),
+            Combinator::Builtin {
+                name,
+                arity,
+                arguments,
+                function,
+            } => apply(
+                Combinator::Builtin {
+                    name: name.clone(),
+                    arity: *arity,
+                    arguments: Vec::new(),
+                    function: function.clone(),
+                },
+                arguments,
+            ),
+            Combinator::S { x, y } => Combinator::S {
+                x: run_some(x),
+                y: run_some(y),
+            },
+            Combinator::B { x, y } => Combinator::B {
+                x: run_some(x),
+                y: run_some(y),
+            },
+            Combinator::C { x, y } => Combinator::C {
+                x: run_some(x),
+                y: run_some(y),
+            },
+            Combinator::K { x } => Combinator::K { x: run_some(x) },
+            Combinator::Table(table) => {
+                Combinator::Table(table.iter().map(|(k, v)| (k.clone(), run(v))).collect())
+            }
+            Combinator::List(list) => Combinator::List(list.iter().map(run).collect()),
+            otherwise => otherwise.clone(),
+        }
+    }
+}
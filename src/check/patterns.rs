@@ -0,0 +1,490 @@
+use crate::ast::{Arm, Binder, Constructor, Expression, Pattern, AST};
+
+use alloc::{
+    collections::BTreeMap,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{cell::Cell, iter};
+
+/// What a decision tree gives when no arm matches.
+/// Cases that can reach it are reported before the program is lowered.
+const UNMATCHED: &str = "$unmatched";
+
+/// The constructors that patterns can match, grouped by the data type they build
+pub(crate) struct Patterns {
+    /// Each data type's constructors, and whether they are matched with `case` rather than `let`
+    families: Vec<(Vec<Constructor>, bool)>,
+    /// The number of variables made so far, so that none of them shadow each other
+    next: Cell<usize>,
+}
+
+/// A case compiled to a decision tree
+pub(crate) struct Compiled {
+    pub tree: Expression,
+    /// Whether each arm can be taken
+    pub reached: Vec<bool>,
+    /// Values that no arm matches, if there are any
+    pub missing: Option<Vec<Pattern>>,
+}
+
+/// An arm that could still be taken: the patterns it has left to match against variables,
+/// and the variables it has bound so far
+#[derive(Clone)]
+struct Row {
+    tests: Vec<(String, Pattern)>,
+    bindings: Vec<(String, String)>,
+    arm: usize,
+}
+
+impl Row {
+    /// Bind the variables and skip the wildcards of a row, leaving only the patterns that test
+    /// a value. An as-pattern binds its variable and leaves its inner pattern.
+    fn simplify(mut self) -> Self {
+        let mut tests = Vec::new();
+        let mut pending = self.tests.into_iter().rev().collect::<Vec<_>>();
+        while let Some((variable, pattern)) = pending.pop() {
+            match pattern {
+                Pattern::Wildcard => {}
+                Pattern::Variable(name) => self.bindings.push((name.get_name().clone(), variable)),
+                Pattern::As(name, inner) => {
+                    self.bindings
+                        .push((name.get_name().clone(), variable.clone()));
+                    pending.push((variable, (*inner).clone()));
+                }
+                other => tests.push((variable, other)),
+            }
+        }
+        self.tests = tests;
+        self
+    }
+
+    /// Bind the variables of the row around the body or guard of its arm
+    fn bind(&self, mut body: Rc<Expression>) -> Rc<Expression> {
+        for (name, variable) in self.bindings.iter().rev() {
+            let value = Rc::new(Expression::Identifier(variable.clone()));
+            body = bind(name.clone(), value, body);
+        }
+        body
+    }
+
+    /// The position of the pattern this row matches against a variable, if it has one
+    fn test_of(&self, variable: &str) -> Option<usize> {
+        self.tests.iter().position(|(other, _)| other == variable)
+    }
+
+    /// The row left once a variable is known to be built with a constructor,
+    /// or nothing if the row cannot match it
+    fn specialize(&self, variable: &str, cons_name: &str, members: &[String]) -> Option<Self> {
+        let i = match self.test_of(variable) {
+            Some(i) => i,
+            None => return Some(self.clone()),
+        };
+        match &self.tests[i].1 {
+            Pattern::Constructor(name, patterns) if name == cons_name => {
+                // Members with the wrong count are reported when member counts are checked
+                let nested = members
+                    .iter()
+                    .cloned()
+                    .zip(
+                        patterns
+                            .iter()
+                            .cloned()
+                            .chain(iter::repeat(Pattern::Wildcard)),
+                    )
+                    .collect::<Vec<_>>();
+                let mut row = self.clone();
+                row.tests.splice(i..=i, nested);
+                Some(row)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_literal(pattern: &Pattern) -> bool {
+    matches!(pattern, Pattern::Number(_) | Pattern::String(_))
+}
+
+/// Bind a name to a value
+///
+/// let x = v in body  ==>  (x. body) v
+fn bind(name: String, value: Rc<Expression>, body: Rc<Expression>) -> Rc<Expression> {
+    Rc::new(Expression::Application(
+        Rc::new(Expression::Lambda(name.into(), body)),
+        value,
+    ))
+}
+
+/// The state of compiling one case
+struct Compiler<'a> {
+    patterns: &'a Patterns,
+    arms: &'a [Arm],
+    /// The constructor each variable is known to be built with, on the way to the current branch
+    shapes: BTreeMap<String, (String, Vec<String>)>,
+    reached: Vec<bool>,
+    missing: Option<Vec<Pattern>>,
+    /// The variables that hold the values being matched
+    roots: Vec<String>,
+}
+
+impl Compiler<'_> {
+    /// A value that the current branch is taken for
+    fn witness(&self, variable: &str) -> Pattern {
+        match self.shapes.get(variable) {
+            Some((cons_name, members)) => Pattern::Constructor(
+                cons_name.clone(),
+                members.iter().map(|member| self.witness(member)).collect(),
+            ),
+            None => Pattern::Wildcard,
+        }
+    }
+
+    fn compile(&mut self, rows: Vec<Row>) -> Rc<Expression> {
+        let mut rows = rows.into_iter().map(Row::simplify).collect::<Vec<_>>();
+        let first = match rows.first() {
+            Some(first) => first.clone(),
+            None => {
+                if self.missing.is_none() {
+                    let missing = self.roots.iter().map(|root| self.witness(root)).collect();
+                    self.missing = Some(missing);
+                }
+                return Rc::new(Expression::Identifier(UNMATCHED.to_string()));
+            }
+        };
+
+        // The first arm that has nothing left to test is taken, unless its guard does not hold
+        let (variable, pattern) = match first.tests.first() {
+            Some(test) => test.clone(),
+            None => {
+                self.reached[first.arm] = true;
+                let (_, guard, body) = &self.arms[first.arm];
+                let body = first.bind(body.clone());
+                return match guard {
+                    // The arms after it are compiled without its variables, which could shadow theirs
+                    Some(guard) => {
+                        rows.remove(0);
+                        Rc::new(Expression::IfThenElse {
+                            condition: first.bind(guard.clone()),
+                            then_case: body,
+                            else_case: self.compile(rows),
+                        })
+                    }
+                    None => body,
+                };
+            }
+        };
+
+        match pattern {
+            Pattern::Constructor(cons_name, _) => self.switch(&variable, &cons_name, rows),
+            _ => self.compare(&variable, rows),
+        }
+    }
+
+    /// Take a variable apart with a `case` on its constructor, or a `let` if it only has one
+    fn switch(&mut self, variable: &str, cons_name: &str, rows: Vec<Row>) -> Rc<Expression> {
+        let family = self
+            .patterns
+            .families
+            .iter()
+            .find(|(family, _)| family.iter().any(|cons| cons.get_name() == cons_name));
+        let (family, is_data) = match family {
+            Some((family, is_data)) => (family, *is_data),
+            // Unknown constructors are reported when cases are checked, and match nothing
+            None => {
+                let rows = rows
+                    .into_iter()
+                    .filter(|row| match row.test_of(variable) {
+                        Some(i) => !matches!(
+                            &row.tests[i].1,
+                            Pattern::Constructor(name, _) if name == cons_name
+                        ),
+                        None => true,
+                    })
+                    .collect();
+                return self.compile(rows);
+            }
+        };
+
+        let mut branches = Vec::new();
+        for cons in family {
+            let members = cons
+                .get_members()
+                .iter()
+                .map(|_| self.patterns.variable())
+                .collect::<Vec<_>>();
+            let specialized = rows
+                .iter()
+                .filter_map(|row| row.specialize(variable, cons.get_name(), &members))
+                .collect();
+
+            self.shapes.insert(
+                variable.to_string(),
+                (cons.get_name().to_string(), members.clone()),
+            );
+            let body = self.compile(specialized);
+            self.shapes.remove(variable);
+            let members = members.into_iter().map(Binder::from).collect();
+            branches.push((cons.get_name().to_string(), members, body));
+        }
+
+        let value = Rc::new(Expression::Identifier(variable.to_string()));
+        if is_data {
+            return Rc::new(Expression::CaseOf {
+                data_type: None,
+                value,
+                cases: branches,
+            });
+        }
+        let (cons_name, members, body) = branches.remove(0);
+        Rc::new(Expression::Deconstruct {
+            data_type: None,
+            cons_name,
+            members,
+            value,
+            body,
+        })
+    }
+
+    /// Compare a variable with each literal that an arm matches it against, in order
+    fn compare(&mut self, variable: &str, rows: Vec<Row>) -> Rc<Expression> {
+        let mut literals = Vec::new();
+        for row in &rows {
+            if let Some(i) = row.test_of(variable) {
+                let pattern = &row.tests[i].1;
+                if is_literal(pattern) && !literals.contains(pattern) {
+                    literals.push(pattern.clone());
+                }
+            }
+        }
+
+        let mut branches = Vec::new();
+        for literal in &literals {
+            let matching = rows
+                .iter()
+                .filter_map(|row| match row.test_of(variable) {
+                    Some(i) if row.tests[i].1 == *literal => {
+                        let mut row = row.clone();
+                        row.tests.remove(i);
+                        Some(row)
+                    }
+                    Some(i) if is_literal(&row.tests[i].1) => None,
+                    _ => Some(row.clone()),
+                })
+                .collect();
+            branches.push(self.compile(matching));
+        }
+        let otherwise = rows
+            .into_iter()
+            .filter(|row| match row.test_of(variable) {
+                Some(i) => !is_literal(&row.tests[i].1),
+                None => true,
+            })
+            .collect();
+        let mut result = self.compile(otherwise);
+
+        for (literal, then_case) in literals.into_iter().zip(branches).rev() {
+            let literal = match literal {
+                Pattern::Number(n) => Expression::Number(n),
+                Pattern::String(s) => Expression::String(s),
+                _ => unreachable!(),
+            };
+            result = Rc::new(Expression::IfThenElse {
+                condition: Rc::new(Expression::Equal(
+                    Rc::new(Expression::Identifier(variable.to_string())),
+                    Rc::new(literal),
+                )),
+                then_case,
+                else_case: result,
+            });
+        }
+        result
+    }
+}
+
+impl Patterns {
+    pub(crate) fn new(ast: &AST) -> Self {
+        let mut families = ast
+            .get_data()
+            .iter()
+            .map(|data| (data.cons.clone(), true))
+            .collect::<Vec<_>>();
+        for cons in ast.get_constructors() {
+            families.push((vec![cons.clone()], false));
+        }
+        Self {
+            families,
+            next: Cell::new(0),
+        }
+    }
+
+    fn variable(&self) -> String {
+        let n = self.next.get();
+        self.next.set(n + 1);
+        format!("$match{}", n)
+    }
+
+    /// Compile the arms of a case, or the clauses of a function, to a decision tree,
+    /// which tests each part of the values at most once. Arms are tried in order,
+    /// so the first arm that matches the values is taken.
+    ///
+    /// A guard that does not hold falls through to the arms after it.
+    ///
+    /// case m of | Just(Ok(x)) => a | _ => b
+    ///   ==>  case m of
+    ///          | Just($match0) => case $match0 of | Ok($match1) => (x. a) $match1 | Err($match2) => b
+    ///          | Nothing => b
+    pub(crate) fn compile(&self, values: Vec<Rc<Expression>>, arms: &[Arm]) -> Compiled {
+        // A variable can be matched as it is, but anything else is only evaluated once
+        let roots = values
+            .iter()
+            .map(|value| match value.unlocated() {
+                Expression::Identifier(name) => name.clone(),
+                _ => self.variable(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut compiler = Compiler {
+            patterns: self,
+            arms,
+            shapes: BTreeMap::new(),
+            reached: vec![false; arms.len()],
+            missing: None,
+            roots: roots.clone(),
+        };
+        let rows = arms
+            .iter()
+            .enumerate()
+            .map(|(arm, (patterns, _, _))| Row {
+                tests: roots
+                    .iter()
+                    .cloned()
+                    .zip(patterns.iter().cloned())
+                    .collect(),
+                bindings: Vec::new(),
+                arm,
+            })
+            .collect();
+        let mut tree = compiler.compile(rows);
+        for (root, value) in roots.into_iter().zip(values).rev() {
+            if !matches!(value.unlocated(), Expression::Identifier(_)) {
+                tree = bind(root, value, tree);
+            }
+        }
+
+        Compiled {
+            tree: (*tree).clone(),
+            reached: compiler.reached,
+            missing: compiler.missing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::testing::{compile, parse};
+
+    const DATA: &str = "data Maybe a = Just(a) | Nothing\n\
+                        data Result a e = Ok(a) | Err(e)\n";
+
+    /// What `main` prints, given the declarations it uses
+    fn run(declarations: &str, printed: &str) -> Option<String> {
+        let source = format!("{}{}\nlet main _ = print {}", DATA, declarations, printed);
+        compile(&source).0
+    }
+
+    #[test]
+    fn nested_patterns() {
+        let value = "let value m = case m of\n\
+                     | Just(Ok(x)) => x\n\
+                     | Just(Err(_)) => 0 - 1\n\
+                     | Nothing => 0";
+        assert_eq!(
+            run(
+                value,
+                "(value (Just(Ok(4)))) (value (Just(Err(2)))) (value Nothing)"
+            )
+            .as_deref(),
+            Some("K(print(4.0)(-1.0)(0.0))")
+        );
+    }
+
+    #[test]
+    fn literals_guards_and_defaults() {
+        let name = "let name n = case n of\n\
+                    | 0 => \"zero\"\n\
+                    | k if k < 0 => \"negative\"\n\
+                    | _ => \"many\"";
+        assert_eq!(
+            run(name, "(name 0) (name (0 - 2)) (name 7)").as_deref(),
+            Some("K(print(\"zero\")(\"negative\")(\"many\"))")
+        );
+    }
+
+    #[test]
+    fn guards_fall_through_to_later_arms() {
+        let size = "data Shape = Circle(Num) | Square(Num)\n\
+                    let size s = case s of\n\
+                    | Circle(r) if r > 10 => \"big\"\n\
+                    | Square(w) if w > 10 => \"big\"\n\
+                    | Circle(_) => \"small circle\"\n\
+                    | _ => \"small\"";
+        assert_eq!(
+            run(
+                size,
+                "(size (Circle(20))) (size (Circle(2))) (size (Square(2)))"
+            )
+            .as_deref(),
+            Some("K(print(\"big\")(\"small circle\")(\"small\"))")
+        );
+    }
+
+    #[test]
+    fn first_matching_arm_is_taken() {
+        let which = "let which m = case m of\n\
+                     | Just(1) => \"one\"\n\
+                     | m @ Just(_) => \"other\"\n\
+                     | _ => \"none\"";
+        assert_eq!(
+            run(which, "(which (Just(1))) (which (Just(2))) (which Nothing)").as_deref(),
+            Some("K(print(\"one\")(\"other\")(\"none\"))")
+        );
+    }
+
+    #[test]
+    fn clauses_match_every_parameter() {
+        let both = "let both 0 0 = \"zeros\"\n\
+                    let both 0 _ = \"left\"\n\
+                    let both _ _ = \"neither\"";
+        assert_eq!(
+            run(both, "(both 0 0) (both 0 1) (both 1 0)").as_deref(),
+            Some("K(print(\"zeros\")(\"left\")(\"neither\"))")
+        );
+    }
+
+    #[test]
+    fn each_value_is_tested_once() {
+        let ast = parse(&format!(
+            "{}let value m = case m of\n\
+             | Just(Ok(x)) => x\n\
+             | Just(Err(e)) => e\n\
+             | Nothing => 0",
+            DATA
+        ));
+        let mut body = ast.get_functions()[0].get_body().unlocated();
+        while let Expression::Lambda(_, inner) = body {
+            body = inner.unlocated();
+        }
+        let Expression::Match { values, arms } = body else {
+            panic!("the case should have nested patterns");
+        };
+        let compiled = Patterns::new(&ast).compile(values.clone(), arms);
+        assert!(compiled.reached.iter().all(|&reached| reached));
+        assert!(compiled.missing.is_none());
+        let tree = format!("{:?}", compiled.tree);
+        assert_eq!(tree.matches("case (m)").count(), 1);
+        assert_eq!(tree.matches("| Ok(").count(), 1);
+    }
+}
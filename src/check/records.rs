@@ -0,0 +1,265 @@
+use crate::{
+    ast::{Constructor, Expression, AST},
+    code::{DUPLICATE_DEFINITION, MISSING_FIELD, UNKNOWN_CONSTRUCTOR, UNKNOWN_FIELD},
+    diagnostic::Diagnostics,
+};
+
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Report records built with missing, unknown, or repeated fields,
+/// and fields that no record declares
+pub(crate) fn check_records(ast: &AST, diagnostics: &mut Diagnostics) {
+    let mut constructors = Vec::new();
+    for data in ast.get_data() {
+        constructors.extend(data.cons.iter());
+    }
+    constructors.extend(ast.get_constructors().iter());
+
+    for constant in ast.get_constants() {
+        check_expression(constant.get_body(), &constructors, diagnostics);
+    }
+    for function in ast.get_functions() {
+        check_expression(function.get_body(), &constructors, diagnostics);
+    }
+    for method in ast.get_instance_methods() {
+        check_expression(method.get_body(), &constructors, diagnostics);
+    }
+}
+
+/// Report fields given more than once
+fn check_repeated(fields: &[(String, Rc<Expression>)], diagnostics: &mut Diagnostics) {
+    for (i, (field, _)) in fields.iter().enumerate() {
+        if fields[..i].iter().any(|(other, _)| other == field) {
+            diagnostics.error(
+                DUPLICATE_DEFINITION,
+                format!("the field `{}` is given more than once", field),
+            );
+        }
+    }
+}
+
+/// Report a field that no record declares
+fn check_declared(field: &str, constructors: &[&Constructor], diagnostics: &mut Diagnostics) {
+    let declared = constructors
+        .iter()
+        .any(|cons| cons.get_field_index(field).is_some());
+    if !declared {
+        diagnostics.error(
+            UNKNOWN_FIELD,
+            format!("no record has a field named `{}`", field),
+        );
+    }
+}
+
+fn check_expression(
+    expr: &Expression,
+    constructors: &[&Constructor],
+    diagnostics: &mut Diagnostics,
+) {
+    match expr {
+        Expression::Record(cons_name, fields) => {
+            match constructors
+                .iter()
+                .find(|cons| cons.get_name() == cons_name)
+            {
+                Some(record) if record.is_record() => {
+                    check_repeated(fields, diagnostics);
+                    for (field, _) in fields {
+                        if record.get_field_index(field).is_none() {
+                            diagnostics.error(
+                                UNKNOWN_FIELD,
+                                format!("`{}` has no field named `{}`", cons_name, field),
+                            );
+                        }
+                    }
+                    let missing = record
+                        .get_members()
+                        .iter()
+                        .filter(|member| !fields.iter().any(|(field, _)| field == *member))
+                        .map(|member| format!("`{}`", member))
+                        .collect::<Vec<_>>();
+                    match missing.as_slice() {
+                        [] => {}
+                        [field] => diagnostics.error(
+                            MISSING_FIELD,
+                            format!("`{}` is missing the field {}", cons_name, field),
+                        ),
+                        _ => diagnostics.error(
+                            MISSING_FIELD,
+                            format!(
+                                "`{}` is missing the fields {}",
+                                cons_name,
+                                missing.join(", ")
+                            ),
+                        ),
+                    }
+                }
+                Some(_) => diagnostics.error(
+                    UNKNOWN_FIELD,
+                    format!("`{}` is not a record, and has no named fields", cons_name),
+                ),
+                None => diagnostics.error(
+                    UNKNOWN_CONSTRUCTOR,
+                    format!("no constructor named `{}`", cons_name),
+                ),
+            }
+        }
+        Expression::Update(_, fields) => {
+            check_repeated(fields, diagnostics);
+            for (field, _) in fields {
+                check_declared(field, constructors, diagnostics);
+            }
+        }
+        Expression::Field(_, field) => check_declared(field, constructors, diagnostics),
+        Expression::Located(span, a) => {
+            let from = diagnostics.len();
+            check_expression(a, constructors, diagnostics);
+            diagnostics.place(from, *span);
+            return;
+        }
+        _ => {}
+    }
+
+    for child in expr.children() {
+        check_expression(child, constructors, diagnostics);
+    }
+}
+
+/// The name a field is bound to while its record is taken apart
+fn member_name(field: &str) -> String {
+    format!("${}", field)
+}
+
+fn construct(record: &Constructor, members: Vec<Rc<Expression>>) -> Expression {
+    Expression::Construct {
+        data_type: None,
+        cons_name: record.get_name().to_string(),
+        members,
+    }
+}
+
+fn deconstruct(record: &Constructor, value: Rc<Expression>, body: Expression) -> Expression {
+    Expression::Deconstruct {
+        data_type: None,
+        cons_name: record.get_name().to_string(),
+        members: record
+            .get_members()
+            .iter()
+            .map(|m| member_name(m).into())
+            .collect(),
+        value,
+        body: Rc::new(body),
+    }
+}
+
+/// Build a record, with its fields in the order they are declared
+///
+/// Point(1, 2)
+pub(crate) fn build(record: &Constructor, fields: &[(String, Rc<Expression>)]) -> Expression {
+    let members = record
+        .get_members()
+        .iter()
+        .filter_map(|member| fields.iter().find(|(field, _)| field == member))
+        .map(|(_, value)| value.clone())
+        .collect();
+    construct(record, members)
+}
+
+/// Take a record apart, and give the value of one of its fields
+///
+/// let Point($x, $y) = p in $x
+pub(crate) fn access(record: &Constructor, value: Rc<Expression>, field: &str) -> Expression {
+    deconstruct(record, value, Expression::Identifier(member_name(field)))
+}
+
+/// Take a record apart, and build it again with some of its fields replaced
+///
+/// let Point($x, $y) = p in Point(3, $y)
+pub(crate) fn update(
+    record: &Constructor,
+    value: Rc<Expression>,
+    fields: &[(String, Rc<Expression>)],
+) -> Expression {
+    let members = record
+        .get_members()
+        .iter()
+        .map(
+            |member| match fields.iter().find(|(field, _)| field == member) {
+                Some((_, value)) => value.clone(),
+                None => Rc::new(Expression::Identifier(member_name(member))),
+            },
+        )
+        .collect();
+    deconstruct(record, value, construct(record, members))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        check::testing::compile,
+        code::{DUPLICATE_DEFINITION, MISSING_FIELD, UNKNOWN_FIELD},
+    };
+    use alloc::string::ToString;
+
+    const POINT: &str = "type Point { x : Num, y : Num }\n";
+
+    #[test]
+    fn fields_are_read_and_updated() {
+        let source = format!(
+            "{}let main _ = let p = Point {{ y = 2, x = 1 }} in \
+             print p.x p.y (p {{ y = 5 }}).y (p {{ y = 5 }}).x",
+            POINT
+        );
+        assert_eq!(
+            compile(&source),
+            (Some("K(print(1.0)(2.0)(5.0)(1.0))".to_string()), vec![])
+        );
+    }
+
+    #[test]
+    fn fields_must_match_the_record() {
+        let codes = |record: &str| compile(&format!("{}let main _ = print {}.x", POINT, record)).1;
+        assert_eq!(codes("(Point { x = 1 })"), [MISSING_FIELD.get_name()]);
+        assert_eq!(
+            codes("(Point { x = 1, y = 2, z = 3 })"),
+            [UNKNOWN_FIELD.get_name()]
+        );
+        assert_eq!(
+            codes("(Point { x = 1, y = 2, x = 3 })"),
+            [DUPLICATE_DEFINITION.get_name()]
+        );
+        assert_eq!(
+            codes("((Point { x = 1, y = 2 }) { z = 3 })"),
+            [UNKNOWN_FIELD.get_name()]
+        );
+
+        // Fields of other records are found by inference
+        let source = format!(
+            "{}type Size {{ w : Num }}\nlet main _ = print (Point {{ x = 1, y = 2 }}).w",
+            POINT
+        );
+        assert_eq!(compile(&source).1, [UNKNOWN_FIELD.get_name()]);
+
+        // Members of constructors that are not records are not fields
+        let source = "type Pair(x, y)\nlet first p = p.x\nlet main _ = print (first Pair(1, 2))";
+        assert_eq!(compile(source), (None, vec![UNKNOWN_FIELD.get_name()]));
+    }
+
+    #[test]
+    fn dots_read_fields_of_variables_in_scope() {
+        // `p.x` reads a field when `p` is in scope, and is a lambda otherwise
+        let source = format!(
+            "{}let first x = (_p.x) 0\n\
+             let main _ = let p = Point {{ x = 3, y = 4 }} in print (first 5) p.x",
+            POINT
+        );
+        assert_eq!(
+            compile(&source),
+            (Some("K(print(5.0)(3.0))".to_string()), vec![])
+        );
+    }
+}
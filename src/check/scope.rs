@@ -0,0 +1,129 @@
+use crate::{
+    ast::{Expression, Pattern, AST},
+    code::UNBOUND_VARIABLE,
+    diagnostic::Diagnostics,
+};
+
+use alloc::{
+    collections::BTreeSet,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Every name that is bound at the top level of a program
+pub(crate) fn global_names(ast: &AST) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for builtin in Expression::BUILTINS {
+        names.insert(builtin.to_string());
+    }
+    for import in ast.get_imports() {
+        names.extend(import.get_items().iter().cloned());
+    }
+    for constant in ast.get_constants() {
+        names.insert(constant.get_name().clone());
+    }
+    for function in ast.get_functions() {
+        names.insert(function.get_name().clone());
+    }
+    for cons in ast.get_constructors() {
+        names.insert(cons.get_name().to_string());
+    }
+    for data in ast.get_data() {
+        for cons in &data.cons {
+            names.insert(cons.get_name().to_string());
+        }
+    }
+    for class in ast.get_classes() {
+        for method in class.get_methods() {
+            names.insert(method.get_name().clone());
+        }
+    }
+    names
+}
+
+/// Report every variable that is not bound by a parameter, a pattern, a `let`, or a declaration
+pub(crate) fn check_unbound_variables(ast: &AST, diagnostics: &mut Diagnostics) {
+    let globals = global_names(ast);
+
+    for constant in ast.get_constants() {
+        check_expression(constant.get_body(), &mut Vec::new(), &globals, diagnostics);
+    }
+    for function in ast.get_functions() {
+        check_expression(function.get_body(), &mut Vec::new(), &globals, diagnostics);
+    }
+    for method in ast.get_instance_methods() {
+        check_expression(method.get_body(), &mut Vec::new(), &globals, diagnostics);
+    }
+}
+
+fn check_expression(
+    expr: &Expression,
+    scope: &mut Vec<String>,
+    globals: &BTreeSet<String>,
+    diagnostics: &mut Diagnostics,
+) {
+    match expr {
+        Expression::Identifier(name) => {
+            if !scope.contains(name) && !globals.contains(name) {
+                diagnostics.error(UNBOUND_VARIABLE, format!("unbound variable `{}`", name));
+            }
+        }
+        Expression::Lambda(arg, body) => {
+            scope.push(arg.get_name().clone());
+            check_expression(body, scope, globals, diagnostics);
+            scope.pop();
+        }
+        Expression::Deconstruct {
+            members,
+            value,
+            body,
+            ..
+        } => {
+            check_expression(value, scope, globals, diagnostics);
+            scope.extend(members.iter().map(|member| member.get_name().clone()));
+            check_expression(body, scope, globals, diagnostics);
+            scope.truncate(scope.len() - members.len());
+        }
+        Expression::Let { name, value, body } => {
+            check_expression(value, scope, globals, diagnostics);
+            scope.push(name.get_name().clone());
+            check_expression(body, scope, globals, diagnostics);
+            scope.pop();
+        }
+        Expression::Match { values, arms } => {
+            for value in values {
+                check_expression(value, scope, globals, diagnostics);
+            }
+            for (patterns, guard, body) in arms {
+                let variables = patterns
+                    .iter()
+                    .flat_map(Pattern::variables)
+                    .collect::<Vec<_>>();
+                scope.extend(variables.iter().map(|name| name.get_name().clone()));
+                if let Some(guard) = guard {
+                    check_expression(guard, scope, globals, diagnostics);
+                }
+                check_expression(body, scope, globals, diagnostics);
+                scope.truncate(scope.len() - variables.len());
+            }
+        }
+        Expression::CaseOf { value, cases, .. } => {
+            check_expression(value, scope, globals, diagnostics);
+            for (_, members, case_body) in cases {
+                scope.extend(members.iter().map(|member| member.get_name().clone()));
+                check_expression(case_body, scope, globals, diagnostics);
+                scope.truncate(scope.len() - members.len());
+            }
+        }
+        Expression::Located(span, a) => {
+            let from = diagnostics.len();
+            check_expression(a, scope, globals, diagnostics);
+            diagnostics.place(from, *span);
+        }
+        otherwise => {
+            for child in otherwise.children() {
+                check_expression(child, scope, globals, diagnostics);
+            }
+        }
+    }
+}
@@ -0,0 +1,971 @@
+use crate::ast::{Binder, Expression};
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// How the size of an argument to a recursive call compares with a parameter
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Change {
+    Same,
+    Smaller,
+}
+
+/// The size-change graph of a recursive call.
+/// An edge from parameter `i` to argument `j` says how the `j`th argument compares with the `i`th parameter.
+type Graph = BTreeMap<(usize, usize), Change>;
+
+/// Follow one call with another
+fn compose(first: &Graph, second: &Graph) -> Graph {
+    let mut result = Graph::new();
+    for (&(i, j), &a) in first {
+        for (&(k, l), &b) in second {
+            if j == k {
+                let change = a.max(b);
+                let edge = result.entry((i, l)).or_insert(change);
+                *edge = (*edge).max(change);
+            }
+        }
+    }
+    result
+}
+
+/// A recursive call from one function of a group to another, or to itself
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Call {
+    from: usize,
+    to: usize,
+    graph: Graph,
+}
+
+/// The recursive calls of a group, and the largest magnitude that the numbers they are
+/// measured by can have, by the position of the function and of the parameter
+#[derive(Default)]
+struct Found {
+    calls: Vec<Call>,
+    limits: BTreeMap<(usize, usize), f64>,
+}
+
+/// What is known about the parameters at some point in the body of a function
+#[derive(Clone, Default)]
+struct Facts {
+    /// The functions of the group that are not shadowed, by their position in the group
+    functions: BTreeMap<String, usize>,
+    /// The parameters that are not shadowed, by their position
+    parameters: BTreeMap<String, usize>,
+    /// Variables bound to a part of a parameter, taken apart by a `case` or `let`
+    parts: BTreeMap<String, usize>,
+    /// Variables bound to a number that is smaller than a parameter, as with `measure`
+    measures: BTreeMap<String, (usize, f64)>,
+    /// Parameters that a condition has bounded from below
+    bounds: BTreeMap<usize, f64>,
+}
+
+impl Facts {
+    fn shadow(&mut self, name: &str) {
+        self.functions.remove(name);
+        self.parameters.remove(name);
+        self.parts.remove(name);
+        self.measures.remove(name);
+    }
+
+    fn parameter(&self, expr: &Expression) -> Option<usize> {
+        match expr.unlocated() {
+            Expression::Identifier(name) => self.parameters.get(name).copied(),
+            Expression::Annotated(a, _) => self.parameter(a),
+            _ => None,
+        }
+    }
+
+    /// How an argument compares with each of the parameters it is known to be related to
+    fn changes(&self, expr: &Expression) -> Vec<(usize, Change)> {
+        match expr.unlocated() {
+            Expression::Identifier(name) => match (self.parameters.get(name), self.parts.get(name))
+            {
+                (Some(&i), _) => vec![(i, Change::Same)],
+                (_, Some(&i)) => vec![(i, Change::Smaller)],
+                _ => Vec::new(),
+            },
+            Expression::Annotated(a, _) => self.changes(a),
+            Expression::Divide(_, _) | Expression::Subtract(_, _) => self
+                .measure(expr)
+                .map(|(i, _)| (i, Change::Smaller))
+                .into_iter()
+                .collect(),
+            // p.x, which takes a record apart to give one of its fields
+            Expression::Deconstruct {
+                members,
+                value,
+                body,
+                ..
+            } => match body.unlocated() {
+                Expression::Identifier(name) if members.iter().any(|m| m.get_name() == name) => {
+                    self.changes(value)
+                        .into_iter()
+                        .map(|(i, _)| (i, Change::Smaller))
+                        .collect()
+                }
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// The parameter that a number is smaller than, along with the largest magnitude that
+    /// parameter can have for the number to be smaller.
+    ///
+    /// Numbers only get smaller so many times while a condition keeps them above some bound,
+    /// but only while they are finite: `inf / 10 == inf`. `n - 1` is not smaller either when `n`
+    /// is too large for its ones to be represented, as in `1e20 - 1 == 1e20`. A function that
+    /// is measured by a number is only evaluated while compiling when the number is within
+    /// this limit, and is left to run at runtime otherwise.
+    ///
+    /// n / 2, where n > 0  ==>  n must be finite
+    /// n - 1, where n > 0  ==>  n must be at most 2^52
+    fn measure(&self, expr: &Expression) -> Option<(usize, f64)> {
+        let bounded = |a: &Expression| {
+            self.parameter(a)
+                .and_then(|i| Some((i, *self.bounds.get(&i)?)))
+        };
+        match expr.unlocated() {
+            Expression::Identifier(name) => self.measures.get(name).copied(),
+            Expression::Annotated(a, _) => self.measure(a),
+            Expression::Divide(a, b) => match (bounded(a), number(b)) {
+                (Some((i, bound)), Some(k)) if k >= 2.0 && bound > 0.0 => Some((i, f64::MAX)),
+                _ => None,
+            },
+            // Every number within `c * 2^52` of zero has a unit of least precision no
+            // larger than `c`, so taking `c` away always gives a smaller number
+            Expression::Subtract(a, b) => match (bounded(a), number(b)) {
+                (Some((i, _)), Some(c)) if c > 0.0 => Some((i, (c * 2f64.powi(52)).min(f64::MAX))),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Bind the members of a value that is taken apart
+    fn take_apart(&self, value: &Expression, members: &[Binder]) -> Self {
+        let mut inner = self.clone();
+        for member in members {
+            inner.shadow(member);
+        }
+        if let Some(&(i, _)) = self.changes(value).first() {
+            for member in members {
+                inner.parts.insert(member.get_name().clone(), i);
+            }
+        }
+        inner
+    }
+
+    /// Bind a name to a value, which it stands for in the same way
+    ///
+    /// (x. body) n
+    fn alias(&self, name: &str, value: &Expression) -> Self {
+        let mut inner = self.clone();
+        inner.shadow(name);
+        match self.changes(value).first() {
+            Some(&(i, Change::Same)) => inner.parameters.insert(name.to_string(), i),
+            Some(&(i, Change::Smaller)) => inner.parts.insert(name.to_string(), i),
+            None => None,
+        };
+        if let Some(measure) = self.measure(value) {
+            inner.measures.insert(name.to_string(), measure);
+        }
+        inner
+    }
+
+    /// Learn the lower bound that a condition puts on a parameter,
+    /// in the branch taken when the condition is `holds`
+    fn assume(&mut self, condition: &Expression, holds: bool) {
+        let bound = match (condition.unlocated(), holds) {
+            (Expression::Not(a), _) => return self.assume(a, !holds),
+            (Expression::And(a, b), true) | (Expression::Or(a, b), false) => {
+                self.assume(a, holds);
+                self.assume(b, holds);
+                return;
+            }
+            (Expression::Greater(a, b), true)
+            | (Expression::GreaterEqual(a, b), true)
+            | (Expression::Less(b, a), true)
+            | (Expression::LessEqual(b, a), true)
+            | (Expression::Less(a, b), false)
+            | (Expression::LessEqual(a, b), false)
+            | (Expression::Greater(b, a), false)
+            | (Expression::GreaterEqual(b, a), false) => (self.parameter(a), number(b)),
+            _ => return,
+        };
+
+        if let (Some(i), Some(k)) = bound {
+            let known = self.bounds.entry(i).or_insert(k);
+            *known = known.max(k);
+        }
+    }
+}
+
+fn number(expr: &Expression) -> Option<f64> {
+    match expr.unlocated() {
+        Expression::Number(n) => Some(*n),
+        Expression::Negate(a) => number(a).map(|n| -n),
+        _ => None,
+    }
+}
+
+/// The parameters of a function, and the body within them
+pub(crate) fn parameters(body: &Expression) -> (Vec<String>, &Expression) {
+    match body {
+        Expression::Lambda(arg, inner) => {
+            let (mut args, inner) = parameters(inner);
+            args.insert(0, arg.get_name().clone());
+            (args, inner)
+        }
+        Expression::Located(_, inner) | Expression::Annotated(inner, _) => parameters(inner),
+        otherwise => (Vec::new(), otherwise),
+    }
+}
+
+/// A function applied to its arguments
+///
+/// f a b  ==>  (f, [a, b])
+fn spine(expr: &Expression) -> (&Expression, Vec<&Rc<Expression>>) {
+    let mut args = Vec::new();
+    let mut head = expr.unlocated();
+    while let Expression::Application(f, x) = head {
+        args.push(x);
+        head = f.unlocated();
+    }
+    args.reverse();
+    (head, args)
+}
+
+/// The functions of a group are called from their own bodies either with `rec`, or by name
+fn callee<'a>(
+    expr: &'a Expression,
+    facts: &Facts,
+    from: usize,
+) -> Option<(usize, Vec<&'a Rc<Expression>>)> {
+    match expr {
+        Expression::TailCall(args) => Some((from, args.iter().collect())),
+        Expression::Application(_, _) | Expression::Identifier(_) => match spine(expr) {
+            (Expression::Identifier(name), args) => Some((*facts.functions.get(name)?, args)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Collect the size-change graph of every recursive call made by the `from`th function of a group,
+/// or give up on a call that does not pass every parameter
+fn calls(
+    expr: &Expression,
+    facts: &Facts,
+    from: usize,
+    arities: &[usize],
+    found: &mut Found,
+) -> bool {
+    if let Some((to, args)) = callee(expr, facts, from) {
+        if args.len() != arities[to] {
+            return false;
+        }
+        let mut graph = Graph::new();
+        for (j, arg) in args.iter().enumerate() {
+            for (i, change) in facts.changes(arg) {
+                let edge = graph.entry((i, j)).or_insert(change);
+                *edge = (*edge).max(change);
+            }
+            if let Some((i, limit)) = facts.measure(arg) {
+                let known = found.limits.entry((from, i)).or_insert(limit);
+                *known = known.min(limit);
+            }
+        }
+        found.calls.push(Call { from, to, graph });
+        return args
+            .into_iter()
+            .all(|arg| calls(arg, facts, from, arities, found));
+    }
+
+    match expr {
+        Expression::IfThenElse {
+            condition,
+            then_case,
+            else_case,
+        } => {
+            let (mut then_facts, mut else_facts) = (facts.clone(), facts.clone());
+            then_facts.assume(condition, true);
+            else_facts.assume(condition, false);
+            return calls(condition, facts, from, arities, found)
+                && calls(then_case, &then_facts, from, arities, found)
+                && calls(else_case, &else_facts, from, arities, found);
+        }
+        Expression::CaseOf { value, cases, .. } => {
+            return calls(value, facts, from, arities, found)
+                && cases.iter().all(|(_, members, body)| {
+                    let inner = facts.take_apart(value, members);
+                    calls(body, &inner, from, arities, found)
+                });
+        }
+        Expression::Deconstruct {
+            members,
+            value,
+            body,
+            ..
+        } => {
+            return calls(value, facts, from, arities, found)
+                && calls(
+                    body,
+                    &facts.take_apart(value, members),
+                    from,
+                    arities,
+                    found,
+                );
+        }
+        Expression::Application(f, value) => {
+            if let Expression::Lambda(arg, body) = f.unlocated() {
+                return calls(value, facts, from, arities, found)
+                    && calls(body, &facts.alias(arg, value), from, arities, found);
+            }
+        }
+        Expression::Lambda(arg, body) => {
+            let mut inner = facts.clone();
+            inner.shadow(arg);
+            return calls(body, &inner, from, arities, found);
+        }
+        _ => {}
+    }
+
+    expr.children()
+        .into_iter()
+        .all(|child| calls(child, facts, from, arities, found))
+}
+
+/// The largest magnitude that some parameters of each function of a group can have
+/// for the group to be sure to terminate, by the position of the parameter
+pub(crate) type Limits = Vec<BTreeMap<usize, f64>>;
+
+/// Prove that a group of functions that call themselves with `rec`, or each other by name,
+/// terminates, giving the limits that its numeric parameters must be within for the proof to hold.
+///
+/// This is the size-change principle: every call gives a graph of which arguments are smaller than,
+/// or the same size as, which parameters. If every way of repeating calls makes some parameter
+/// smaller on each repetition, the recursion cannot go on forever, because no value can get
+/// smaller forever. Numbers only get smaller when a condition keeps them above some bound,
+/// and only while they are within their limits.
+pub(crate) fn limits(group: &[(&String, &Expression)]) -> Option<Limits> {
+    let functions = group
+        .iter()
+        .enumerate()
+        .map(|(k, (name, _))| ((*name).clone(), k))
+        .collect::<BTreeMap<_, _>>();
+    let arities = group
+        .iter()
+        .map(|(_, body)| parameters(body).0.len())
+        .collect::<Vec<_>>();
+
+    let mut found = Found::default();
+    for (from, (_, body)) in group.iter().enumerate() {
+        let (params, inner) = parameters(body);
+        let mut facts = Facts {
+            functions: functions.clone(),
+            ..Facts::default()
+        };
+        for (i, param) in params.iter().enumerate() {
+            facts.shadow(param);
+            facts.parameters.insert(param.clone(), i);
+        }
+        if params.is_empty() || !calls(inner, &facts, from, &arities, &mut found) {
+            return None;
+        }
+    }
+
+    let mut closure = found.calls.iter().cloned().collect::<BTreeSet<_>>();
+    loop {
+        let mut added = Vec::new();
+        for first in &closure {
+            for second in found.calls.iter().filter(|second| second.from == first.to) {
+                let call = Call {
+                    from: first.from,
+                    to: second.to,
+                    graph: compose(&first.graph, &second.graph),
+                };
+                if !closure.contains(&call) {
+                    added.push(call);
+                }
+            }
+        }
+        if added.is_empty() {
+            break;
+        }
+        closure.extend(added);
+    }
+
+    let decreasing = closure.iter().all(|call| {
+        call.from != call.to
+            || compose(&call.graph, &call.graph) != call.graph
+            || (0..arities[call.from]).any(|i| call.graph.get(&(i, i)) == Some(&Change::Smaller))
+    });
+    if !decreasing {
+        return None;
+    }
+
+    let mut limits = vec![BTreeMap::new(); group.len()];
+    for ((from, i), limit) in found.limits {
+        limits[from].insert(i, limit);
+    }
+    Some(limits)
+}
+
+/// The variable that a delayed branch waits for before it is evaluated.
+/// Each branch has its own, so that none of them shadow each other.
+fn force(depth: usize) -> String {
+    format!("$force{}", depth)
+}
+
+/// Delay the branches of a recursive function until they are taken.
+///
+/// Every expression is evaluated as soon as its variables are known, so both branches of an `if`
+/// would be evaluated, and the recursion would never stop. Each branch is made to wait for one
+/// more argument, `$force`, which is only given to the branch that is taken. Recursive calls take
+/// `$force` before their other arguments, so that a call waits for its branch to be taken.
+///
+/// `false` applies its first argument to its second before ignoring it, so the branches of an `if`
+/// first skip that argument before waiting for `$force`.
+///
+/// if c then a else b  ==>  c ($skip.$force1.a) ($skip.$force1.b) $force0 $force0
+/// rec n-1             ==>  rec $force0 n-1
+fn delay(expr: &Rc<Expression>, depth: usize) -> Rc<Expression> {
+    let forced = || Rc::new(Expression::Identifier(force(depth)));
+    let branch = |body: &Rc<Expression>| {
+        Rc::new(Expression::Lambda(
+            force(depth + 1).into(),
+            delay(body, depth + 1),
+        ))
+    };
+    let skip = |body: Rc<Expression>| Rc::new(Expression::Lambda(Binder::new("$skip"), body));
+    Rc::new(match &**expr {
+        Expression::IfThenElse {
+            condition,
+            then_case,
+            else_case,
+        } => Expression::Application(
+            Rc::new(Expression::Application(
+                Rc::new(Expression::Application(
+                    Rc::new(Expression::Application(
+                        delay(condition, depth),
+                        skip(branch(then_case)),
+                    )),
+                    skip(branch(else_case)),
+                )),
+                forced(),
+            )),
+            forced(),
+        ),
+        Expression::CaseOf {
+            data_type,
+            value,
+            cases,
+        } => Expression::Application(
+            Rc::new(Expression::CaseOf {
+                data_type: data_type.clone(),
+                value: delay(value, depth),
+                cases: cases
+                    .iter()
+                    .map(|(cons_name, members, body)| {
+                        (cons_name.clone(), members.clone(), branch(body))
+                    })
+                    .collect(),
+            }),
+            forced(),
+        ),
+        Expression::TailCall(args) => {
+            let mut delayed = vec![forced()];
+            delayed.extend(args.iter().map(|arg| delay(arg, depth)));
+            Expression::TailCall(delayed)
+        }
+        otherwise => otherwise.map_children(|child| delay(child, depth)),
+    })
+}
+
+/// The name that the `k`th function of a group is given outside of its knot,
+/// to call when its parameters are outside of their limits
+fn fallback(k: usize) -> String {
+    format!("$fallback{}", k)
+}
+
+/// Check that the parameters of the `k`th function of a group are within their limits
+/// before going on with its body, and leave the call to run at runtime otherwise.
+/// `NaN` is within no limits.
+///
+/// let f n = body  ==>  let f n = if n <= 2^52 && n >= -2^52 then body else $fallback0 n
+fn guard(
+    body: Rc<Expression>,
+    params: &[String],
+    limits: &BTreeMap<usize, f64>,
+    k: usize,
+) -> Rc<Expression> {
+    let param = |i: usize| Rc::new(Expression::Identifier(params[i].clone()));
+    let within = limits.iter().map(|(&i, &limit)| {
+        Expression::And(
+            Rc::new(Expression::LessEqual(
+                param(i),
+                Rc::new(Expression::Number(limit)),
+            )),
+            Rc::new(Expression::GreaterEqual(
+                param(i),
+                Rc::new(Expression::Number(-limit)),
+            )),
+        )
+    });
+    let condition = match within.reduce(|a, b| Expression::And(Rc::new(a), Rc::new(b))) {
+        Some(condition) => condition,
+        None => return body,
+    };
+
+    let mut otherwise = Rc::new(Expression::Identifier(fallback(k)));
+    for i in 0..params.len() {
+        otherwise = Rc::new(Expression::Application(otherwise, param(i)));
+    }
+    Rc::new(Expression::IfThenElse {
+        condition: Rc::new(condition),
+        then_case: body,
+        else_case: otherwise,
+    })
+}
+
+/// Give each function of a group that has limits the function to call outside of them
+///
+/// fixpoint  ==>  ($fallback0.fixpoint) fallback_0
+fn with_fallbacks(
+    fixpoint: Expression,
+    limits: &Limits,
+    fallbacks: impl FnOnce() -> Vec<Expression>,
+) -> Expression {
+    if limits.iter().all(BTreeMap::is_empty) {
+        return fixpoint;
+    }
+    let mut result = fixpoint;
+    for (k, function) in fallbacks().into_iter().enumerate() {
+        if !limits[k].is_empty() {
+            result = Expression::Application(
+                Rc::new(Expression::Lambda(fallback(k).into(), Rc::new(result))),
+                Rc::new(function),
+            );
+        }
+    }
+    result
+}
+
+/// Tie the knot of a function that is sure to terminate, so that it can be evaluated while compiling.
+///
+/// let f a b = body  ==>  let f a b = Y (rec.$force0.a.b.body) 0 a b
+pub(crate) fn fixpoint(body: &Expression, limits: &BTreeMap<usize, f64>) -> Expression {
+    tie(body, limits, Expression::y_combinator())
+}
+
+/// Leave a function that calls itself with `rec` to recurse at runtime.
+///
+/// The program runs strictly at runtime too, so its branches are delayed in the same way.
+///
+/// let f a b = body  ==>  let f a b = $recurse (rec.$force0.a.b.body) 0 a b
+pub(crate) fn recursion(body: &Expression) -> Expression {
+    tie(
+        body,
+        &BTreeMap::new(),
+        Expression::Identifier(String::from(Expression::RUNTIME_RECURSION)),
+    )
+}
+
+/// Tie the knot of a function with a fixpoint combinator
+fn tie(body: &Expression, limits: &BTreeMap<usize, f64>, fix: Expression) -> Expression {
+    let (params, inner) = parameters(body);
+    let mut knot = delay(&guard(Rc::new(inner.clone()), &params, limits, 0), 0);
+    for param in params.iter().rev() {
+        knot = Rc::new(Expression::Lambda(param.clone().into(), knot));
+    }
+    let knot = Expression::Lambda(force(0).into(), knot);
+    let knot = Expression::Lambda(
+        Binder::new(Expression::RECURSION_ARGUMENT),
+        Rc::new(knot).resolve_tailcall(),
+    );
+
+    // Any value will do to force the body
+    let mut result = Expression::Application(
+        Rc::new(Expression::Application(Rc::new(fix), Rc::new(knot))),
+        Rc::new(Expression::Number(0.0)),
+    );
+    result = with_fallbacks(result, &vec![limits.clone()], || vec![recursion(body)]);
+    for param in &params {
+        result = Expression::Application(
+            Rc::new(result),
+            Rc::new(Expression::Identifier(param.clone())),
+        );
+    }
+
+    for param in params.iter().rev() {
+        result = Expression::Lambda(param.clone().into(), Rc::new(result));
+    }
+    result
+}
+
+/// The argument that picks the `k`th of `n` functions out of the knot they share.
+/// A function that only calls itself has nothing to pick.
+fn select(k: usize, n: usize) -> Vec<Rc<Expression>> {
+    if n == 1 {
+        return Vec::new();
+    }
+    let member = |k: usize| format!("$member{}", k);
+    let mut result = Rc::new(Expression::Identifier(member(k)));
+    for i in (0..n).rev() {
+        result = Rc::new(Expression::Lambda(member(i).into(), result));
+    }
+    vec![result]
+}
+
+/// Call the functions of a group the way the `from`th of them calls itself, with `rec`,
+/// giving each call the function to pick out of the knot before its arguments
+///
+/// is_odd (n - 1)  ==>  rec ($member0.$member1.$member1) (n - 1)
+fn name_calls(
+    expr: &Rc<Expression>,
+    functions: &BTreeMap<String, usize>,
+    from: usize,
+    n: usize,
+) -> Rc<Expression> {
+    let recurse = |child: &Rc<Expression>| name_calls(child, functions, from, n);
+    let shadowed = |names: &[Binder], child: &Rc<Expression>| {
+        let mut inner = functions.clone();
+        for name in names {
+            inner.remove(name.get_name());
+        }
+        name_calls(child, &inner, from, n)
+    };
+    let call = |to: usize, args: Vec<&Rc<Expression>>| {
+        let mut result = select(to, n);
+        result.extend(args.into_iter().map(recurse));
+        Rc::new(Expression::TailCall(result))
+    };
+
+    match &**expr {
+        Expression::TailCall(args) => call(from, args.iter().collect()),
+        Expression::Application(_, _) | Expression::Identifier(_) => match spine(expr) {
+            (Expression::Identifier(name), args) if functions.contains_key(name) => {
+                call(functions[name], args)
+            }
+            _ => Rc::new(expr.map_children(recurse)),
+        },
+        Expression::Lambda(arg, body) => Rc::new(Expression::Lambda(
+            arg.clone(),
+            shadowed(core::slice::from_ref(arg), body),
+        )),
+        Expression::Let { name, value, body } => Rc::new(Expression::Let {
+            name: name.clone(),
+            value: recurse(value),
+            body: shadowed(core::slice::from_ref(name), body),
+        }),
+        Expression::Deconstruct {
+            data_type,
+            cons_name,
+            members,
+            value,
+            body,
+        } => Rc::new(Expression::Deconstruct {
+            data_type: data_type.clone(),
+            cons_name: cons_name.clone(),
+            members: members.clone(),
+            value: recurse(value),
+            body: shadowed(members, body),
+        }),
+        Expression::CaseOf {
+            data_type,
+            value,
+            cases,
+        } => Rc::new(Expression::CaseOf {
+            data_type: data_type.clone(),
+            value: recurse(value),
+            cases: cases
+                .iter()
+                .map(|(cons_name, members, body)| {
+                    (cons_name.clone(), members.clone(), shadowed(members, body))
+                })
+                .collect(),
+        }),
+        _ => Rc::new(expr.map_children(recurse)),
+    }
+}
+
+/// Replace each call a function makes to itself by name with a `rec` call
+///
+/// let length list = case list of | Cons(_, rest) => 1 + length rest | Nil => 0
+///   ==>  let length list = case list of | Cons(_, rest) => 1 + rec rest | Nil => 0
+pub(crate) fn self_calls(name: &str, body: &Expression) -> Expression {
+    let functions = BTreeMap::from([(name.to_string(), 0)]);
+    (*name_calls(&Rc::new(body.clone()), &functions, 0, 1)).clone()
+}
+
+/// The body of each function of a group, shared by all of them in one knot.
+/// Each function checks its parameters against its limits, the branches of each function
+/// wait to be taken, as in a single fixpoint, and the knot takes `$force` before the
+/// function to pick.
+///
+/// A function of a group can call another outside of any branch, which a function calling
+/// itself cannot do and still terminate. So that such a call waits for the function to be
+/// called, each function forces its body with its first argument.
+///
+/// f a = body_f, g b = body_g  ==>  rec.$force0.$which.$which (a.($force1.body_f) a) (b.($force1.body_g) b)
+fn knot(group: &[(&String, &Expression)], limits: &Limits) -> Expression {
+    let functions = group
+        .iter()
+        .enumerate()
+        .map(|(k, (name, _))| ((*name).clone(), k))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut result = Rc::new(Expression::Identifier(String::from("$which")));
+    for (from, (_, body)) in group.iter().enumerate() {
+        let (params, inner) = parameters(body);
+        let mut functions = functions.clone();
+        for param in &params {
+            functions.remove(param);
+        }
+        let mut member = name_calls(&Rc::new(inner.clone()), &functions, from, group.len());
+        member = guard(member, &params, &limits[from], from);
+        member = Rc::new(Expression::Application(
+            Rc::new(Expression::Lambda(force(1).into(), delay(&member, 1))),
+            Rc::new(Expression::Identifier(params[0].clone())),
+        ));
+        for param in params.iter().rev() {
+            member = Rc::new(Expression::Lambda(param.clone().into(), member));
+        }
+        result = Rc::new(Expression::Application(result, member));
+    }
+
+    let result = Expression::Lambda(Binder::new("$which"), result);
+    let result = Expression::Lambda(force(0).into(), Rc::new(result));
+    Expression::Lambda(
+        Binder::new(Expression::RECURSION_ARGUMENT),
+        Rc::new(result).resolve_tailcall(),
+    )
+}
+
+/// Pick each function of a group out of the fixpoint of their knot
+///
+/// f a  ==>  f a = fix knot ($member0.$member1.$member0) a
+fn pick(group: &[(&String, &Expression)], fixpoint: Expression) -> Vec<Expression> {
+    let fixpoint = Rc::new(fixpoint);
+    group
+        .iter()
+        .enumerate()
+        .map(|(k, (_, body))| {
+            let (params, _) = parameters(body);
+            let mut result = fixpoint.clone();
+            for arg in select(k, group.len()) {
+                result = Rc::new(Expression::Application(result, arg));
+            }
+            for param in &params {
+                result = Rc::new(Expression::Application(
+                    result,
+                    Rc::new(Expression::Identifier(param.clone())),
+                ));
+            }
+            for param in params.iter().rev() {
+                result = Rc::new(Expression::Lambda(param.clone().into(), result));
+            }
+            (*result).clone()
+        })
+        .collect()
+}
+
+/// Tie the knot of a group of functions that call each other and are sure to terminate,
+/// so that they can be evaluated while compiling
+///
+/// f a  ==>  f a = Y (rec.$force0.$which.$which (a.body_f) (b.body_g)) 0 ($member0.$member1.$member0) a
+pub(crate) fn group_fixpoint(group: &[(&String, &Expression)], limits: &Limits) -> Vec<Expression> {
+    let fixpoint = tie_group(group, limits, Expression::y_combinator());
+    pick(
+        group,
+        with_fallbacks(fixpoint, limits, || group_recursion(group)),
+    )
+}
+
+/// Tie the knot of a group of functions that call each other at runtime.
+///
+/// The program runs strictly at runtime too, so its branches are delayed in the same way.
+///
+/// f a  ==>  f a = $recurse (rec.$force0.$which.$which (a.body_f) (b.body_g)) 0 ($member0.$member1.$member0) a
+pub(crate) fn group_recursion(group: &[(&String, &Expression)]) -> Vec<Expression> {
+    let fixpoint = tie_group(
+        group,
+        &vec![BTreeMap::new(); group.len()],
+        Expression::Identifier(String::from(Expression::RUNTIME_RECURSION)),
+    );
+    pick(group, fixpoint)
+}
+
+/// Tie the knot of a group of functions with a fixpoint combinator
+fn tie_group(group: &[(&String, &Expression)], limits: &Limits, fix: Expression) -> Expression {
+    // Any value will do to force the body
+    Expression::Application(
+        Rc::new(Expression::Application(
+            Rc::new(fix),
+            Rc::new(knot(group, limits)),
+        )),
+        Rc::new(Expression::Number(0.0)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::testing::{compile, evaluate, parse, run};
+
+    /// Whether the functions of a program are shown to terminate, as one group
+    fn proven(source: &str) -> bool {
+        let ast = parse(source);
+        let group = ast
+            .get_functions()
+            .iter()
+            .map(|function| (function.get_name(), function.get_body()))
+            .collect::<Vec<_>>();
+        limits(&group).is_some()
+    }
+
+    #[test]
+    fn members_of_a_parameter_are_smaller() {
+        assert!(proven(
+            "let length list = case list of\n\
+             | Cons(_, rest) => 1 + rec rest\n\
+             | Nil => 0"
+        ));
+    }
+
+    #[test]
+    fn calls_with_the_same_argument_are_rejected() {
+        assert!(!proven("let forever n = rec n"));
+        assert!(!proven("let swap a b = rec b a"));
+    }
+
+    #[test]
+    fn subtraction_needs_a_bound() {
+        assert!(proven("let count n = if n > 0 then rec (n - 1) else 0"));
+        assert!(proven(
+            "let factorial n = if n > 0 then n * rec (n - 1) else 1"
+        ));
+        assert!(!proven("let count n = rec (n - 1)"));
+        assert!(!proven("let count n = if n > 0 then rec (n - 0) else 0"));
+    }
+
+    #[test]
+    fn numbers_outside_their_limits_run_at_runtime() {
+        // `inf / 10 == inf`, so this would never stop while compiling
+        let (result, codes) = compile(
+            "let digits n = if n >= 10 then 1 + rec (n / 10) else 1\n\
+             let main _ = print (digits (1 / 0)) (digits 12345)",
+        );
+        assert!(codes.is_empty());
+        let result = result.unwrap();
+        assert!(result.starts_with("K(print(recurse("), "{}", result);
+        assert!(result.ends_with("(inf))(5.0))"), "{}", result);
+
+        // `1e20 - 1 == 1e20`
+        let (result, codes) = compile(
+            "let count n = if n > 0 then rec (n - 1) else 0\n\
+             let main _ = print (count 1e20) (count 3)",
+        );
+        assert!(codes.is_empty());
+        let result = result.unwrap();
+        assert!(result.starts_with("K(print(recurse("), "{}", result);
+        assert!(result.ends_with("(1e20))(0.0))"), "{}", result);
+    }
+
+    #[test]
+    fn division_needs_a_positive_bound() {
+        assert!(proven(
+            "let digits n = if n >= 10 then 1 + rec (n / 10) else 1"
+        ));
+        assert!(!proven("let digits n = 1 + rec (n / 10)"));
+        assert!(!proven(
+            "let digits n = if n > 0 then 1 + rec (n / 1) else 1"
+        ));
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        assert!(proven(
+            "let evens list = case list of\n\
+             | Cons(x, rest) => Cons(x, odds rest)\n\
+             | Nil => Nil\n\
+             let odds list = case list of\n\
+             | Cons(_, rest) => evens rest\n\
+             | Nil => Nil"
+        ));
+        assert!(!proven(
+            "let ping n = pong n\n\
+             let pong n = ping n"
+        ));
+    }
+
+    #[test]
+    fn unproven_functions_are_reported() {
+        let (_, codes) = compile(
+            "let count n = if n > 0 then rec (n + 1) else 0\n\
+             let main _ = print (count 3)",
+        );
+        assert_eq!(codes, ["R0304"]);
+    }
+
+    #[test]
+    fn deep_recursion_is_evaluated_while_compiling() {
+        let (result, codes) = compile(
+            "let bits n = if n >= 1 then 1 + rec (n / 2) else 0\n\
+             let main _ = print (bits 1e300)",
+        );
+        assert!(codes.is_empty());
+        assert_eq!(result.as_deref(), Some("K(print(997.0))"));
+    }
+
+    #[test]
+    fn mutually_recursive_groups_are_evaluated_while_compiling() {
+        let (result, codes) = compile(
+            "let is_even n = if n > 0 then is_odd (n - 1) else true\n\
+             let is_odd n = if n > 0 then is_even (n - 1) else false\n\
+             let parity n = if is_even n then \"even\" else \"odd\"\n\
+             let main _ = print (parity 7) (parity 10)",
+        );
+        assert!(codes.is_empty());
+        assert_eq!(result.as_deref(), Some("K(print(\"odd\")(\"even\"))"));
+    }
+
+    #[test]
+    fn recursion_left_for_runtime_runs() {
+        let (result, codes) = evaluate(
+            "let count n = if n == 0 then 0 else 1 + rec (n - 1)\n\
+             let main _ = print (count 3)",
+        );
+        assert_eq!(codes, ["R0304"]);
+        let result = result.unwrap();
+        assert!(format!("{}", result).contains("recurse"));
+        assert_eq!(format!("{}", run(&result)), "K(print(3.0))");
+
+        let (result, codes) = evaluate(
+            "let is_even n = if n == 0 then true else is_odd (n - 1)\n\
+             let is_odd n = if n == 0 then false else is_even (n - 1)\n\
+             let parity n = if is_even n then \"even\" else \"odd\"\n\
+             let main _ = print (parity 7) (parity 10)",
+        );
+        assert_eq!(codes, ["R0304", "R0304"]);
+        let result = result.unwrap();
+        assert!(format!("{}", result).contains("recurse"));
+        assert_eq!(format!("{}", run(&result)), "K(print(\"odd\")(\"even\"))");
+
+        // Outside of its limits, a function that is sure to terminate falls back to running at runtime
+        let (result, codes) = evaluate(
+            "let count n = if n > 0 then 1 + rec (n - 1) else 0\n\
+             let main _ = print (count (0 / 0))",
+        );
+        assert!(codes.is_empty());
+        let result = result.unwrap();
+        assert!(format!("{}", result).contains("recurse"));
+        assert_eq!(format!("{}", run(&result)), "K(print(0.0))");
+    }
+}
@@ -0,0 +1,759 @@
+use crate::diagnostic::LintLevel;
+use core::fmt::{Display, Error, Formatter};
+
+/// A stable identifier for a kind of diagnostic
+///
+/// Every diagnostic carries one of these codes, and `ramify --explain <code>`
+/// prints the longer explanation registered in `CODES`. Warnings are lints,
+/// which also have a name that is used to allow or deny them.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct Code {
+    name: &'static str,
+    lint: Option<&'static str>,
+    /// The level of the lint when it is not configured
+    default_level: LintLevel,
+    title: &'static str,
+    explanation: &'static str,
+}
+
+impl Code {
+    const fn new(name: &'static str, title: &'static str, explanation: &'static str) -> Self {
+        Self {
+            name,
+            lint: None,
+            default_level: LintLevel::Deny,
+            title,
+            explanation,
+        }
+    }
+
+    const fn lint(
+        name: &'static str,
+        lint: &'static str,
+        title: &'static str,
+        explanation: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            lint: Some(lint),
+            default_level: LintLevel::Warn,
+            title,
+            explanation,
+        }
+    }
+
+    /// Report a lint as an error unless it is configured otherwise
+    const fn denied(mut self) -> Self {
+        self.default_level = LintLevel::Deny;
+        self
+    }
+
+    /// Find a registered code by its name, such as `R0102`, or by its lint name
+    pub fn lookup(name: &str) -> Option<Self> {
+        let name = name.trim();
+        CODES
+            .iter()
+            .find(|code| code.name.eq_ignore_ascii_case(name) || code.lint == Some(name))
+            .copied()
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The name of the lint, if diagnostics with this code can be allowed or denied
+    pub fn get_lint(&self) -> Option<&'static str> {
+        self.lint
+    }
+
+    pub fn get_default_level(&self) -> LintLevel {
+        self.default_level
+    }
+
+    pub fn get_title(&self) -> &'static str {
+        self.title
+    }
+
+    pub fn get_explanation(&self) -> &'static str {
+        self.explanation
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub const UNBOUND_VARIABLE: Code = Code::new(
+    "R0001",
+    "unbound variable",
+    r#"A name was used that is not bound by a function parameter, a lambda, a
+pattern, or a top level declaration.
+
+    let area r = pi * r * r
+
+Here `pi` is never defined. Every free variable has to be removed before a
+program can be translated into SKI combinators, so this is an error.
+
+Define the name at the top level, or bind it as a parameter:
+
+    const pi = 3.14159
+    let area r = pi * r * r
+"#,
+);
+
+pub const NO_ENTRY_POINT: Code = Code::new(
+    "R0002",
+    "no entry point",
+    r#"Every program is reduced starting from a function named `main`, and this
+program does not define one.
+
+    let greet name = print "Hello, " name
+
+Add a `main` function. It takes a single argument that is ignored:
+
+    let greet name = print "Hello, " name
+    let main _ = greet "world"
+"#,
+);
+
+pub const DUPLICATE_DEFINITION: Code = Code::new(
+    "R0003",
+    "duplicate definition",
+    r#"The same name was declared more than once. Functions and constants share
+one namespace, as do `data` and `type` names, and a constructor name can only
+belong to one declaration.
+
+    data Shape = Circle(Num) | Square(Num)
+    data Tile = Square(Num) | Empty
+    let area s = 1
+    let area w h = w * h
+
+Only one of the declarations would ever be used, so this is an error. Rename
+or remove one of them:
+
+    data Shape = Circle(Num) | Square(Num)
+    data Tile = Tile(Num) | Empty
+    let area w h = w * h
+
+Declarations of a function with the same name and the same number of
+parameters are not duplicates, but the clauses of one function.
+"#,
+);
+
+pub const UNKNOWN_LINT: Code = Code::new(
+    "R0004",
+    "unknown lint",
+    r#"An `@allow`, `@warn`, or `@deny` attribute names something that is not a
+lint. Lints can be named by their lint name or by their code, and errors
+cannot be allowed.
+
+    @allow(unused-variable, R0001)
+    let first a b = a
+
+There is no lint named `unused-variable`, and `R0001` is an error. Run
+`ramify --explain <code>` to find the name of a lint:
+
+    @allow(unused-variables)
+    let first a b = a
+"#,
+);
+
+pub const UNKNOWN_CONSTRUCTOR: Code = Code::new(
+    "R0100",
+    "unknown constructor",
+    r#"A value was constructed with a constructor that is not declared by any
+`data` or `type` declaration.
+
+    type Point(x, y)
+    let origin _ = Pointt(0, 0)
+
+Check the spelling of the constructor:
+
+    type Point(x, y)
+    let origin _ = Point(0, 0)
+"#,
+);
+
+pub const UNRESOLVED_CASE: Code = Code::new(
+    "R0101",
+    "case arms do not belong to a data type",
+    r#"The arms of a `case` expression are used to find the data type being
+matched. Every arm has to name a constructor of the same `data` declaration.
+
+    data Maybe a = Just(a) | Nothing
+    let get m = case m of
+                  | Just(x) => x
+                  | None => 0
+
+`None` is not a constructor of `Maybe`, so no data type matches. Use the
+constructors that `Maybe` declares:
+
+    let get m = case m of
+                  | Just(x) => x
+                  | Nothing => 0
+"#,
+);
+
+pub const WRONG_MEMBER_COUNT: Code = Code::new(
+    "R0104",
+    "wrong number of members",
+    r#"A constructor was used with a different number of members than its
+declaration has. This applies to constructing a value, to deconstructing it
+with `let` or a parameter pattern, and to the arms of a `case` expression.
+
+    type Point(x, y)
+    data Shape = Circle(Num) | Square(Num)
+    let origin _ = Point(0)
+    let sum p = let Point(a, b, c) = p in a + b + c
+    let size s = case s of
+                  | Circle(r, extra) => r
+                  | Square(w) => w
+
+Values are Scott encoded, so a constructor with the wrong number of members
+produces a function that takes the wrong number of arguments, and the program
+computes nonsense. Give every use of a constructor one member for each member
+of its declaration:
+
+    let origin _ = Point(0, 0)
+    let sum p = let Point(a, b) = p in a + b
+    let size s = case s of
+                  | Circle(r) => r
+                  | Square(w) => w
+"#,
+);
+
+pub const UNKNOWN_FIELD: Code = Code::new(
+    "R0105",
+    "unknown field",
+    r#"A field was used that the record does not declare, or braces were used to
+build a value with a constructor that has no named fields.
+
+    type Point { x, y }
+    let origin _ = Point { x = 0, z = 0 }
+    let up p = p { z = 1 }
+
+Check the spelling of the field against the record's declaration:
+
+    let origin _ = Point { x = 0, y = 0 }
+    let up p = p { y = 1 }
+"#,
+);
+
+pub const MISSING_FIELD: Code = Code::new(
+    "R0106",
+    "missing field",
+    r#"A record was built without giving a value for every one of its fields.
+
+    type Point { x, y }
+    let origin _ = Point { x = 0 }
+
+Records are Scott encoded like any other constructor, so every field needs a
+value. Give the missing fields, or use an existing record and update the
+fields that change:
+
+    let origin _ = Point { x = 0, y = 0 }
+    let shift p = p { x = 0 }
+"#,
+);
+
+pub const NON_EXHAUSTIVE_CASE: Code = Code::new(
+    "R0102",
+    "non-exhaustive case",
+    r#"A `case` expression does not have an arm for every constructor of the data
+type it matches.
+
+    data Shape = Circle(Num) | Square(Num) | Triangle(Num, Num)
+    let area s = case s of
+                  | Circle(r) => 3.14159 * r * r
+                  | Square(w) => w * w
+
+The arms of a case are passed to the data value in the order its constructors
+are sorted, so a missing arm shifts every arm after it and the program computes
+nonsense. Add an arm for every constructor:
+
+    let area s = case s of
+                  | Circle(r) => 3.14159 * r * r
+                  | Square(w) => w * w
+                  | Triangle(b, h) => b * h / 2
+
+A case with nested patterns, numbers, or strings must match every value, and
+the error names a value that no arm matches. A `_` arm matches everything else:
+
+    let describe m = case m of
+                      | Just(0) => "zero"
+                      | _ => "something else"
+
+An arm with a guard, such as `| Circle(r) if r > 10 => ...`, does not count as
+matching its pattern, because the guard may not hold.
+
+The clauses of a function defined by patterns must also match every argument:
+
+    let len Nil = 0
+    let len Cons(_, rest) = 1 + (len rest)
+"#,
+);
+
+pub const UNREACHABLE_ARM: Code = Code::new(
+    "R0103",
+    "unreachable arm",
+    r#"A `case` expression has more than one arm for the same constructor, so only
+one of them could ever be used.
+
+    data Maybe a = Just(a) | Nothing
+    let get m = case m of
+                 | Just(x) => x
+                 | Just(y) => y + 1
+                 | Nothing => 0
+
+Just like a missing arm, an extra arm shifts the arms after it. Remove the
+repeated arm:
+
+    let get m = case m of
+                 | Just(x) => x
+                 | Nothing => 0
+
+With nested patterns, an arm is unreachable when the arms before it match every
+value it matches, such as an arm after a `_` arm. The same goes for a clause of a
+function after a clause that matches any arguments.
+"#,
+);
+
+pub const MISMATCHED_SIGNATURES: Code = Code::lint(
+    "R0201",
+    "mismatched-signatures",
+    "branches take different numbers of arguments",
+    r#"The branches of an `if` or `case` expression evaluate to functions that take
+a different number of arguments. After translation into combinators this is
+usually a sign that one branch is missing an argument, or has one too many.
+
+    let choose c = if c then a.b.a else 1
+
+The first branch is a function of two arguments, but the second is a number.
+Make both branches produce the same kind of value:
+
+    let choose c = if c then a.b.a else a.b.b
+"#,
+);
+
+pub const MISMATCHED_TYPES: Code = Code::new(
+    "R0202",
+    "mismatched types",
+    r#"Type inference found a value of one type where a value of another type is
+required.
+
+    data Result x e = Ok(x) | Err(e)
+    const TWO = f.x.f (f x)
+    let unwrap r = case r of
+                    | Ok(x) => x
+                    | Err(e) => 0
+    let main _ = print (unwrap TWO)
+
+`unwrap` expects a `Result`, but `TWO` is a Church numeral, which has the type
+`(a -> a) -> a -> a`. Without type checking, this would be translated into
+combinators that compute nonsense. Pass a value of the expected type:
+
+    let main _ = print (unwrap (Ok(2)))
+"#,
+);
+
+pub const INFINITE_TYPE: Code = Code::lint(
+    "R0203",
+    "infinite-types",
+    "infinite type",
+    r#"A value is used in a way that would require its type to contain itself,
+which usually happens when a function is applied to itself.
+
+    let twice f = f f
+
+If `f` has type `a -> b`, then `f f` requires `a` to be `a -> b`, which has no
+finite solution. Pass the value being applied separately:
+
+    let twice f x = f (f x)
+
+Encodings from the untyped lambda calculus often apply a value to itself.
+The Church booleans `and` and `or` choose between their arguments by applying
+a boolean to itself:
+
+    let and a b = a b a
+    let or  a b = a a b
+
+These run as intended, but Hindley-Milner inference gives every use of a
+parameter the same type, so no type describes them. Infinite types are
+warnings, and the code is compiled without types. To require types, deny them
+with `-D infinite-types` or an attribute, or allow the encodings that are
+intended:
+
+    @allow(infinite-types)
+    let and a b = a b a
+"#,
+);
+
+pub const MISSING_DEFINITION: Code = Code::new(
+    "R0204",
+    "annotation without a definition",
+    r#"A type annotation was written for a function or constant that is never
+defined.
+
+    let area : Num -> Num
+    let aera r = 3.14159 * r * r
+
+The annotation is for `area`, but the function is named `aera`. Make the names
+of the annotation and the definition match:
+
+    let area : Num -> Num
+    let area r = 3.14159 * r * r
+"#,
+);
+
+pub const NO_INSTANCE: Code = Code::new(
+    "R0205",
+    "missing instance",
+    r#"An overloaded method or operator is used on a type that has no instance of
+its class.
+
+    data Point = Point(Num, Num)
+    let main _ = print (show Point(1, 2))
+
+`show` can only be used on types with an instance of `Show`, and there is none
+for `Point`. Derive one, or declare it:
+
+    data Point = Point(Num, Num) deriving (Show)
+
+    instance Show Point where
+        let show _p = "point"
+
+`+`, `==`, `show`, and `compare` are the methods of the builtin classes `Add`,
+`Eq`, `Show`, and `Ord`. They work on numbers and strings without an instance,
+but data types only have the instances they declare or derive.
+"#,
+);
+
+pub const AMBIGUOUS_INSTANCE: Code = Code::new(
+    "R0206",
+    "ambiguous instance",
+    r#"An overloaded method is used on a value whose type is never decided, so
+there is no way to choose which instance to use.
+
+    class Default a where default : a
+    let main _ = print default
+
+Nothing says which type `default` should have. Use it where its type is
+known:
+
+    let main _ = print (default + 1)
+"#,
+);
+
+pub const UNKNOWN_CLASS: Code = Code::new(
+    "R0207",
+    "unknown class",
+    r#"An instance was declared for a class that does not exist.
+
+    instance Sho Shape where
+        let show _s = "shape"
+
+Declare the class, or fix the spelling of its name:
+
+    instance Show Shape where
+        let show _s = "shape"
+"#,
+);
+
+pub const MISSING_METHOD: Code = Code::new(
+    "R0208",
+    "missing method",
+    r#"An instance does not define every method of its class.
+
+    class Describe a where
+        name : a -> String
+        describe : a -> String
+    instance Describe Shape where
+        let name _s = "shape"
+        let descibe _s = "a shape"
+
+The functions after `where` belong to the instance until the first one that is
+not a method of the class, so the misspelled `descibe` is a top level function
+instead. Every method must be defined, so that any use of the class can be
+resolved to the instance:
+
+    instance Describe Shape where
+        let name _s = "shape"
+        let describe _s = "a shape"
+"#,
+);
+
+pub const AMBIGUOUS_FIELD: Code = Code::new(
+    "R0209",
+    "ambiguous field",
+    r#"A field was used on a value whose type is not known, and more than one
+record declares a field with that name.
+
+    type Point { x, y }
+    type Size { x, width }
+    let left p = p.x
+
+Nothing says whether `p` is a `Point` or a `Size`. Annotate the value, so the
+record can be found from its type:
+
+    let left (p : Point) = p.x
+"#,
+);
+
+pub const CANNOT_DERIVE: Code = Code::new(
+    "R0210",
+    "cannot derive",
+    r#"A data declaration derives a class that instances cannot be generated for.
+
+    data Shape = Circle(Num) | Square(Num) deriving (Show, Add)
+
+Only `Show`, `Eq`, and `Ord` can be derived. Declare the instance of any other
+class yourself:
+
+    data Shape = Circle(Num) | Square(Num) deriving (Show)
+
+    instance Add Shape where
+        let plus a _b = a
+"#,
+);
+
+pub const UNKNOWN_TYPE_PARAMETER: Code = Code::new(
+    "R0211",
+    "unknown type parameter",
+    r#"The type of a member of a constructor in a data declaration names a type
+variable that is not one of the type parameters of the data type.
+
+    data Result = Ok(x) | Err(e)
+
+Nothing says what type of value `x` and `e` stand for, so an `Ok` made from a
+number could be taken apart as a string. Declare them as parameters, or give
+the members the types they hold:
+
+    data Result x e = Ok(x) | Err(e)
+    data Shape = Circle(Num) | Rectangle(Num, Num)
+
+Members can hold any type written in terms of the parameters, including the
+data type itself:
+
+    data List a = Cons(a, List a) | Nil
+
+The fields of a record are given types in the same way. A field without a type
+holds the parameter it is named after:
+
+    data Point = Point { x : Num, y : Num }
+    data Pair a b = Pair { a, b }
+"#,
+);
+
+pub const UNUSED_VARIABLE: Code = Code::lint(
+    "R0301",
+    "unused-variables",
+    "unused variable",
+    r#"A lambda parameter or a member of a `let` deconstruction is never used.
+
+    let first a b = a
+    let x_of p = let Point(x, y) = p in x
+
+`b` and `y` are bound but never read. This is often a typo, or a sign that the
+wrong variable is used somewhere else in the body.
+
+If the value really is ignored, start its name with an underscore:
+
+    let first a _b = a
+    let x_of p = let Point(x, _y) = p in x
+"#,
+);
+
+pub const UNUSED_DECLARATION: Code = Code::lint(
+    "R0302",
+    "unused-declarations",
+    "unused declaration",
+    r#"A top level function or constant can never be reached from `main`.
+
+    const TWO = f.x.f (f x)
+    let main _ = print "Hello world!"
+
+Unused declarations are still parsed and checked, but they are dead code.
+Remove them, or use them from `main`:
+
+    const TWO = f.x.f (f x)
+    let main _ = print (TWO (n.n + 1) 0)
+
+Only declarations that nothing else uses are reported, along with the unused
+declarations they use. Files without `main` are libraries, and are not checked.
+Declarations that are kept on purpose can allow the lint, or start with `_`:
+
+    @allow(unused-declarations)
+    const THREE = f.x.f (f (f x))
+"#,
+);
+
+pub const SHADOWED_NAME: Code = Code::lint(
+    "R0303",
+    "shadowing",
+    "shadowed name",
+    r#"A variable is bound with the same name as a variable in an enclosing scope,
+so the outer one can no longer be referred to.
+
+    let const_x x = x.x
+
+The inner `x` hides the parameter `x`, so `const_x` returns the identity
+function instead of a function that always returns `x`. Give the inner
+variable a different name:
+
+    let const_x x = y.x
+"#,
+);
+
+pub const UNPROVEN_TERMINATION: Code = Code::lint(
+    "R0304",
+    "unproven-termination",
+    "recursion that may not terminate",
+    r#"A function calls itself with `rec`, but it cannot be shown that the recursion
+ever stops.
+
+    let count_down n = if n == 0 then 0 else rec (n - 1)
+
+Every program is evaluated while it is compiled, so a function that recursed
+forever would hang the compiler. Functions that cannot be shown to terminate
+are left to run at runtime instead, which is slower, and which will hang the
+program if the recursion really does go on forever. Here `count_down` never
+stops when `n` is negative.
+
+A function is known to terminate when, however its recursive calls follow one
+another, some argument keeps getting smaller. An argument gets smaller when it
+is a member of a parameter that was taken apart with `case` or `let`, or when
+it is a number taken from a parameter in a branch where that parameter is known
+to be greater than some bound. The number can be the parameter minus a positive
+constant, or the parameter divided by at least 2 when the bound is positive:
+
+    let count_down n = if n > 0 then rec (n - 1) else 0
+    let digits n = if n >= 10 then 1 + rec (n / 10) else 1
+
+Numbers are floating point, so this only holds for numbers that are not too
+large. A number that is large enough does not change when a small one is
+subtracted from it, as in `1e20 - 1 == 1e20`, and infinity does not change
+when it is divided. Calls with such numbers, or with `NaN`, are left to run at
+runtime, so `count_down 1e20` and `digits (1 / 0)` do not hang the compiler.
+Recursion over a data type always stops:
+
+    data Nat = Zero | Succ(Nat)
+
+    let count_down n = case n of
+                         | Succ(m) => rec m
+                         | Zero => 0
+"#,
+);
+
+pub const TYPED_HOLE: Code = Code::lint(
+    "R0305",
+    "typed-holes",
+    "typed hole",
+    r#"An expression is a hole, written `?` followed by an optional name, which
+stands in for code that is not written yet.
+
+    data Shape = Circle(Num) | Rectangle(Num, Num)
+
+    let area shape = case shape of
+                       | Circle(r) => 3.14 * r * r
+                       | Rectangle(w, h) => ?todo
+
+The type checker reports the type that each hole must have, and the type of
+every variable in scope where it is written:
+
+    found the hole `?todo`, of type `Num`
+    note: `shape : Shape` is in scope
+    note: `w : Num` is in scope
+    note: `h : Num` is in scope
+
+Each note points at where its variable is bound. `rec` is only in scope in a
+function that calls itself with it.
+
+Holes are errors by default, so a program with holes does not compile. To
+keep sketching a program while it still runs, report them as warnings instead,
+with `-W typed-holes` or an attribute like `@warn(typed-holes)`. Each hole is
+then compiled into a stub that stops the program with an error if it is ever
+reached.
+"#,
+)
+.denied();
+
+/// Every code a diagnostic can carry
+pub const CODES: &[Code] = &[
+    UNBOUND_VARIABLE,
+    NO_ENTRY_POINT,
+    DUPLICATE_DEFINITION,
+    UNKNOWN_LINT,
+    UNKNOWN_CONSTRUCTOR,
+    UNRESOLVED_CASE,
+    NON_EXHAUSTIVE_CASE,
+    UNREACHABLE_ARM,
+    WRONG_MEMBER_COUNT,
+    UNKNOWN_FIELD,
+    MISSING_FIELD,
+    MISMATCHED_SIGNATURES,
+    MISMATCHED_TYPES,
+    INFINITE_TYPE,
+    MISSING_DEFINITION,
+    NO_INSTANCE,
+    AMBIGUOUS_INSTANCE,
+    UNKNOWN_CLASS,
+    MISSING_METHOD,
+    AMBIGUOUS_FIELD,
+    CANNOT_DERIVE,
+    UNKNOWN_TYPE_PARAMETER,
+    UNUSED_VARIABLE,
+    UNUSED_DECLARATION,
+    SHADOWED_NAME,
+    UNPROVEN_TERMINATION,
+    TYPED_HOLE,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::testing::compile;
+    use alloc::{collections::BTreeSet, vec::Vec};
+
+    #[test]
+    fn codes_are_unique() {
+        let names = CODES.iter().map(Code::get_name).collect::<BTreeSet<_>>();
+        assert_eq!(names.len(), CODES.len());
+        let lints = CODES.iter().filter_map(Code::get_lint).collect::<Vec<_>>();
+        assert_eq!(lints.iter().collect::<BTreeSet<_>>().len(), lints.len());
+    }
+
+    #[test]
+    fn codes_are_found_by_name_or_lint() {
+        assert_eq!(Code::lookup("R0101"), Some(UNRESOLVED_CASE));
+        assert_eq!(Code::lookup(" r0101 "), Some(UNRESOLVED_CASE));
+        assert_eq!(Code::lookup("unused-variables"), Some(UNUSED_VARIABLE));
+        assert_eq!(Code::lookup("R9999"), None);
+    }
+
+    #[test]
+    fn every_explanation_has_an_example() {
+        for code in CODES {
+            assert!(
+                code.get_explanation()
+                    .lines()
+                    .any(|line| line.starts_with("    ")),
+                "{} has no example",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn diagnostics_carry_their_codes() {
+        assert_eq!(
+            compile("let main _ = print x"),
+            (None, vec![UNBOUND_VARIABLE.get_name()])
+        );
+        assert_eq!(
+            compile("let start _ = print 1"),
+            (None, vec![NO_ENTRY_POINT.get_name()])
+        );
+    }
+}
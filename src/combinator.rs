@@ -7,6 +7,9 @@ use alloc::{
 };
 use core::fmt::{Debug, Display, Error, Formatter};
 
+/// What a builtin does once it has all of its arguments
+pub type BuiltinFunction = dyn Fn(&[Combinator]) -> Combinator;
+
 #[derive(Clone)]
 pub enum Combinator {
     S {
@@ -29,9 +32,13 @@ pub enum Combinator {
     List(Vec<Self>),
     Number(f64),
     String(String),
+    /// A function of the compiler that runs once it has all of its arguments.
+    /// Until then it keeps the arguments it was given, so that it can still be compiled.
     Builtin {
         name: String,
-        function: Rc<dyn Fn(Self) -> Self>,
+        arity: usize,
+        arguments: Vec<Self>,
+        function: Rc<BuiltinFunction>,
     },
     Foreign {
         name: String,
@@ -49,9 +56,15 @@ pub const I: Combinator = Combinator::I;
 pub const Nil: Combinator = Combinator::Nil;
 
 #[inline]
-pub fn builtin(name: impl ToString, f: impl Fn(Combinator) -> Combinator + 'static) -> Combinator {
+pub fn builtin(
+    name: impl ToString,
+    arity: usize,
+    f: impl Fn(&[Combinator]) -> Combinator + 'static,
+) -> Combinator {
     Combinator::Builtin {
         name: name.to_string(),
+        arity,
+        arguments: Vec::new(),
         function: Rc::new(f),
     }
 }
@@ -64,95 +77,151 @@ pub fn foreign(name: impl ToString) -> Combinator {
     }
 }
 
+/// What is left to do with a value once it has been computed,
+/// while applying combinators without recursing
+enum Continuation {
+    /// Apply this function to the value
+    ApplyTo(Combinator),
+    /// Apply the first combinator to the second, then apply the value to the result
+    Substitute(Rc<Combinator>, Combinator),
+}
+
 impl Combinator {
+    /// Apply a combinator to an argument, and reduce the result as far as it goes.
+    ///
+    /// Programs are evaluated while they are compiled, so a recursive function
+    /// can apply thousands of combinators inside one another. The pending
+    /// applications are kept on a stack instead of the call stack, so deep
+    /// recursion cannot overflow it.
     pub fn applied_to(&self, arg: Self) -> Self {
-        match self {
-            Self::Nil => Self::Nil,
-            Self::S {
-                x: Some(a),
-                y: Some(b),
-            } => {
-                let first = a.applied_to(arg.clone());
-                let second = b.applied_to(arg);
-                first.applied_to(second)
-            }
-            Self::S {
-                x: Some(a),
-                y: None,
-            } => Self::S {
-                x: Some(a.clone()),
-                y: Some(Rc::new(arg)),
-            },
-            Self::S { x: None, y: None } => Self::S {
-                x: Some(Rc::new(arg)),
-                y: None,
-            },
-            Self::S {
-                x: None,
-                y: Some(_),
-            } => compiler_error("Malformed Substitution combinator"),
+        let mut pending = Vec::new();
+        let mut function = self.clone();
+        let mut arg = arg;
+        loop {
+            let value = match function {
+                Self::Nil => Self::Nil,
+                Self::S {
+                    x: Some(a),
+                    y: Some(b),
+                } => {
+                    pending.push(Continuation::Substitute(b, arg.clone()));
+                    function = (*a).clone();
+                    continue;
+                }
+                Self::S {
+                    x: Some(a),
+                    y: None,
+                } => Self::S {
+                    x: Some(a),
+                    y: Some(Rc::new(arg)),
+                },
+                Self::S { x: None, y: None } => Self::S {
+                    x: Some(Rc::new(arg)),
+                    y: None,
+                },
+                Self::S {
+                    x: None,
+                    y: Some(_),
+                } => compiler_error("Malformed Substitution combinator"),
 
-            Self::B { x: None, y: None } => Self::S {
-                x: Some(Rc::new(arg)),
-                y: None,
-            },
-            Self::B {
-                x: Some(a),
-                y: None,
-            } => Self::B {
-                x: Some(a.clone()),
-                y: Some(Rc::new(arg)),
-            },
-            Self::B {
-                x: Some(a),
-                y: Some(b),
-            } => a.applied_to(b.applied_to(arg)),
-            Self::B {
-                x: None,
-                y: Some(_),
-            } => compiler_error("Malformed Bluebird combinator"),
+                Self::B { x: None, y: None } => Self::B {
+                    x: Some(Rc::new(arg)),
+                    y: None,
+                },
+                Self::B {
+                    x: Some(a),
+                    y: None,
+                } => Self::B {
+                    x: Some(a),
+                    y: Some(Rc::new(arg)),
+                },
+                Self::B {
+                    x: Some(a),
+                    y: Some(b),
+                } => {
+                    pending.push(Continuation::ApplyTo((*a).clone()));
+                    function = (*b).clone();
+                    continue;
+                }
+                Self::B {
+                    x: None,
+                    y: Some(_),
+                } => compiler_error("Malformed Bluebird combinator"),
 
-            Self::C { x: None, y: None } => Self::S {
-                x: Some(Rc::new(arg)),
-                y: None,
-            },
-            Self::C {
-                x: Some(a),
-                y: None,
-            } => Self::C {
-                x: Some(a.clone()),
-                y: Some(Rc::new(arg)),
-            },
-            Self::C {
-                x: Some(a),
-                y: Some(b),
-            } => a.applied_to(arg.applied_to((**b).clone())),
-            Self::C {
-                x: None,
-                y: Some(_),
-            } => compiler_error("Malformed Flip Combinator"),
+                Self::C { x: None, y: None } => Self::S {
+                    x: Some(Rc::new(arg)),
+                    y: None,
+                },
+                Self::C {
+                    x: Some(a),
+                    y: None,
+                } => Self::C {
+                    x: Some(a),
+                    y: Some(Rc::new(arg)),
+                },
+                Self::C {
+                    x: Some(a),
+                    y: Some(b),
+                } => {
+                    pending.push(Continuation::ApplyTo((*a).clone()));
+                    function = arg;
+                    arg = (*b).clone();
+                    continue;
+                }
+                Self::C {
+                    x: None,
+                    y: Some(_),
+                } => compiler_error("Malformed Flip Combinator"),
 
-            Self::K { x: Some(a) } => (**a).clone(),
-            Self::K { x: None } => match arg {
-                // Self::String(_) | Self::Table(_) | Self::List(_) | Self::Number(_) => arg,
-                _ => Self::K {
+                Self::K { x: Some(a) } => (*a).clone(),
+                Self::K { x: None } => Self::K {
                     x: Some(Rc::new(arg)),
                 },
-            },
-
-            Self::I => arg,
-
-            Self::Table(t) => Self::Table(t.clone()),
-            Self::String(s) => Self::String(s.clone()),
-            Self::List(l) => Self::List(l.clone()),
-            Self::Number(n) => Self::Number(*n),
-            Self::Builtin { function, .. } => function(arg),
-            Self::Foreign { name, arguments } => {
-                let mut result = arguments.clone();
-                result.push(arg);
+
+                Self::I => arg,
+
+                Self::Table(t) => Self::Table(t),
+                Self::String(s) => Self::String(s),
+                Self::List(l) => Self::List(l),
+                Self::Number(n) => Self::Number(n),
+                Self::Builtin {
+                    name,
+                    arity,
+                    mut arguments,
+                    function,
+                } => {
+                    arguments.push(arg);
+                    if arguments.len() < arity {
+                        Self::Builtin {
+                            name,
+                            arity,
+                            arguments,
+                            function,
+                        }
+                    } else {
+                        function(&arguments)
+                    }
+                }
                 Self::Foreign {
-                    name: name.clone(),
-                    arguments: result,
+                    name,
+                    mut arguments,
+                } => {
+                    arguments.push(arg);
+                    Self::Foreign { name, arguments }
+                }
+            };
+
+            // Hand the value to the application that was waiting for it
+            match pending.pop() {
+                None => return value,
+                Some(Continuation::ApplyTo(f)) => {
+                    function = f;
+                    arg = value;
+                }
+                Some(Continuation::Substitute(b, x)) => {
+                    pending.push(Continuation::ApplyTo(value));
+                    function = (*b).clone();
+                    arg = x;
                 }
             }
         }
@@ -226,18 +295,19 @@ impl Display for Combinator {
                 write!(f, "]")
             }
 
-            Self::Builtin { name, .. } => write!(f, "{}", name),
-
-            Self::Number(n) => write!(f, "{}", n),
-            Self::String(s) => write!(f, "{}", s),
-
-            Self::Foreign { name, arguments } => {
+            Self::Builtin {
+                name, arguments, ..
+            }
+            | Self::Foreign { name, arguments } => {
                 write!(f, "{}", name)?;
                 for item in arguments {
                     write!(f, "({:?})", item)?;
                 }
                 Ok(())
             }
+
+            Self::Number(n) => write!(f, "{}", n),
+            Self::String(s) => write!(f, "{}", s),
         }
     }
 }
@@ -309,18 +379,19 @@ impl Debug for Combinator {
                 write!(f, "]")
             }
 
-            Self::Builtin { name, .. } => write!(f, "{}", name),
-
-            Self::Number(n) => write!(f, "{:?}", n),
-            Self::String(s) => write!(f, "{:?}", s),
-
-            Self::Foreign { name, arguments } => {
+            Self::Builtin {
+                name, arguments, ..
+            }
+            | Self::Foreign { name, arguments } => {
                 write!(f, "{}", name)?;
                 for item in arguments {
                     write!(f, "({:?})", item)?;
                 }
                 Ok(())
             }
+
+            Self::Number(n) => write!(f, "{:?}", n),
+            Self::String(s) => write!(f, "{:?}", s),
         }
     }
 }
@@ -337,7 +408,18 @@ impl PartialEq for Combinator {
             (Self::Table(a), Self::Table(b)) => a == b,
             (Self::List(a), Self::List(b)) => a == b,
             (Self::Number(a), Self::Number(b)) => a == b,
-            (Self::Builtin { name: a, .. }, Self::Builtin { name: b, .. }) => a == b,
+            (
+                Self::Builtin {
+                    name: a,
+                    arguments: b,
+                    ..
+                },
+                Self::Builtin {
+                    name: x,
+                    arguments: y,
+                    ..
+                },
+            ) => a == x && b == y,
             (
                 Self::Foreign {
                     name: a,
@@ -75,11 +75,19 @@ impl Target<CompilerError> for Golang {
                 }
                 Ok(result + "})")
             }
+            Combinator::Number(n) if n.is_nan() => Ok(String::from("make_f64(nan)")),
+            Combinator::Number(n) if n.is_infinite() && n > 0.0 => {
+                Ok(String::from("make_f64(inf)"))
+            }
+            Combinator::Number(n) if n.is_infinite() => Ok(String::from("make_f64(-inf)")),
             Combinator::Number(n) => Ok(format!("make_f64({})", n)),
-            Combinator::String(s) => Ok(format!("make_str(\"{}\")", s)),
-            Combinator::Builtin { name, .. } => Ok(name),
+            Combinator::String(s) => Ok(format!("make_str({})", go_string(&s))),
 
-            Combinator::Foreign { name, arguments } => {
+            // Builtins are defined in the prelude under the same name
+            Combinator::Builtin {
+                name, arguments, ..
+            }
+            | Combinator::Foreign { name, arguments } => {
                 let mut result = name.clone();
                 for arg in &arguments {
                     result += &format!(".apply({})", self.compile(arg.clone())?);
@@ -91,3 +99,118 @@ impl Target<CompilerError> for Golang {
         }
     }
 }
+
+/// A Go string literal with the same contents
+fn go_string(s: &str) -> String {
+    let mut result = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => result += "\\\"",
+            '\\' => result += "\\\\",
+            '\n' => result += "\\n",
+            '\r' => result += "\\r",
+            '\t' => result += "\\t",
+            c if c.is_control() => result += &format!("\\U{:08x}", c as u32),
+            c => result.push(c),
+        }
+    }
+    result + "\""
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check::testing::parse, diagnostic::Diagnostics, reduce::Reduce};
+    use alloc::vec::Vec;
+
+    const PRELUDE: &str = include_str!("prelude.go");
+
+    const EXAMPLES: &[(&str, &str)] = &[
+        ("all", include_str!("../examples/all.rm")),
+        ("bool", include_str!("../examples/bool.rm")),
+        ("classes", include_str!("../examples/classes.rm")),
+        ("clauses", include_str!("../examples/clauses.rm")),
+        ("data", include_str!("../examples/data.rm")),
+        ("deriving", include_str!("../examples/deriving.rm")),
+        ("factorial", include_str!("../examples/factorial.rm")),
+        ("hello_world", include_str!("../examples/hello_world.rm")),
+        ("lambdas", include_str!("../examples/lambdas.rm")),
+        ("let", include_str!("../examples/let.rm")),
+        ("maybe", include_str!("../examples/maybe.rm")),
+        ("operators", include_str!("../examples/operators.rm")),
+        ("patterns", include_str!("../examples/patterns.rm")),
+        ("pipelines", include_str!("../examples/pipelines.rm")),
+        ("records", include_str!("../examples/records.rm")),
+        ("recursion", include_str!("../examples/recursion.rm")),
+        ("tuple", include_str!("../examples/tuple.rm")),
+        ("where", include_str!("../examples/where.rm")),
+    ];
+
+    /// The names of the builtins and foreign functions left in a combinator
+    fn names(combinator: &Combinator, found: &mut Vec<String>) {
+        match combinator {
+            Combinator::S { x, y } | Combinator::B { x, y } | Combinator::C { x, y } => {
+                x.iter().chain(y.iter()).for_each(|c| names(c, found))
+            }
+            Combinator::K { x } => x.iter().for_each(|c| names(c, found)),
+            Combinator::Table(table) => table.values().for_each(|c| names(c, found)),
+            Combinator::List(list) => list.iter().for_each(|c| names(c, found)),
+            Combinator::Builtin {
+                name, arguments, ..
+            }
+            | Combinator::Foreign { name, arguments } => {
+                found.push(name.clone());
+                arguments.iter().for_each(|c| names(c, found))
+            }
+            _ => {}
+        }
+    }
+
+    fn defined(name: &str) -> bool {
+        PRELUDE.contains(&format!("\nvar {} ", name))
+    }
+
+    #[test]
+    fn prelude_defines_everything_the_examples_use() {
+        for (example, source) in EXAMPLES {
+            let mut diagnostics = Diagnostics::new();
+            let source = comment::shell::strip(*source).unwrap();
+            let result = parse(&source)
+                .reduce(&(), &mut diagnostics)
+                .unwrap_or_else(|_| panic!("{} should compile", example))
+                .to_combinator();
+            assert!(Golang.compile(result.clone()).is_ok());
+
+            let mut found = Vec::new();
+            names(&result, &mut found);
+            for name in found {
+                assert!(defined(&name), "{} uses `{}`", example, name);
+            }
+        }
+        for name in ["S", "B", "C", "K", "I", "inf", "nan"] {
+            assert!(defined(name), "`{}` is not defined", name);
+        }
+    }
+
+    #[test]
+    fn prelude_defines_every_builtin() {
+        let reduce = include_str!("reduce/expression.rs");
+        for call in ["builtin(\"", "foreign(\""] {
+            for (i, _) in reduce.match_indices(call) {
+                let rest = &reduce[i + call.len()..];
+                let name = &rest[..rest.find('"').unwrap()];
+                assert!(defined(name), "`{}` is not defined", name);
+            }
+        }
+    }
+
+    #[test]
+    fn partially_applied_builtins_keep_their_arguments() {
+        let (result, _) = crate::check::testing::compile(
+            "let up n = if n > 9 then 0 else 1 + up (n + 1)\nlet main _ = print (up 0)",
+        );
+        let result = result.unwrap();
+        assert!(result.contains("add(1.0)"), "{}", result);
+        assert!(result.contains("greater"), "{}", result);
+    }
+}
@@ -0,0 +1,588 @@
+use crate::code::Code;
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{Display, Error, Formatter};
+
+/// A region of the source code
+///
+/// The parser works on suffixes of the input, so positions are recorded as
+/// the number of bytes remaining in the source when the region starts and
+/// ends. They are converted to absolute offsets with the source they were
+/// parsed from.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct Span {
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    /// Create a span from the remaining input at the start and end of a region
+    pub(crate) fn new(start: &str, end: &str) -> Self {
+        Self {
+            start: start.len(),
+            end: end.len(),
+        }
+    }
+
+    /// The absolute byte offsets of the span in the source it was parsed from
+    pub fn range(&self, source: &str) -> (usize, usize) {
+        let start = source.len().saturating_sub(self.start);
+        let end = source.len().saturating_sub(self.end);
+        (start, end.max(start))
+    }
+
+    /// Check if another span lies within this one
+    pub fn contains(&self, other: &Self) -> bool {
+        self.start >= other.start && self.end <= other.end
+    }
+
+    /// The one-based line and column where the span starts
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        let (start, _) = self.range(source);
+        let before = &source[..start];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |n| n + 1) + 1;
+        (line, column)
+    }
+
+    /// Render the location of the span and the line of source it points to
+    ///
+    ///  --> examples/all.rm:3:14
+    ///   |
+    /// 3 | let f x = if x then a.b.a else 1
+    ///   |              ^^^^^^^^^^^^^^^^^^^^^^
+    pub fn snippet(&self, path: &str, source: &str) -> String {
+        let (start, end) = self.range(source);
+        let (line, column) = self.location(source);
+        let line_start = start - (column - 1);
+        let text = source[line_start..].lines().next().unwrap_or("");
+        let text = text.trim_end();
+
+        // Only underline the first line of a span, without trailing whitespace
+        let underlined = source[start..end].lines().next().unwrap_or("").trim_end();
+        let width = underlined.chars().count().max(1);
+
+        let gutter = " ".repeat(line.to_string().len());
+        format!(
+            "{gutter}--> {}:{}:{}\n{gutter} |\n{} | {}\n{gutter} | {}{}\n",
+            path,
+            line,
+            column,
+            line,
+            text,
+            " ".repeat(source[line_start..start].chars().count()),
+            "^".repeat(width),
+            gutter = gutter,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// How a lint is reported
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Self::Allow),
+            "warn" => Some(Self::Warn),
+            "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// The level of each lint, configured by the project manifest or the command line
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintLevels {
+    /// The level of each lint, keyed by the name of its code
+    levels: BTreeMap<String, LintLevel>,
+    deny_warnings: bool,
+}
+
+impl LintLevels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the level of a lint, named by its lint name or its code
+    pub fn set(&mut self, lint: &str, level: LintLevel) -> Result<(), String> {
+        match Code::lookup(lint) {
+            Some(code) if code.get_lint().is_some() => {
+                self.levels.insert(code.get_name().to_string(), level);
+                Ok(())
+            }
+            Some(code) => Err(format!("`{}` is an error, not a lint", code)),
+            None => Err(format!("unknown lint `{}`", lint)),
+        }
+    }
+
+    /// Report every warning as an error
+    pub fn set_deny_warnings(&mut self, deny_warnings: bool) {
+        self.deny_warnings = deny_warnings;
+    }
+
+    /// Override these levels with every level set in `other`
+    pub fn extend(&mut self, other: &Self) {
+        for (code, level) in &other.levels {
+            self.levels.insert(code.clone(), *level);
+        }
+        self.deny_warnings |= other.deny_warnings;
+    }
+
+    pub fn get(&self, code: Code) -> Option<LintLevel> {
+        self.levels.get(code.get_name()).copied()
+    }
+
+    /// Read the `[lints]` table of a `ramify.toml` project manifest
+    ///
+    /// [lints]
+    /// unused-declarations = "allow"
+    /// shadowing = "deny"
+    /// deny-warnings = true
+    pub fn from_manifest(manifest: &str) -> Result<Self, String> {
+        let mut result = Self::new();
+        let mut in_lints = false;
+
+        for (n, line) in manifest.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_lints = line == "[lints]";
+                continue;
+            }
+            if !in_lints {
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => return Err(format!("line {}: expected `lint = \"level\"`", n + 1)),
+            };
+
+            if key == "deny-warnings" {
+                match value {
+                    "true" => result.set_deny_warnings(true),
+                    "false" => result.set_deny_warnings(false),
+                    _ => return Err(format!("line {}: expected `true` or `false`", n + 1)),
+                }
+                continue;
+            }
+
+            let level = LintLevel::parse(value.trim_matches('"')).ok_or_else(|| {
+                format!(
+                    "line {}: expected \"allow\", \"warn\", or \"deny\" for `{}`",
+                    n + 1,
+                    key
+                )
+            })?;
+            result
+                .set(key, level)
+                .map_err(|e| format!("line {}: {}", n + 1, e))?;
+        }
+
+        Ok(result)
+    }
+}
+
+/// A warning or error found while compiling a program
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    level: Level,
+    code: Code,
+    message: String,
+    span: Option<Span>,
+    notes: Vec<(String, Option<Span>)>,
+}
+
+impl Diagnostic {
+    pub fn warning(code: Code, message: impl ToString) -> Self {
+        Self {
+            level: Level::Warning,
+            code,
+            message: message.to_string(),
+            span: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(code: Code, message: impl ToString) -> Self {
+        Self {
+            level: Level::Error,
+            code,
+            message: message.to_string(),
+            span: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Add a note about another place in the source, such as an earlier definition
+    pub fn with_note(mut self, message: impl ToString, span: Option<Span>) -> Self {
+        self.notes.push((message.to_string(), span));
+        self
+    }
+
+    pub fn get_level(&self) -> Level {
+        self.level
+    }
+
+    pub fn get_code(&self) -> Code {
+        self.code
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_span(&self) -> Option<Span> {
+        self.span
+    }
+
+    pub fn get_notes(&self) -> &Vec<(String, Option<Span>)> {
+        &self.notes
+    }
+
+    /// Render the location of the diagnostic and of each of its notes
+    pub fn snippet(&self, path: &str, source: &str) -> String {
+        let mut result = match self.span {
+            Some(span) => span.snippet(path, source),
+            None => format!(" --> {}\n", path),
+        };
+
+        for (message, span) in &self.notes {
+            result += &format!("note: {}\n", message);
+            if let Some(span) = span {
+                result += &span.snippet(path, source);
+            }
+        }
+        result
+    }
+
+    /// Render the diagnostic with its location in the source code
+    pub fn render(&self, path: &str, source: &str) -> String {
+        format!("{}\n{}", self, self.snippet(path, source))
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}[{}]: {}", self.level, self.code, self.message)
+    }
+}
+
+/// A sink that collects the diagnostics produced by every compiler pass
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+    levels: LintLevels,
+    /// Levels set by attributes on declarations, which apply within their span
+    scoped_levels: Vec<(Span, Code, LintLevel)>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect diagnostics, reporting lints at the given levels
+    pub fn with_levels(levels: LintLevels) -> Self {
+        Self {
+            levels,
+            ..Self::default()
+        }
+    }
+
+    /// Set the level of a lint for every diagnostic within `span`
+    pub(crate) fn set_level_within(&mut self, span: Span, code: Code, level: LintLevel) {
+        self.scoped_levels.push((span, code, level));
+    }
+
+    /// The level of a lint at a place in the source.
+    /// Attributes take precedence over the configured levels, and inner attributes
+    /// take precedence over outer ones.
+    fn level_of(&self, code: Code, span: Option<Span>) -> LintLevel {
+        let mut scoped: Option<(Span, LintLevel)> = None;
+        if let Some(span) = span {
+            for (outer, lint, level) in &self.scoped_levels {
+                if *lint == code
+                    && outer.contains(&span)
+                    && scoped.is_none_or(|(inner, _)| inner.contains(outer))
+                {
+                    scoped = Some((*outer, *level));
+                }
+            }
+        }
+
+        scoped
+            .map(|(_, level)| level)
+            .or_else(|| self.levels.get(code))
+            .unwrap_or(code.get_default_level())
+    }
+
+    /// Apply the lint levels to every diagnostic collected so far.
+    ///
+    /// This has to run after the diagnostics have been placed, because attributes
+    /// only apply to diagnostics within the declarations they are written on.
+    pub(crate) fn apply_levels(&mut self) {
+        let mut result = Vec::new();
+        for mut diagnostic in self.diagnostics.clone() {
+            if diagnostic.code.get_lint().is_some() {
+                diagnostic.level = match self.level_of(diagnostic.code, diagnostic.span) {
+                    LintLevel::Allow => continue,
+                    LintLevel::Warn => Level::Warning,
+                    LintLevel::Deny => Level::Error,
+                };
+            }
+            if self.levels.deny_warnings {
+                diagnostic.level = Level::Error;
+            }
+            result.push(diagnostic);
+        }
+        self.diagnostics = result;
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic)
+    }
+
+    pub fn warning(&mut self, code: Code, message: impl ToString) {
+        self.push(Diagnostic::warning(code, message))
+    }
+
+    pub fn error(&mut self, code: Code, message: impl ToString) {
+        self.push(Diagnostic::error(code, message))
+    }
+
+    /// Give a span to every diagnostic pushed since `from` that has none.
+    ///
+    /// Passes that walk located expressions call this on the way back out,
+    /// so a diagnostic is attributed to the innermost expression around it.
+    pub(crate) fn place(&mut self, from: usize, span: Span) {
+        for diagnostic in &mut self.diagnostics[from..] {
+            if diagnostic.span.is_none() {
+                diagnostic.span = Some(span);
+            }
+        }
+    }
+
+    /// Add a note to every diagnostic with the given code that was pushed since `from`
+    pub(crate) fn note(&mut self, from: usize, code: Code, message: impl ToString) {
+        for diagnostic in &mut self.diagnostics[from..] {
+            if diagnostic.code == code {
+                diagnostic.notes.push((message.to_string(), None));
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.level == Level::Error)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = alloc::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        code::{MISMATCHED_SIGNATURES, UNBOUND_VARIABLE, UNUSED_DECLARATION, UNUSED_VARIABLE},
+        parse::expression::parse_expression,
+        reduce::Reduce,
+    };
+
+    #[test]
+    fn reduction_collects_warnings() {
+        let (_, expr) = parse_expression("c. if c then a.b.a else 1").unwrap();
+        let expr = expr.resolve_dotted(&mut Vec::new());
+        let mut diagnostics = Diagnostics::new();
+        assert!(expr.reduce(&Vec::new(), &mut diagnostics).is_ok());
+
+        let messages = diagnostics
+            .iter()
+            .map(Diagnostic::get_message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            ["different type signatures for branches of if expression"]
+        );
+        assert!(!diagnostics.has_errors());
+    }
+
+    #[test]
+    fn diagnostics_are_placed_at_the_innermost_span() {
+        let source = "let main x = print y";
+        let (inner, outer) = (
+            Span::new(&source[19..], &source[20..]),
+            Span::new(source, ""),
+        );
+
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.warning(MISMATCHED_SIGNATURES, "inner");
+        diagnostics.place(0, inner);
+        diagnostics.warning(MISMATCHED_SIGNATURES, "outer");
+        diagnostics.place(0, outer);
+
+        let spans = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.get_span())
+            .collect::<Vec<_>>();
+        assert_eq!(spans, [Some(inner), Some(outer)]);
+    }
+
+    #[test]
+    fn snippets_underline_the_span() {
+        let source = "let main x =\n    print y\n";
+        let span = Span::new(&source[23..], &source[24..]);
+        assert_eq!(span.location(source), (2, 11));
+        assert_eq!(
+            span.snippet("main.rm", source),
+            " --> main.rm:2:11\n  |\n2 |     print y\n  |           ^\n"
+        );
+    }
+
+    /// The level and message of each diagnostic left once the levels are applied
+    fn reported(diagnostics: &mut Diagnostics) -> Vec<(Level, String)> {
+        diagnostics.apply_levels();
+        diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.get_level(), diagnostic.get_message().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn levels_change_how_lints_are_reported() {
+        let mut levels = LintLevels::new();
+        levels.set("unused-variables", LintLevel::Allow).unwrap();
+        levels.set("R0302", LintLevel::Deny).unwrap();
+        assert!(levels.set("R0001", LintLevel::Allow).is_err());
+        assert!(levels.set("unused", LintLevel::Allow).is_err());
+
+        let mut diagnostics = Diagnostics::with_levels(levels.clone());
+        diagnostics.warning(UNUSED_VARIABLE, "variable");
+        diagnostics.warning(UNUSED_DECLARATION, "declaration");
+        diagnostics.warning(MISMATCHED_SIGNATURES, "signatures");
+        diagnostics.error(UNBOUND_VARIABLE, "unbound");
+        assert_eq!(
+            reported(&mut diagnostics),
+            [
+                (Level::Error, "declaration".to_string()),
+                (Level::Warning, "signatures".to_string()),
+                (Level::Error, "unbound".to_string()),
+            ]
+        );
+
+        levels.set_deny_warnings(true);
+        let mut diagnostics = Diagnostics::with_levels(levels);
+        diagnostics.warning(UNUSED_VARIABLE, "variable");
+        diagnostics.warning(MISMATCHED_SIGNATURES, "signatures");
+        assert_eq!(
+            reported(&mut diagnostics),
+            [(Level::Error, "signatures".to_string())]
+        );
+    }
+
+    #[test]
+    fn inner_attributes_take_precedence() {
+        let source = "let main x = print y";
+        let (inner, outer) = (
+            Span::new(&source[19..], &source[20..]),
+            Span::new(source, ""),
+        );
+        let mut levels = LintLevels::new();
+        levels.set("unused-variables", LintLevel::Deny).unwrap();
+
+        let mut diagnostics = Diagnostics::with_levels(levels);
+        diagnostics.set_level_within(outer, UNUSED_VARIABLE, LintLevel::Allow);
+        diagnostics.set_level_within(inner, UNUSED_VARIABLE, LintLevel::Warn);
+        diagnostics.push(Diagnostic::warning(UNUSED_VARIABLE, "inner").with_span(inner));
+        diagnostics.push(Diagnostic::warning(UNUSED_VARIABLE, "outer").with_span(outer));
+        diagnostics.warning(UNUSED_VARIABLE, "anywhere");
+        assert_eq!(
+            reported(&mut diagnostics),
+            [
+                (Level::Warning, "inner".to_string()),
+                (Level::Error, "anywhere".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn manifests_set_lint_levels() {
+        let levels = LintLevels::from_manifest(
+            "[package]\n\
+             unused-variables = \"deny\"\n\
+             \n\
+             [lints]\n\
+             # Libraries do not use everything they declare\n\
+             unused-declarations = \"allow\"\n\
+             R0303 = \"deny\" # shadowing\n\
+             deny-warnings = true\n",
+        )
+        .unwrap();
+
+        let mut expected = LintLevels::new();
+        expected.set("R0302", LintLevel::Allow).unwrap();
+        expected.set("shadowing", LintLevel::Deny).unwrap();
+        expected.set_deny_warnings(true);
+        assert_eq!(levels, expected);
+
+        assert_eq!(
+            LintLevels::from_manifest("[lints]\nshadowing = \"loud\""),
+            Err("line 2: expected \"allow\", \"warn\", or \"deny\" for `shadowing`".to_string())
+        );
+        assert_eq!(
+            LintLevels::from_manifest("[lints]\nR0001 = \"allow\""),
+            Err("line 2: `R0001` is an error, not a lint".to_string())
+        );
+    }
+}
@@ -37,6 +37,7 @@ impl Lambda {
 
     /// Construct a Lambda abstraction
     #[inline]
+    #[allow(clippy::self_named_constructors)]
     pub fn lambda(var: impl ToString, result: Self) -> Self {
         Self::Abstraction(var.to_string(), Rc::new(result))
     }
@@ -11,8 +11,19 @@ pub use lambda::Lambda;
 mod compile;
 pub use compile::{CompilerError, Golang, Target};
 
+mod code;
+pub use code::{Code, CODES};
+
+mod diagnostic;
+pub use diagnostic::{Diagnostic, Diagnostics, Level, LintLevel, LintLevels, Span};
+
+mod types;
+pub use types::{Scheme, Type};
+
 pub(crate) mod ast;
 
+pub(crate) mod check;
+
 pub(crate) mod reduce;
 pub use reduce::{Reduce, ReductionError};
 
@@ -1,114 +1,393 @@
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    combinator::map,
-    sequence::{separated_pair, tuple},
+    bytes::complete::{tag, take_while1},
+    combinator::{map, verify},
+    error::{make_error, ErrorKind},
+    sequence::{delimited, pair, terminated, tuple},
     IResult,
 };
 
-use crate::ast::Expression;
-use alloc::rc::Rc;
+use crate::ast::{Associativity, Binder, Expression};
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+};
 
-use crate::parse::expression::parse_atom;
+use crate::parse::{
+    basic::{parse_identifier, sp},
+    declared_fixity,
+    expression::parse_expression,
+    is_defined_operator,
+    lambda::{parse_application, parse_tailcall},
+};
 
-#[inline]
-pub fn parse_arithmetic(input: &str) -> IResult<&str, Expression> {
-    parse_bottom_precedence(input)
+/// The fixities of the builtin operators
+///
+/// Application groups more tightly than any operator, so `f x + g y` adds two applications.
+const BUILTIN_FIXITIES: &[(&str, Associativity, u8)] = &[
+    ("|>", Associativity::Left, 1),
+    ("||", Associativity::Right, 2),
+    ("&&", Associativity::Right, 3),
+    ("==", Associativity::Neither, 4),
+    ("!=", Associativity::Neither, 4),
+    (">", Associativity::Neither, 4),
+    (">=", Associativity::Neither, 4),
+    ("<", Associativity::Neither, 4),
+    ("<=", Associativity::Neither, 4),
+    ("+", Associativity::Left, 6),
+    ("-", Associativity::Left, 6),
+    ("*", Associativity::Left, 7),
+    ("/", Associativity::Left, 7),
+    (">>", Associativity::Left, 9),
+    ("<<", Associativity::Right, 9),
+];
+
+/// Symbols made of operator characters that mean something else
+const RESERVED_SYMBOLS: &[&str] = &["=", "|", "=>", "->"];
+
+/// The variables of the arguments that an operator section is missing
+const LEFT: &str = "$left";
+const RIGHT: &str = "$right";
+
+/// How an operator groups with the operators around it.
+/// Operators without a declared fixity group like `infixl 9`.
+fn fixity(operator: &str) -> (Associativity, u8) {
+    if let Some(fixity) = declared_fixity(operator) {
+        return (fixity.get_associativity(), fixity.get_precedence());
+    }
+    BUILTIN_FIXITIES
+        .iter()
+        .find(|(builtin, _, _)| *builtin == operator)
+        .map(|(_, associativity, precedence)| (*associativity, *precedence))
+        .unwrap_or((Associativity::Left, 9))
 }
 
-pub fn parse_top_precedence(input: &str) -> IResult<&str, Expression> {
-    alt((
-        map(
-            tuple((parse_atom, alt((tag("*"), tag("/"))), parse_top_precedence)),
-            |(left, symbol, right)| match symbol {
-                "*" => Expression::Multiply(Rc::new(left), Rc::new(right)),
-                "/" => match right {
-                    Expression::Divide(a, b) => {
-                        Expression::Divide(Rc::new(Expression::Divide(Rc::new(left), a)), b)
-                    }
-                    Expression::Multiply(a, b) => {
-                        Expression::Multiply(Rc::new(Expression::Divide(Rc::new(left), a)), b)
-                    }
-                    otherwise => Expression::Divide(Rc::new(left), Rc::new(otherwise)),
-                },
-                _ => unreachable!(),
-            },
-        ),
-        parse_atom,
-    ))(input)
+pub(crate) fn is_operator_char(c: char) -> bool {
+    "!#$%&*+/<=>^|-~".contains(c)
 }
 
-pub fn parse_high_precedence(input: &str) -> IResult<&str, Expression> {
-    alt((
-        map(
-            tuple((
-                parse_top_precedence,
-                alt((tag("+"), tag("-"))),
-                parse_high_precedence,
-            )),
-            |(left, symbol, right)| match symbol {
-                "+" => Expression::Add(Rc::new(left), Rc::new(right)),
-                "-" => match right {
-                    Expression::Add(a, b) => {
-                        Expression::Add(Rc::new(Expression::Subtract(Rc::new(left), a)), b)
-                    }
-                    Expression::Subtract(a, b) => {
-                        Expression::Subtract(Rc::new(Expression::Subtract(Rc::new(left), a)), b)
-                    }
-                    otherwise => Expression::Subtract(Rc::new(left), Rc::new(otherwise)),
-                },
-                _ => unreachable!(),
-            },
-        ),
-        parse_top_precedence,
-    ))(input)
+/// Parse a run of operator characters up to the end of the operator it starts with.
+///
+/// A run that ends in `-` or `!` is usually an operator followed by a negation, as in `2*-3`
+/// or `a&&!b`, so those characters are left for the operand unless the whole run is an operator
+/// that is builtin, has a declared fixity, or is defined by the program.
+fn parse_symbol(input: &str) -> IResult<&str, &str> {
+    let (_, run) = take_while1(is_operator_char)(input)?;
+    let mut symbol = run;
+    while symbol.len() > 1 && symbol.ends_with(['-', '!']) && !is_known_operator(symbol) {
+        symbol = &symbol[..symbol.len() - 1];
+    }
+    Ok((&input[symbol.len()..], symbol))
 }
 
-pub fn parse_mid_precedence(input: &str) -> IResult<&str, Expression> {
-    alt((
-        map(
-            tuple((
-                parse_high_precedence,
-                alt((
-                    tag("=="),
-                    tag("!="),
-                    tag(">="),
-                    tag(">"),
-                    tag("<="),
-                    tag("<"),
-                )),
-                parse_mid_precedence,
-            )),
-            |(left, symbol, right)| match symbol {
-                "==" => Expression::Equal(Rc::new(left), Rc::new(right)),
-                "!=" => Expression::NotEqual(Rc::new(left), Rc::new(right)),
-                ">" => Expression::Greater(Rc::new(left), Rc::new(right)),
-                ">=" => Expression::GreaterEqual(Rc::new(left), Rc::new(right)),
-                "<" => Expression::Less(Rc::new(left), Rc::new(right)),
-                "<=" => Expression::LessEqual(Rc::new(left), Rc::new(right)),
-                _ => unreachable!(),
-            },
-        ),
-        parse_high_precedence,
-    ))(input)
+fn is_known_operator(operator: &str) -> bool {
+    BUILTIN_FIXITIES
+        .iter()
+        .any(|(builtin, _, _)| *builtin == operator)
+        || declared_fixity(operator).is_some()
+        || is_defined_operator(operator)
+}
+
+/// Parse an infix operator, which is either made of symbols
+/// or is the name of a function between backticks
+///
+/// <+>
+/// `div`
+pub(crate) fn parse_operator(input: &str) -> IResult<&str, String> {
+    operator(input, parse_symbol)
+}
+
+/// Parse an infix operator where its fixity is declared, which takes every operator character
+///
+/// infixl 6 *-
+pub(crate) fn parse_declared_operator(input: &str) -> IResult<&str, String> {
+    operator(input, take_while1(is_operator_char))
 }
 
-pub fn parse_low_precedence(input: &str) -> IResult<&str, Expression> {
+fn operator<'a>(
+    input: &'a str,
+    symbol: impl Fn(&'a str) -> IResult<&'a str, &'a str>,
+) -> IResult<&'a str, String> {
+    let (input, _) = sp(input)?;
     alt((
         map(
-            separated_pair(parse_mid_precedence, tag("&&"), parse_low_precedence),
-            |(left, right)| Expression::And(Rc::new(left), Rc::new(right)),
+            verify(symbol, |symbol: &str| !RESERVED_SYMBOLS.contains(&symbol)),
+            String::from,
+        ),
+        map(
+            delimited(tag("`"), parse_identifier, tag("`")),
+            String::from,
         ),
-        parse_mid_precedence,
     ))(input)
 }
 
-pub fn parse_bottom_precedence(input: &str) -> IResult<&str, Expression> {
-    alt((
+/// Parse the name of an operator as it is declared
+///
+/// (<+>)
+pub(crate) fn parse_operator_name(input: &str) -> IResult<&str, &str> {
+    delimited(
+        tuple((tag("("), sp)),
+        verify(take_while1(is_operator_char), |symbol: &str| {
+            !RESERVED_SYMBOLS.contains(&symbol)
+        }),
+        tuple((sp, tag(")"))),
+    )(input)
+}
+
+/// Apply an operator to two operands
+fn binary(operator: &str, a: Expression, b: Expression) -> Expression {
+    let (a, b) = (Rc::new(a), Rc::new(b));
+    match operator {
+        "||" => Expression::Or(a, b),
+        "&&" => Expression::And(a, b),
+        "==" => Expression::Equal(a, b),
+        "!=" => Expression::NotEqual(a, b),
+        ">" => Expression::Greater(a, b),
+        ">=" => Expression::GreaterEqual(a, b),
+        "<" => Expression::Less(a, b),
+        "<=" => Expression::LessEqual(a, b),
+        "+" => Expression::Add(a, b),
+        "-" => Expression::Subtract(a, b),
+        "*" => Expression::Multiply(a, b),
+        "/" => Expression::Divide(a, b),
+        // x |> f  ==>  f x
+        "|>" => Expression::Application(b, a),
+        // f << g  ==>  $compose f g
+        "<<" => compose(a, b),
+        // f >> g  ==>  $compose g f
+        ">>" => compose(b, a),
+        // a <+> b  ==>  (<+>) a b
+        name => {
+            let f = Rc::new(Expression::Identifier(name.to_string()));
+            Expression::Application(Rc::new(Expression::Application(f, a)), b)
+        }
+    }
+}
+
+/// The function that applies `g` and then `f`
+fn compose(f: Rc<Expression>, g: Rc<Expression>) -> Expression {
+    let compose = Rc::new(Expression::Identifier(Expression::COMPOSE.to_string()));
+    Expression::Application(Rc::new(Expression::Application(compose, f)), g)
+}
+
+fn variable(name: &str) -> Expression {
+    Expression::Identifier(name.to_string())
+}
+
+fn lambda(name: &str, body: Expression) -> Expression {
+    Expression::Lambda(Binder::new(name), Rc::new(body))
+}
+
+#[inline]
+pub fn parse_arithmetic(input: &str) -> IResult<&str, Expression> {
+    parse_operators(input, 0)
+}
+
+/// Parse operands separated by operators of at least a given precedence.
+///
+/// This is precedence climbing: an operator takes as its right operand everything after it
+/// that is joined by operators which group more tightly than it does.
+fn parse_operators(input: &str, minimum: u8) -> IResult<&str, Expression> {
+    let (mut input, mut left) = alt((parse_tailcall, parse_application))(input)?;
+    // The precedence of the last operator, if it cannot be written twice in a row
+    let mut non_associative = None;
+
+    while let Ok((rest, operator)) = parse_operator(input) {
+        let (associativity, precedence) = fixity(&operator);
+        if precedence < minimum {
+            break;
+        }
+        // `a == b == c` has no grouping
+        if non_associative == Some(precedence) {
+            return Err(nom::Err::Failure(make_error(input, ErrorKind::Verify)));
+        }
+
+        let next = match associativity {
+            Associativity::Left | Associativity::Neither => precedence + 1,
+            Associativity::Right => precedence,
+        };
+        let (rest, right) = match parse_operators(rest, next) {
+            Ok(result) => result,
+            // An operator without a right operand ends a left section, such as `(1 +)`
+            Err(nom::Err::Error(_)) => break,
+            Err(error) => return Err(error),
+        };
+
+        left = binary(&operator, left, right);
+        non_associative = match associativity {
+            Associativity::Neither => Some(precedence),
+            _ => None,
+        };
+        input = rest;
+    }
+
+    Ok((input, left))
+}
+
+/// Parse a parenthesized expression, or an operator section,
+/// which is an operator that is missing one or both of its operands
+///
+/// (+)    ==>  $left. $right. $left + $right
+/// (/ 2)  ==>  $left. $left / 2
+/// (1 -)  ==>  $right. 1 - $right
+pub(crate) fn parse_parenthesized<'a>(input: &'a str) -> IResult<&'a str, Expression> {
+    let (input, _) = tuple((tag("("), sp))(input)?;
+    let close = |input: &'a str| tuple((sp, tag(")"), sp))(input);
+
+    let (input, result) = alt((
+        map(terminated(parse_operator, close), |operator| {
+            lambda(
+                LEFT,
+                lambda(RIGHT, binary(&operator, variable(LEFT), variable(RIGHT))),
+            )
+        }),
+        // `(- 1)` is a negative number and `(!a)` is a negation, rather than sections
         map(
-            separated_pair(parse_low_precedence, tag("||"), parse_bottom_precedence),
-            |(left, right)| Expression::Or(Rc::new(left), Rc::new(right)),
+            terminated(
+                pair(
+                    verify(parse_operator, |operator: &str| {
+                        operator != "-" && operator != "!"
+                    }),
+                    parse_expression,
+                ),
+                close,
+            ),
+            |(operator, right)| lambda(LEFT, binary(&operator, variable(LEFT), right)),
         ),
-        parse_low_precedence,
-    ))(input)
+        |input| {
+            let (input, inner) = parse_expression(input)?;
+            if let Ok((input, _)) = close(input) {
+                return Ok((input, inner));
+            }
+            let (input, operator) = terminated(parse_operator, close)(input)?;
+            Ok((
+                input,
+                lambda(RIGHT, binary(&operator, inner, variable(RIGHT))),
+            ))
+        },
+    ))(input)?;
+
+    Ok((input, result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check::testing::compile, parse::expression::parse_expression};
+
+    fn parse(input: &str) -> String {
+        let (rest, expr) = parse_expression(input).expect("the expression should parse");
+        assert!(
+            rest.is_empty(),
+            "the expression should parse up to {:?}",
+            rest
+        );
+        format!("{:?}", expr)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parse("1 + 2 * 3"), "(1 + (2 * 3))");
+        assert_eq!(parse("1 - 2 - 3"), "((1 - 2) - 3)");
+        assert_eq!(parse("a || b && c"), "(a || (b && c))");
+    }
+
+    #[test]
+    fn sections() {
+        assert_eq!(parse("(/ 2)"), "$left.(($left / 2))");
+        assert_eq!(parse("(1 -)"), "$right.((1 - $right))");
+        assert_eq!(parse("(+)"), "$left.$right.(($left + $right))");
+        // A minus sign on its own negates its operand instead
+        assert_eq!(parse("(- 1)"), "-(1)");
+    }
+
+    #[test]
+    fn sections_apply() {
+        let (result, _) = compile("let main _ = print ((+ 1) 2) ((/ 2) 8) ((10 -) 3)");
+        assert_eq!(result.as_deref(), Some("K(print(3.0)(4.0)(7.0))"));
+    }
+
+    #[test]
+    fn non_associative_chains_are_rejected() {
+        assert!(matches!(
+            parse_expression("a == b == c"),
+            Err(nom::Err::Failure(_))
+        ));
+        assert!(matches!(
+            parse_expression("a < b > c"),
+            Err(nom::Err::Failure(_))
+        ));
+        assert_eq!(parse("(a == b) == c"), "((a == b) == c)");
+    }
+
+    #[test]
+    fn backtick_infix() {
+        assert_eq!(parse("9 `div` 3"), "div(9)(3)");
+    }
+
+    #[test]
+    fn declared_fixities() {
+        let (result, _) = compile(
+            "infixr 5 +++\n\
+             let (+++) a b = a - b\n\
+             let main _ = print (10 +++ 4 +++ 1)",
+        );
+        assert_eq!(result.as_deref(), Some("K(print(7.0))"));
+    }
+
+    #[test]
+    fn negations_after_operators() {
+        assert_eq!(parse("2*-3"), "(2 * -(3))");
+        assert_eq!(parse("a&&!b"), "(a && !(b))");
+        assert_eq!(parse("4--1"), "(4 - -(1))");
+        // `!=` and `>=` do not end in a negation
+        assert_eq!(parse("a!=b"), "(a != b)");
+    }
+
+    #[test]
+    fn defined_operators_ending_in_a_negation() {
+        let (result, codes) = compile(
+            "let (<-!) a b = a - b\n\
+             let main _ = print (5<-!2) (2*-3)",
+        );
+        assert_eq!(result.as_deref(), Some("K(print(3.0)(-6.0))"));
+        assert!(codes.is_empty(), "{:?}", codes);
+
+        let (result, codes) = compile(
+            "infixl 6 *-\n\
+             let (*-) a b = a * b - 1\n\
+             let main _ = print (2*-3)",
+        );
+        assert_eq!(result.as_deref(), Some("K(print(5.0))"));
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
+
+    #[test]
+    fn pipelines_and_composition() {
+        assert_eq!(parse("x |> f |> g"), "g(f(x))");
+        assert_eq!(parse("1 + 2 |> f"), "f((1 + 2))");
+        assert_eq!(parse("f >> g"), "$compose(g)(f)");
+        assert_eq!(parse("f << g << h"), "$compose(f)($compose(g)(h))");
+        assert_eq!(parse("x |> f >> g"), "$compose(g)(f)(x)");
+    }
+
+    #[test]
+    fn composition_is_the_b_combinator() {
+        let (result, codes) = compile(
+            "let inc x = x + 1\n\
+             let double x = x * 2\n\
+             let main _ = print ((inc >> double) 3) (inc >> double) (inc << double)",
+        );
+        let inc = "S(S(K(add))(I))(K(1.0))";
+        let double = "S(S(K(mul))(I))(K(2.0))";
+        assert_eq!(
+            result,
+            Some(format!(
+                "K(print(8.0)(B({double})({inc}))(B({inc})({double})))",
+                inc = inc,
+                double = double
+            ))
+        );
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
 }
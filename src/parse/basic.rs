@@ -5,6 +5,12 @@ use nom::{
     IResult,
 };
 
+use crate::{
+    ast::{Binder, Expression},
+    diagnostic::Span,
+};
+use alloc::rc::Rc;
+
 pub(crate) fn sp(input: &str) -> IResult<&str, &str> {
     let chars = " \t\r\n";
 
@@ -13,6 +19,20 @@ pub(crate) fn sp(input: &str) -> IResult<&str, &str> {
     take_while(move |c| chars.contains(c))(input)
 }
 
+/// Record where the expression parsed by `parser` was written
+pub(crate) fn located<'a>(
+    parser: impl Fn(&'a str) -> IResult<&'a str, Expression>,
+) -> impl Fn(&'a str) -> IResult<&'a str, Expression> {
+    move |input| {
+        let (start, _) = sp(input)?;
+        let (end, expr) = parser(start)?;
+        Ok((
+            end,
+            Expression::Located(Span::new(start, end), Rc::new(expr)),
+        ))
+    }
+}
+
 pub(crate) fn parse_keyword(input: &str) -> IResult<&str, &str> {
     let (input, _) = sp(input)?;
     alt((
@@ -20,8 +40,16 @@ pub(crate) fn parse_keyword(input: &str) -> IResult<&str, &str> {
         tag("from"),
         tag("import"),
         tag("data"),
+        tag("deriving"),
         tag("type"),
         tag("let"),
+        tag("class"),
+        tag("instance"),
+        tag("where"),
+        // Before `in`, which they start with
+        tag("infixl"),
+        tag("infixr"),
+        tag("infix"),
         tag("in"),
         tag("case"),
         tag("of"),
@@ -40,7 +68,7 @@ pub(crate) fn parse_identifier(input: &str) -> IResult<&str, &str> {
     // Check to see if a keyword can be extracted from input
     if let Ok((i, keyword)) = parse_keyword(input) {
         // If the remaining input is empty
-        let next_char_is_ident = i.chars().nth(0).map_or(false, is_ident_ch);
+        let next_char_is_ident = i.chars().next().is_some_and(is_ident_ch);
 
         if i.is_empty() || !next_char_is_ident {
             return Err(nom::Err::Error(make_error(keyword, ErrorKind::Tag)));
@@ -49,13 +77,20 @@ pub(crate) fn parse_identifier(input: &str) -> IResult<&str, &str> {
 
     let (input, parsed) = take_while1(is_ident_ch)(input)?;
 
-    if parsed.chars().nth(0).unwrap().is_digit(10) {
+    if parsed.chars().next().unwrap().is_ascii_digit() {
         Err(nom::Err::Error(make_error(parsed, ErrorKind::Digit)))
     } else {
         Ok((input, parsed))
     }
 }
 
+/// Parse a name that a value is bound to, remembering where it is bound
+pub(crate) fn parse_binder(input: &str) -> IResult<&str, Binder> {
+    let (start, _) = sp(input)?;
+    let (end, name) = parse_identifier(start)?;
+    Ok((end, Binder::new(name).with_span(Span::new(start, end))))
+}
+
 pub(crate) fn parse_string(input: &str) -> IResult<&str, &str> {
     let mut end = 0;
     let mut skip_next = false;
@@ -73,11 +108,7 @@ pub(crate) fn parse_string(input: &str) -> IResult<&str, &str> {
 
         match ch {
             '"' => break,
-            '\\' => {
-                if input.chars().nth(n + 1).unwrap() == '"' {
-                    skip_next = true;
-                }
-            }
+            '\\' if input.chars().nth(n + 1).unwrap() == '"' => skip_next = true,
             _ => {}
         }
     }
@@ -86,8 +117,8 @@ pub(crate) fn parse_string(input: &str) -> IResult<&str, &str> {
     if quote_count == 2 {
         Ok((&input[end..], &input[0..end]))
     } else if quote_count > 0 {
-        return Err(nom::Err::Error(make_error(input, ErrorKind::Escaped)));
+        Err(nom::Err::Error(make_error(input, ErrorKind::Escaped)))
     } else {
-        return Err(nom::Err::Error(make_error(input, ErrorKind::Eof)));
+        Err(nom::Err::Error(make_error(input, ErrorKind::Eof)))
     }
 }
@@ -1,15 +1,18 @@
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    combinator::map,
+    combinator::{map, opt, recognize, verify},
     error::{make_error, ErrorKind},
-    multi::{many1, separated_list},
+    multi::{many0, many1, separated_list, separated_nonempty_list},
     number::complete::double,
     sequence::{delimited, preceded, separated_pair, tuple},
     IResult,
 };
 
-use crate::ast::Expression;
+use crate::{
+    ast::{Binder, Expression, Pattern},
+    diagnostic::Span,
+};
 use alloc::{
     collections::BTreeMap,
     rc::Rc,
@@ -18,39 +21,102 @@ use alloc::{
 };
 
 use crate::parse::{
-    arithmetic::parse_arithmetic,
-    basic::{parse_identifier, parse_string, sp},
-    lambda::{parse_abstraction, parse_application, parse_tailcall},
-    statements::parse_constructor_declaration,
+    arithmetic::{parse_arithmetic, parse_parenthesized},
+    basic::{located, parse_binder, parse_identifier, parse_string, sp},
+    lambda::{annotated_lambda, parse_abstraction, parse_dotted, parse_parameter},
 };
 
 pub fn parse_expression(input: &str) -> IResult<&str, Expression> {
-    if input == "" {
+    if input.is_empty() {
         return Err(nom::Err::Failure(make_error(input, ErrorKind::Eof)));
     }
-    if &input[0..1] == ")" {
+    if input.starts_with(')') {
         return Err(nom::Err::Failure(make_error(input, ErrorKind::Tag)));
     }
 
-    let (input, _) = sp(input)?;
-    alt((
+    located(alt((
         parse_if_then_else,
         parse_case_of,
+        parse_let,
         parse_deconstruct,
+        parse_dotted,
         parse_abstraction,
-        parse_tailcall,
-        parse_application,
         parse_arithmetic,
-        parse_atom,
         delimited(
             tuple((sp, tag("("), sp)),
             parse_expression,
             tuple((sp, tag(")"), sp)),
         ),
-    ))(input)
+    )))(input)
 }
 
+/// Parse an atom, along with any field accesses and record updates applied to it
+///
+/// p.x
+/// p { x = 3 }
+/// Point { x = 1, y = 2 }
 pub(crate) fn parse_atom(input: &str) -> IResult<&str, Expression> {
+    let (start, _) = sp(input)?;
+    let (mut input, mut result) = parse_primary(start)?;
+
+    loop {
+        if let Ok((rest, field)) = parse_field_access(input) {
+            result = Expression::Field(Rc::new(result), String::from(field));
+            input = rest;
+        } else if let Ok((rest, fields)) = parse_field_values(input) {
+            result = match result.unlocated() {
+                Expression::Identifier(name) if name.starts_with(char::is_uppercase) => {
+                    Expression::Record(name.clone(), fields)
+                }
+                _ => Expression::Update(Rc::new(result), fields),
+            };
+            input = rest;
+        } else {
+            break;
+        }
+        result = Expression::Located(Span::new(start, input), Rc::new(result));
+    }
+
+    let (input, _) = sp(input)?;
+    Ok((input, result))
+}
+
+/// Parse the name of a field being accessed, which directly follows the dot
+///
+/// .x
+fn parse_field_access(input: &str) -> IResult<&str, &str> {
+    let (rest, _) = tag(".")(input)?;
+    if !rest.starts_with(|c: char| c == '_' || c.is_alphabetic()) {
+        return Err(nom::Err::Error(make_error(rest, ErrorKind::Tag)));
+    }
+    parse_identifier(rest)
+}
+
+/// { x = 3, y = 4 }
+fn parse_field_values(input: &str) -> IResult<&str, Vec<(String, Rc<Expression>)>> {
+    let (input, fields) = delimited(
+        tuple((sp, tag("{"), sp)),
+        separated_nonempty_list(
+            tuple((sp, tag(","), sp)),
+            separated_pair(
+                parse_identifier,
+                tuple((sp, tag("="), sp)),
+                parse_expression,
+            ),
+        ),
+        tuple((sp, tag("}"), sp)),
+    )(input)?;
+
+    Ok((
+        input,
+        fields
+            .into_iter()
+            .map(|(field, value)| (String::from(field), Rc::new(value)))
+            .collect(),
+    ))
+}
+
+fn parse_primary(input: &str) -> IResult<&str, Expression> {
     let (input, result) = delimited(
         sp,
         alt((
@@ -70,18 +136,15 @@ pub(crate) fn parse_atom(input: &str) -> IResult<&str, Expression> {
             }),
             parse_abstraction,
             parse_constructor_instance,
+            delimited(sp, located(parse_hole), sp),
             delimited(
                 sp,
-                map(parse_identifier, |var| {
+                located(map(parse_identifier, |var| {
                     Expression::Identifier(String::from(var))
-                }),
+                })),
                 sp,
             ),
-            delimited(
-                tuple((sp, tag("("), sp)),
-                parse_expression,
-                tuple((sp, tag(")"), sp)),
-            ),
+            parse_parenthesized,
         )),
         sp,
     )(input)?;
@@ -89,6 +152,17 @@ pub(crate) fn parse_atom(input: &str) -> IResult<&str, Expression> {
     Ok((input, result))
 }
 
+/// Parse a hole, which stands in for an expression that is not written yet
+///
+/// ?todo
+/// ?
+pub(crate) fn parse_hole(input: &str) -> IResult<&str, Expression> {
+    map(
+        recognize(preceded(tag("?"), opt(parse_identifier))),
+        |hole| Expression::Hole(String::from(hole)),
+    )(input)
+}
+
 pub(crate) fn parse_if_then_else(input: &str) -> IResult<&str, Expression> {
     let (input, _) = tuple((sp, tag("if"), sp))(input)?;
     let (input, c) = parse_expression(input)?;
@@ -134,83 +208,175 @@ pub(crate) fn parse_table(input: &str) -> IResult<&str, Expression> {
     Ok((input, Expression::Table(table)))
 }
 
+/// Parse a pattern that a value is matched against
+///
+/// _
+/// x
+/// 0
+/// "yes"
+/// Just(Ok(x))
+/// shape @ Circle(r)
+pub(crate) fn parse_pattern(input: &str) -> IResult<&str, Pattern> {
+    delimited(
+        sp,
+        alt((
+            map(
+                tuple((parse_binder, tuple((sp, tag("@"), sp)), parse_pattern)),
+                |(name, _, pattern)| Pattern::As(name, Rc::new(pattern)),
+            ),
+            map(
+                tuple((
+                    verify(parse_identifier, |name: &str| {
+                        name.starts_with(char::is_uppercase)
+                    }),
+                    opt(delimited(
+                        tuple((sp, tag("("), sp)),
+                        separated_list(tuple((sp, tag(","), sp)), parse_pattern),
+                        tuple((sp, tag(")"), sp)),
+                    )),
+                )),
+                |(name, members)| {
+                    Pattern::Constructor(name.to_string(), members.unwrap_or_default())
+                },
+            ),
+            map(parse_binder, |name| match name.as_str() {
+                "_" => Pattern::Wildcard,
+                _ => Pattern::Variable(name),
+            }),
+            map(double, Pattern::Number),
+            map(parse_string, |s| {
+                Pattern::String(s[1..s.len() - 1].to_string())
+            }),
+            delimited(tag("("), parse_pattern, tag(")")),
+        )),
+        sp,
+    )(input)
+}
+
+/// Parse a case, which takes a value apart with the first arm whose pattern matches it,
+/// and whose guard holds if it has one
+///
+/// case m of
+///  | Just(x) if x > 0 => x
+///  | Just(x) => 0 - x
+///  | Nothing => 0
 pub(crate) fn parse_case_of(input: &str) -> IResult<&str, Expression> {
     let (input, _) = tuple((sp, tag("case"), sp))(input)?;
     let (input, value) = parse_expression(input)?;
     let (input, _) = tuple((sp, tag("of"), sp))(input)?;
-    let (input, cases) = many1(preceded(
+    let (input, arms) = many1(preceded(
         tag("|"),
-        separated_pair(parse_constructor_declaration, tag("=>"), parse_expression),
+        tuple((
+            parse_pattern,
+            opt(preceded(tag("if"), parse_expression)),
+            preceded(tag("=>"), parse_expression),
+        )),
     ))(input)?;
 
+    let value = Rc::new(value);
+    // Arms that only name the members of a constructor apply the arms to the value directly
+    if !arms
+        .iter()
+        .all(|(pattern, guard, _)| pattern.is_flat() && guard.is_none())
+    {
+        return Ok((
+            input,
+            Expression::Match {
+                values: vec![value],
+                arms: arms
+                    .into_iter()
+                    .map(|(pattern, guard, body)| {
+                        (vec![pattern], guard.map(Rc::new), Rc::new(body))
+                    })
+                    .collect(),
+            },
+        ));
+    }
+
+    let cases = arms
+        .into_iter()
+        .map(|(pattern, _, body)| match pattern {
+            Pattern::Constructor(cons_name, members) => (
+                cons_name,
+                members
+                    .into_iter()
+                    .map(|member| match member {
+                        Pattern::Variable(name) => name,
+                        member => Binder::new(member),
+                    })
+                    .collect(),
+                Rc::new(body),
+            ),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<(String, Vec<Binder>, Rc<Expression>)>>();
     Ok((
         input,
         Expression::CaseOf {
             data_type: None,
+            value,
+            cases,
+        },
+    ))
+}
+
+/// Parse the name, parameters, and value of a local value or function
+///
+/// total = a + b
+/// double n = n * 2
+pub(crate) fn parse_binding(input: &str) -> IResult<&str, (Binder, Expression)> {
+    // Capitalized names are constructors, which are taken apart instead
+    let (input, name) = verify(parse_binder, |name: &Binder| {
+        !name.starts_with(char::is_uppercase)
+    })(input)?;
+    let (input, parameters) = many0(parse_parameter)(input)?;
+    let (input, _) = tuple((sp, tag("="), sp))(input)?;
+    let (input, mut value) = parse_expression(input)?;
+
+    for (i, parameter) in parameters.into_iter().enumerate().rev() {
+        value = annotated_lambda(parameter, i, value);
+    }
+    Ok((input, (name, value)))
+}
+
+/// Parse a local value or function
+///
+/// let total = a + b in total * total
+/// let double n = n * 2 in double 4
+pub(crate) fn parse_let(input: &str) -> IResult<&str, Expression> {
+    let (input, _) = tuple((sp, tag("let"), sp))(input)?;
+    let (input, (name, value)) = parse_binding(input)?;
+    let (input, _) = tuple((sp, tag("in"), sp))(input)?;
+    let (input, body) = parse_expression(input)?;
+
+    Ok((
+        input,
+        Expression::Let {
+            name,
             value: Rc::new(value),
-            cases: cases
-                .iter()
-                .map(|(cons, expr)| {
-                    (
-                        cons.get_name().to_string(),
-                        cons.get_members().clone(),
-                        Rc::new(expr.clone()),
-                    )
-                })
-                .collect::<Vec<(String, Vec<String>, Rc<Expression>)>>(),
+            body: Rc::new(body),
         },
     ))
-    // // case input "> " of
-    // //  | Ok(val) => print "You said: " val
-    // //  | Err(_)  => print "There was a problem retrieving input"
-    // CaseOf {
-    //     /// This field is optional because the data type is derived from
-    //     /// the constructors. Likely, the first compiler pass will not
-    //     /// be able to determine the data type of the constructor cases.
-    //     data_type: Option<Data>,
-    //     /// The value to scrutinize
-    //     value: Rc<Self>,
-    //     /// Constructor name, constructor arguments, body of case
-    //     ///
-    //     /// Name Arguments Body of case
-    //     ///  |     |         |
-    //     ///  v v---/         v
-    //     /// Ok(x) => print "You said: " x
-    //     cases: Vec<(String, Vec<String>, Rc<Self>)>,
-    // },
-
-    // let (input, cons) = parse_constructor_declaration(input)?;
-    // let (input, _) = tuple((sp, tag("in"), sp))(input)?;
-    // let (input, body) = parse_expression(input)?;
-    // let cons_name = cons.get_name().to_string();
-    // let members = cons.get_members().clone();
-    // Ok((
-    //     input,
-    //     Expression::Deconstruct {
-    //         data_type: None,
-    //         cons_name,
-    //         members,
-    //         value: Rc::new(value),
-    //         body: Rc::new(body),
-    //     },
-    // ))
 }
 
 pub(crate) fn parse_deconstruct(input: &str) -> IResult<&str, Expression> {
     let (input, _) = tuple((sp, tag("let"), sp))(input)?;
-    let (input, cons) = parse_constructor_declaration(input)?;
+    let (input, cons_name) = parse_identifier(input)?;
+    let (input, members) = opt(delimited(
+        tuple((sp, tag("("), sp)),
+        separated_list(tuple((sp, tag(","), sp)), parse_binder),
+        tuple((sp, tag(")"), sp)),
+    ))(input)?;
     let (input, _) = tuple((sp, tag("="), sp))(input)?;
     let (input, value) = parse_expression(input)?;
     let (input, _) = tuple((sp, tag("in"), sp))(input)?;
     let (input, body) = parse_expression(input)?;
-    let cons_name = cons.get_name().to_string();
-    let members = cons.get_members().clone();
     Ok((
         input,
         Expression::Deconstruct {
             data_type: None,
-            cons_name,
-            members,
+            cons_name: cons_name.to_string(),
+            members: members.unwrap_or_default(),
             value: Rc::new(value),
             body: Rc::new(body),
         },
@@ -225,7 +391,10 @@ pub(crate) fn parse_constructor_instance(input: &str) -> IResult<&str, Expressio
         tuple((sp, tag(")"), sp)),
     )(input)?;
 
-    if args.len() <= 1 {
+    // A single member in parentheses could just be an argument to a function,
+    // so only constructors, which are capitalized, take them
+    let is_constructor = name.starts_with(char::is_uppercase);
+    if args.is_empty() || (args.len() == 1 && !is_constructor) {
         return Err(nom::Err::Error(make_error(input, ErrorKind::Many1)));
     }
 
@@ -241,3 +410,53 @@ pub(crate) fn parse_constructor_instance(input: &str) -> IResult<&str, Expressio
         },
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::testing::compile;
+
+    fn parse(input: &str) -> String {
+        let (rest, expr) = parse_expression(input).expect("the expression should parse");
+        assert!(
+            rest.is_empty(),
+            "the expression should parse up to {:?}",
+            rest
+        );
+        format!("{:?}", expr)
+    }
+
+    #[test]
+    fn local_bindings() {
+        assert_eq!(
+            parse("let total = a + b in total * total"),
+            "let total = (a + b) in ((total * total))"
+        );
+        assert_eq!(
+            parse("let double n = n * 2 in double 4"),
+            "let double = n.((n * 2)) in (double(4))"
+        );
+    }
+
+    #[test]
+    fn local_functions_are_generalized() {
+        let (result, codes) = compile(
+            "let main _ =\n\
+             \x20   let id x = x in\n\
+             \x20   let total = 3 + 4 in\n\
+             \x20   print (id total * total) (id \"!\")",
+        );
+        assert_eq!(result.as_deref(), Some("K(print(49.0)(\"!\"))"));
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
+
+    #[test]
+    fn local_bindings_are_scoped_to_their_body() {
+        let (result, codes) = compile(
+            "let f a = let b = a + 1 in b\n\
+             let main _ = print (f 1) b",
+        );
+        assert_eq!(result, None);
+        assert_eq!(codes, ["R0001"]);
+    }
+}
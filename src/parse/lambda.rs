@@ -1,21 +1,43 @@
 use nom::{
-    branch::alt, bytes::complete::tag, combinator::map, multi::many1, sequence::separated_pair,
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{map, verify},
+    error::{make_error, ErrorKind},
+    multi::{many0, many1},
+    sequence::{delimited, preceded, separated_pair, tuple},
     IResult,
 };
 
-use crate::ast::Expression;
+use crate::ast::{Binder, Expression, Pattern};
 use crate::parse::{
-    basic::{parse_identifier, sp},
-    expression::{parse_atom, parse_expression},
+    arithmetic::parse_arithmetic,
+    basic::{parse_binder, parse_identifier, sp},
+    expression::{parse_atom, parse_expression, parse_pattern},
+    types::parse_type,
+};
+use crate::types::Type;
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
 };
-use alloc::{rc::Rc, string::String};
+
+/// Parse an argument of a function application.
+/// Arguments cannot be negated without parentheses, so `n -1` is a subtraction.
+fn parse_argument(input: &str) -> IResult<&str, Expression> {
+    let (input, _) = sp(input)?;
+    if input.starts_with('-') {
+        return Err(nom::Err::Error(make_error(input, ErrorKind::Tag)));
+    }
+    alt((parse_atom, parse_abstraction))(input)
+}
 
 pub(crate) fn parse_tailcall(input: &str) -> IResult<&str, Expression> {
     // println!("parse_application: {:?}", input);
     let (input, _) = sp(input)?;
     let (input, _) = tag("rec")(input)?;
 
-    let (input, args) = many1(alt((parse_atom, parse_abstraction)))(input)?;
+    let (input, args) = many1(parse_argument)(input)?;
 
     Ok((
         input,
@@ -23,12 +45,13 @@ pub(crate) fn parse_tailcall(input: &str) -> IResult<&str, Expression> {
     ))
 }
 
+/// Parse an atom applied to any number of arguments
 pub(crate) fn parse_application(input: &str) -> IResult<&str, Expression> {
     // println!("parse_application: {:?}", input);
     let (input, _) = sp(input)?;
-    let (input, mut f) = alt((parse_atom, parse_application))(input)?;
+    let (input, mut f) = parse_atom(input)?;
 
-    let (input, args) = many1(alt((parse_atom, parse_abstraction)))(input)?;
+    let (input, args) = many0(parse_argument)(input)?;
 
     for arg in args {
         f = Expression::Application(Rc::new(f), Rc::new(arg));
@@ -37,11 +60,296 @@ pub(crate) fn parse_application(input: &str) -> IResult<&str, Expression> {
     Ok((input, f))
 }
 
+/// Parse a parameter of a function or lambda, with an optional type annotation.
+/// A parameter can take its argument apart with a pattern that every value of its type matches.
+///
+/// x
+/// (x : Num)
+/// Point(x, y)
+/// p @ Point(x, _)
+pub(crate) fn parse_parameter(input: &str) -> IResult<&str, (Pattern, Option<Type>)> {
+    let (input, _) = sp(input)?;
+    alt((
+        parse_annotated_parameter,
+        map(verify(parse_pattern, is_irrefutable), |pattern| {
+            (pattern, None)
+        }),
+    ))(input)
+}
+
+/// Parse a parameter of one clause of a function, which can also compare its argument
+/// with a number or a string, or match one of the constructors of a data type
+///
+/// 0
+/// Cons(x, Nil)
+pub(crate) fn parse_clause_parameter(input: &str) -> IResult<&str, (Pattern, Option<Type>)> {
+    let (input, _) = sp(input)?;
+    alt((
+        parse_annotated_parameter,
+        map(parse_pattern, |pattern| (pattern, None)),
+    ))(input)
+}
+
+fn parse_annotated_parameter(input: &str) -> IResult<&str, (Pattern, Option<Type>)> {
+    map(
+        delimited(
+            tuple((tag("("), sp)),
+            separated_pair(parse_binder, tuple((sp, tag(":"), sp)), parse_type),
+            tuple((sp, tag(")"))),
+        ),
+        |(var, annotation)| (Pattern::Variable(var), Some(annotation)),
+    )(input)
+}
+
+/// Whether a pattern can take apart a parameter without comparing it with anything
+fn is_irrefutable(pattern: &Pattern) -> bool {
+    match pattern {
+        Pattern::Wildcard | Pattern::Variable(_) => true,
+        Pattern::Constructor(_, members) => members.iter().all(is_irrefutable),
+        Pattern::As(_, inner) => is_irrefutable(inner),
+        Pattern::Number(_) | Pattern::String(_) => false,
+    }
+}
+
+/// Bind an argument to `name` and take it apart with a pattern,
+/// giving the name of the parameter that the argument is bound to
+///
+/// Point(x, Point(y, z)). body
+///   ==>  $parameter0. let Point(x, $parameter01) = $parameter0 in
+///                     let Point(y, z) = $parameter01 in body
+fn take_apart(pattern: &Pattern, name: String, body: Expression) -> (Binder, Expression) {
+    match pattern {
+        Pattern::Variable(var) => (var.clone(), body),
+        Pattern::Wildcard => (Binder::new("_"), body),
+        Pattern::As(var, inner) => match take_apart(inner, var.to_string(), body) {
+            (inner_name, body) if *inner_name == **var || *inner_name == "_" => (var.clone(), body),
+            // x @ y
+            (inner_name, body) => (
+                var.clone(),
+                Expression::Let {
+                    name: inner_name,
+                    value: Rc::new(Expression::Identifier(var.to_string())),
+                    body: Rc::new(body),
+                },
+            ),
+        },
+        Pattern::Constructor(cons_name, patterns) => {
+            let mut body = body;
+            let mut members = Vec::new();
+            for (i, member) in patterns.iter().enumerate().rev() {
+                let (member, inner) = take_apart(member, format!("{}{}", name, i), body);
+                members.insert(0, member);
+                body = inner;
+            }
+            let value = Rc::new(Expression::Identifier(name.clone()));
+            (
+                Binder::new(name),
+                Expression::Deconstruct {
+                    data_type: None,
+                    cons_name: cons_name.to_string(),
+                    members,
+                    value,
+                    body: Rc::new(body),
+                },
+            )
+        }
+        // Parameters are only parsed with irrefutable patterns
+        Pattern::Number(_) | Pattern::String(_) => unreachable!(),
+    }
+}
+
+/// The name of an argument that a pattern takes apart, by the position of its parameter
+pub(crate) fn parameter_name(position: usize) -> String {
+    format!("$parameter{}", position)
+}
+
+/// Make a lambda, taking apart its parameter if it is a pattern,
+/// and annotating its parameter if a type was given.
+/// The position of the parameter names the argument that a pattern takes apart.
+pub(crate) fn annotated_lambda(
+    (pattern, annotation): (Pattern, Option<Type>),
+    position: usize,
+    body: Expression,
+) -> Expression {
+    let (var, body) = take_apart(&pattern, parameter_name(position), body);
+    let lambda = Expression::Lambda(var, Rc::new(body));
+    match annotation {
+        Some(annotation) => {
+            let result = Type::Named(String::from("_"), Vec::new());
+            Expression::Annotated(Rc::new(lambda), Type::function(annotation, result))
+        }
+        None => lambda,
+    }
+}
+
+/// Parse a lambda, which can take several parameters
+///
+/// \x y -> body
+/// λx y. body
+/// x. body
 pub(crate) fn parse_abstraction(input: &str) -> IResult<&str, Expression> {
     // println!("parse_abstraction: {:?}", input);
     let (input, _) = sp(input)?;
+    alt((
+        map(
+            preceded(
+                tag("\\"),
+                separated_pair(
+                    many1(parse_parameter),
+                    tuple((sp, tag("->"))),
+                    parse_expression,
+                ),
+            ),
+            |(parameters, body)| lambdas(parameters, body),
+        ),
+        map(
+            preceded(
+                tag("λ"),
+                separated_pair(many1(parse_parameter), tag("."), parse_expression),
+            ),
+            |(parameters, body)| lambdas(parameters, body),
+        ),
+        parse_dotted_abstraction,
+    ))(input)
+}
+
+/// Make a lambda for each parameter, the first of which takes the first argument
+fn lambdas(parameters: Vec<(Pattern, Option<Type>)>, mut body: Expression) -> Expression {
+    for (i, parameter) in parameters.into_iter().enumerate().rev() {
+        body = annotated_lambda(parameter, i, body);
+    }
+    body
+}
+
+/// Parse a lambda of one parameter, written without a leading `\\` or `λ`
+///
+/// x. body
+fn parse_dotted_abstraction(input: &str) -> IResult<&str, Expression> {
+    // `p.x` is a field access when it is an argument or an operand
+    if starts_with_dotted_name(input) {
+        return Err(nom::Err::Error(make_error(input, ErrorKind::Tag)));
+    }
     map(
-        separated_pair(parse_identifier, tag("."), parse_expression),
-        |(var, expr)| Expression::Lambda(String::from(var), Rc::new(expr)),
+        separated_pair(parse_parameter, tag("."), parse_expression),
+        |(parameter, expr)| annotated_lambda(parameter, 0, expr),
     )(input)
 }
+
+/// Does the input start with a name followed directly by the name of a field, like `p.x`?
+fn starts_with_dotted_name(input: &str) -> bool {
+    parse_identifier(input).is_ok_and(|(rest, _)| {
+        rest.strip_prefix('.')
+            .and_then(|rest| rest.chars().next())
+            .is_some_and(|c| c == '_' || c.is_alphabetic())
+    })
+}
+
+/// Parse an expression that starts with a name followed directly by the name of a field.
+/// It reads the field if the name is in scope, and is a lambda otherwise,
+/// which is decided once the whole program is parsed.
+///
+/// p.x + 1
+/// a.b.a
+pub(crate) fn parse_dotted(input: &str) -> IResult<&str, Expression> {
+    if !starts_with_dotted_name(input) {
+        return Err(nom::Err::Error(make_error(input, ErrorKind::Tag)));
+    }
+    let lambda = map(
+        separated_pair(parse_binder, tag("."), parse_expression),
+        |(parameter, body)| Expression::Lambda(parameter, Rc::new(body)),
+    )(input);
+    match (lambda, parse_arithmetic(input)) {
+        (Ok((rest, lambda)), Ok((field_rest, field))) if rest == field_rest => Ok((
+            rest,
+            Expression::Dotted {
+                field: Rc::new(field),
+                lambda: Rc::new(lambda),
+            },
+        )),
+        (Ok(lambda), _) => Ok(lambda),
+        (Err(_), field) => field,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::testing::compile;
+
+    fn parse(input: &str) -> String {
+        let (rest, expr) = parse_expression(input).expect("the expression should parse");
+        assert!(
+            rest.is_empty(),
+            "the expression should parse up to {:?}",
+            rest
+        );
+        format!("{:?}", expr)
+    }
+
+    #[test]
+    fn patterns_take_apart_parameters() {
+        assert_eq!(
+            parse("Point(x, y). x + y"),
+            "$parameter0.(let Point(x, y, ) = $parameter0 in ((x + y)))"
+        );
+        assert_eq!(
+            parse("Pair(Point(x, _), b). x + b"),
+            "$parameter0.(let Pair($parameter00, b, ) = $parameter0 in \
+             (let Point(x, _, ) = $parameter00 in ((x + b))))"
+        );
+    }
+
+    #[test]
+    fn parameters_can_be_taken_apart() {
+        let (result, codes) = compile(
+            "type Point(x, y)\n\
+             let second Point(_, y) = y\n\
+             let add Point(x1, y1) p @ Point(x2, _) = Point(x1 + x2, y1 + second p)\n\
+             let sum Point(x, y) = x + y\n\
+             let main _ = print (sum (add Point(1, 2) Point(3, 4)))",
+        );
+        assert_eq!(result.as_deref(), Some("K(print(10.0))"));
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
+
+    #[test]
+    fn parameter_patterns_have_their_members_counted() {
+        let (result, codes) = compile(
+            "type Point(x, y)\n\
+             let first Point(x) = x\n\
+             let main _ = print (first Point(1, 2))",
+        );
+        assert_eq!(result, None);
+        assert_eq!(codes, ["R0104"]);
+    }
+
+    #[test]
+    fn parameters_only_take_apart_what_always_matches() {
+        assert!(parse_abstraction("Just(0). 1").is_err());
+    }
+
+    #[test]
+    fn lambdas_take_several_parameters() {
+        assert_eq!(parse("\\x y -> x"), "x.y.(x)");
+        assert_eq!(parse("λx y. x"), "x.y.(x)");
+        // The dotted form is still accepted
+        assert_eq!(parse("x.y.x"), "x.(y.(x))");
+        assert_eq!(parse("\\(x : Num) -> x"), "(x.(x) : Num -> _)");
+    }
+
+    #[test]
+    fn lambda_bodies_extend_as_far_as_possible() {
+        assert_eq!(parse("\\x -> x + 1"), "x.((x + 1))");
+        assert_eq!(parse("λf x. f x"), "f.x.(f(x))");
+    }
+
+    #[test]
+    fn lambdas_apply() {
+        let (result, codes) = compile(
+            "let apply f = f 3 4\n\
+             let main _ = print (apply (\\x y -> x * y)) (apply (λa b. a - b))",
+        );
+        assert_eq!(result.as_deref(), Some("K(print(12.0)(-1.0))"));
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
+}
@@ -3,3 +3,45 @@ pub(crate) mod basic;
 pub(crate) mod expression;
 pub(crate) mod lambda;
 pub(crate) mod statements;
+pub(crate) mod types;
+
+use crate::ast::Fixity;
+use alloc::{string::String, vec::Vec};
+use core::cell::RefCell;
+
+std::thread_local! {
+    /// The fixities declared in the program being parsed, which decide how its operators group
+    static FIXITIES: RefCell<Vec<Fixity>> = const { RefCell::new(Vec::new()) };
+    /// The operators defined as functions or methods in the program being parsed
+    static DEFINED_OPERATORS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The fixity declared for an operator in the program being parsed, if it has one
+pub(crate) fn declared_fixity(operator: &str) -> Option<Fixity> {
+    FIXITIES.with(|fixities| {
+        fixities
+            .borrow()
+            .iter()
+            .find(|fixity| fixity.get_operator() == operator)
+            .cloned()
+    })
+}
+
+/// Whether an operator is defined as a function or method in the program being parsed
+pub(crate) fn is_defined_operator(operator: &str) -> bool {
+    DEFINED_OPERATORS.with(|defined| defined.borrow().iter().any(|name| name == operator))
+}
+
+/// Parse with the fixities of the program's operators, and the operators it defines, known
+pub(crate) fn with_operators<T>(
+    fixities: Vec<Fixity>,
+    defined: Vec<String>,
+    parse: impl FnOnce() -> T,
+) -> T {
+    let previous_fixities = FIXITIES.with(|known| known.replace(fixities));
+    let previous_defined = DEFINED_OPERATORS.with(|known| known.replace(defined));
+    let result = parse();
+    FIXITIES.with(|known| known.replace(previous_fixities));
+    DEFINED_OPERATORS.with(|known| known.replace(previous_defined));
+    result
+}
@@ -1,23 +1,35 @@
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    combinator::{map, opt},
-    multi::{many0, many1, separated_list},
-    sequence::{delimited, tuple},
+    bytes::complete::{tag, take_while1},
+    character::complete::one_of,
+    combinator::{map, opt, verify},
+    multi::{many0, many1, separated_list, separated_nonempty_list},
+    sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
 
 use alloc::{
+    rc::Rc,
     string::{String, ToString},
     vec::Vec,
 };
+use core::iter;
 
 use crate::{
-    ast::{Constant, Constructor, Data, Function, Import, AST},
+    ast::{
+        Associativity, Binder, Class, Constant, Constructor, Data, Expression, Fixity, Function,
+        Import, Instance, Pattern, Signature, AST,
+    },
+    diagnostic::{LintLevel, Span},
     parse::{
+        arithmetic::{is_operator_char, parse_declared_operator, parse_operator_name},
         basic::{parse_identifier, sp},
-        expression::parse_expression,
+        expression::{parse_binding, parse_expression},
+        lambda::{annotated_lambda, parameter_name, parse_clause_parameter},
+        types::{parse_type, parse_type_atom},
+        with_operators,
     },
+    types::Type,
 };
 
 enum Statement {
@@ -26,9 +38,45 @@ enum Statement {
     Constructor(Constructor),
     Data(Data),
     Function(Function),
+    Signature(Signature),
+    Class(Class),
+    Instance(Instance, Vec<Function>),
+    Fixity(Fixity),
 }
 
 pub fn parse_ast(input: &str) -> IResult<&str, AST> {
+    // Operators group by their declared fixities, and `2*-3` only uses an operator `*-`
+    // if the program defines one, so parse again once they are known
+    let (rest, ast) = parse_statements(input)?;
+    let fixities = ast.get_fixities().clone();
+    let defined = defined_operators(&ast);
+    let (rest, mut ast) = if fixities.is_empty() && defined.is_empty() {
+        (rest, ast)
+    } else {
+        with_operators(fixities, defined, || parse_statements(input))?
+    };
+    // Whether `p.x` reads a field depends on the variables in scope where it is written
+    ast.resolve_dotted();
+    Ok((rest, ast))
+}
+
+/// The operators a program defines as functions or class methods that end in `-` or `!`,
+/// which would otherwise be read as a shorter operator followed by a negation
+fn defined_operators(ast: &AST) -> Vec<String> {
+    let functions = ast.get_functions().iter().map(Function::get_name);
+    let classes = ast.get_classes();
+    let methods = classes
+        .iter()
+        .flat_map(Class::get_methods)
+        .map(Signature::get_name);
+    functions
+        .chain(methods)
+        .filter(|name| name.chars().all(is_operator_char) && name.ends_with(['-', '!']))
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn parse_statements(input: &str) -> IResult<&str, AST> {
     let (input, _) = sp(input)?;
     // let (input, statements) = many0(terminated(
     //     delimited(
@@ -46,6 +94,12 @@ pub fn parse_ast(input: &str) -> IResult<&str, AST> {
         map(parse_import, Statement::Import),
         map(parse_data_declaration, Statement::Data),
         map(parse_type_declaration, Statement::Constructor),
+        map(parse_class_declaration, Statement::Class),
+        map(parse_instance_declaration, |(instance, methods)| {
+            Statement::Instance(instance, methods)
+        }),
+        map(parse_fixity_declaration, Statement::Fixity),
+        map(parse_signature, Statement::Signature),
         map(parse_constant_declaration, Statement::Constant),
         map(parse_function_declaration, Statement::Function),
     )))(input)?;
@@ -56,7 +110,29 @@ pub fn parse_ast(input: &str) -> IResult<&str, AST> {
     let mut cons = Vec::new();
     let mut data = Vec::new();
     let mut functions = Vec::new();
+    let mut signatures = Vec::new();
+    let mut fixities = Vec::new();
+
+    // The functions after an instance are its methods, until the first one
+    // that is not a method of its class
+    let mut classes = Class::builtins();
+    for stmt in &statements {
+        if let Statement::Class(class) = stmt {
+            classes.push(class.clone());
+        }
+    }
+    let is_method = |class: &String, function: &Function| {
+        classes.iter().any(|other| {
+            other.get_name() == class
+                && other
+                    .get_methods()
+                    .iter()
+                    .any(|method| method.get_name() == function.get_name())
+        })
+    };
 
+    let mut declared_classes = Vec::new();
+    let mut instances = Vec::new();
     for stmt in statements {
         match stmt {
             Statement::Import(i) => imports.push(i),
@@ -64,14 +140,25 @@ pub fn parse_ast(input: &str) -> IResult<&str, AST> {
             Statement::Constructor(c) => cons.push(c),
             Statement::Data(d) => data.push(d),
             Statement::Function(f) => functions.push(f),
+            Statement::Signature(s) => signatures.push(s),
+            Statement::Class(c) => declared_classes.push(c),
+            Statement::Fixity(f) => fixities.push(f),
+            Statement::Instance(mut instance, mut defined) => {
+                let count = defined
+                    .iter()
+                    .take_while(|f| is_method(instance.get_class(), f))
+                    .count();
+                functions.extend(defined.split_off(count));
+                instance.get_methods_mut().extend(defined);
+                instances.push(instance);
+            }
         }
     }
 
-    let mut ast = AST::new(imports, constants, cons, data, functions);
-    ast.resolve_tailcalls();
-    ast.replace_constructors();
-    ast.resolve_types();
-    ast.inline_functions();
+    let mut ast = AST::new(imports, constants, cons, data, functions, signatures)
+        .with_classes(declared_classes, instances)
+        .with_fixities(fixities);
+    ast.derive_instances();
     Ok((input, ast))
 }
 
@@ -92,58 +179,370 @@ pub fn parse_import(input: &str) -> IResult<&str, Import> {
 }
 
 pub fn parse_data_declaration(input: &str) -> IResult<&str, Data> {
-    let (input, _) = tuple((sp, tag("data"), sp))(input)?;
+    let (input, lint_levels) = parse_attributes(input)?;
+    let (start, _) = sp(input)?;
+    let (input, _) = tuple((tag("data"), sp))(start)?;
     let (input, name) = parse_identifier(input)?;
+    let (input, parameters) = many0(parse_identifier)(input)?;
     let (input, _) = tuple((sp, tag("="), sp))(input)?;
-    let (input, constructors) = separated_list(tag("|"), parse_constructor_declaration)(input)?;
+    let (input, constructors) = separated_list(
+        tag("|"),
+        alt((parse_record_declaration, parse_constructor_declaration)),
+    )(input)?;
+    let (input, _) = sp(input)?;
+    let end = input;
+    let (input, deriving) = opt(parse_deriving)(input)?;
+
+    Ok((
+        input,
+        Data::new(String::from(name), constructors)
+            .with_parameters(parameters.iter().map(ToString::to_string).collect())
+            .with_deriving(deriving.unwrap_or_default())
+            .with_span(Span::new(start, end))
+            .with_lint_levels(lint_levels),
+    ))
+}
+
+/// Parse the classes a data type derives instances of, and where each was named
+///
+/// deriving (Show, Eq, Ord)
+/// deriving Show
+fn parse_deriving(input: &str) -> IResult<&str, Vec<(String, Span)>> {
+    let class = |input| {
+        let (start, _) = sp(input)?;
+        let (input, name) = parse_identifier(start)?;
+        Ok((input, (name.to_string(), Span::new(start, input))))
+    };
+    let (input, _) = tuple((sp, tag("deriving"), sp))(input)?;
+    let (input, classes) = alt((
+        delimited(
+            tuple((tag("("), sp)),
+            separated_nonempty_list(tuple((sp, tag(","), sp)), class),
+            tuple((sp, tag(")"))),
+        ),
+        map(class, |class| vec![class]),
+    ))(input)?;
+    let (input, _) = sp(input)?;
+    Ok((input, classes))
+}
+
+/// Parse a class, and the signatures of its methods
+///
+/// class Show a where show : a -> String
+pub fn parse_class_declaration(input: &str) -> IResult<&str, Class> {
+    let (start, _) = sp(input)?;
+    let (input, _) = tuple((tag("class"), sp))(start)?;
+    let (input, name) = parse_identifier(input)?;
+    let (input, parameter) = parse_identifier(input)?;
+    let (input, _) = tuple((sp, tag("where"), sp))(input)?;
+    let (input, methods) = many0(parse_method_signature)(input)?;
     let (input, _) = sp(input)?;
 
-    Ok((input, Data::new(String::from(name), constructors)))
+    Ok((
+        input,
+        Class::new(name.to_string(), parameter.to_string(), methods)
+            .with_span(Span::new(start, input)),
+    ))
+}
+
+fn parse_method_signature(input: &str) -> IResult<&str, Signature> {
+    let (start, _) = sp(input)?;
+    let (input, name) = parse_identifier(start)?;
+    let (input, _) = tuple((sp, tag(":"), sp))(input)?;
+    let (input, annotation) = parse_type(input)?;
+    let (input, _) = sp(input)?;
+
+    Ok((
+        input,
+        Signature::new(name.to_string(), annotation).with_span(Span::new(start, input)),
+    ))
+}
+
+/// Parse an instance of a class, and every function declaration after it.
+/// The functions that are not methods of the class are split off when the AST is built.
+///
+/// instance Show Shape where
+///     let show s = "shape"
+pub fn parse_instance_declaration(input: &str) -> IResult<&str, (Instance, Vec<Function>)> {
+    let (start, _) = sp(input)?;
+    let (input, _) = tuple((tag("instance"), sp))(start)?;
+    let (input, class) = parse_identifier(input)?;
+    let (input, instance_type) = verify(parse_type_atom, |t| match t {
+        Type::Named(name, _) => name.starts_with(char::is_uppercase),
+        _ => false,
+    })(input)?;
+    let (end, _) = tuple((sp, tag("where"), sp))(input)?;
+    let (input, methods) = many0(parse_function_declaration)(end)?;
+
+    Ok((
+        input,
+        (
+            Instance::new(class.to_string(), instance_type, Vec::new())
+                .with_span(Span::new(start, end)),
+            methods,
+        ),
+    ))
 }
 
 pub fn parse_type_declaration(input: &str) -> IResult<&str, Constructor> {
     let (input, _) = tuple((sp, tag("type"), sp))(input)?;
-    let (input, cons) = parse_constructor_declaration(input)?;
+    let (input, cons) = alt((parse_record_declaration, parse_constructor_declaration))(input)?;
     let (input, _) = sp(input)?;
 
     Ok((input, cons))
 }
 
+fn parse_lint_level(input: &str) -> IResult<&str, LintLevel> {
+    alt((
+        map(tag("allow"), |_| LintLevel::Allow),
+        map(tag("warn"), |_| LintLevel::Warn),
+        map(tag("deny"), |_| LintLevel::Deny),
+    ))(input)
+}
+
+fn parse_lint_name(input: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_')(input)
+}
+
+/// Parse attributes that set lint levels for the following declaration
+///
+/// @allow(unused-variables, R0303)
+/// @deny(shadowing)
+fn parse_attributes(input: &str) -> IResult<&str, Vec<(String, LintLevel)>> {
+    let (input, attributes) = many0(tuple((
+        delimited(sp, tag("@"), sp),
+        parse_lint_level,
+        delimited(
+            tuple((sp, tag("("), sp)),
+            separated_list(tuple((sp, tag(","), sp)), parse_lint_name),
+            tuple((sp, tag(")"), sp)),
+        ),
+    )))(input)?;
+
+    let mut result = Vec::new();
+    for (_, level, lints) in attributes {
+        for lint in lints {
+            result.push((lint.to_string(), level));
+        }
+    }
+    Ok((input, result))
+}
+
+/// Parse the constraints on the type variables of an annotation
+///
+/// Show a =>
+/// (Show a, Eq b) =>
+fn parse_constraints(input: &str) -> IResult<&str, Vec<(String, String)>> {
+    fn parse_constraint(input: &str) -> IResult<&str, (String, String)> {
+        map(
+            tuple((parse_identifier, parse_identifier)),
+            |(class, variable)| (class.to_string(), variable.to_string()),
+        )(input)
+    }
+
+    map(
+        opt(terminated(
+            alt((
+                map(parse_constraint, |constraint| vec![constraint]),
+                delimited(
+                    tuple((sp, tag("("), sp)),
+                    separated_list(tuple((sp, tag(","), sp)), parse_constraint),
+                    tuple((sp, tag(")"))),
+                ),
+            )),
+            tuple((sp, tag("=>"), sp)),
+        )),
+        Option::unwrap_or_default,
+    )(input)
+}
+
+/// Parse the name of a function, which is an operator when it is written in parentheses
+///
+/// add
+/// (<+>)
+fn parse_function_name(input: &str) -> IResult<&str, &str> {
+    let (input, _) = sp(input)?;
+    alt((parse_identifier, parse_operator_name))(input)
+}
+
+/// Parse the precedence and associativity of infix operators
+///
+/// infixl 6 <+>, <->
+/// infixr 5 ++
+/// infix 4 `elem`
+pub fn parse_fixity_declaration(input: &str) -> IResult<&str, Fixity> {
+    let (start, _) = sp(input)?;
+    let (input, associativity) = alt((
+        map(tag("infixl"), |_| Associativity::Left),
+        map(tag("infixr"), |_| Associativity::Right),
+        map(tag("infix"), |_| Associativity::Neither),
+    ))(start)?;
+    let (input, precedence) = preceded(sp, one_of("0123456789"))(input)?;
+    let (input, operator) = parse_declared_operator(input)?;
+    let (input, _) = sp(input)?;
+
+    let precedence = precedence.to_digit(10).unwrap_or_default() as u8;
+    Ok((
+        input,
+        Fixity::new(operator, associativity, precedence).with_span(Span::new(start, input)),
+    ))
+}
+
+/// Parse the type annotation of a function or constant
+///
+/// let add : Num -> Num -> Num
+/// let show_twice : Show a => a -> String
+/// let (<+>) : Num -> Num -> Num
+/// const True : Bool
+pub fn parse_signature(input: &str) -> IResult<&str, Signature> {
+    let (start, _) = sp(input)?;
+    let (input, _) = tuple((alt((tag("let"), tag("const"))), sp))(start)?;
+    let (input, name) = parse_function_name(input)?;
+    let (input, _) = tuple((sp, tag(":"), sp))(input)?;
+    let (input, constraints) = parse_constraints(input)?;
+    let (input, annotation) = parse_type(input)?;
+    let (input, _) = sp(input)?;
+
+    Ok((
+        input,
+        Signature::new(name.to_string(), annotation)
+            .with_constraints(constraints)
+            .with_span(Span::new(start, input)),
+    ))
+}
+
 pub fn parse_constant_declaration(input: &str) -> IResult<&str, Constant> {
-    let (input, _) = tuple((sp, tag("const"), sp))(input)?;
+    let (input, lint_levels) = parse_attributes(input)?;
+    let (start, _) = sp(input)?;
+    let (input, _) = tuple((tag("const"), sp))(start)?;
     let (input, name) = parse_identifier(input)?;
     let (input, _) = tuple((sp, tag("="), sp))(input)?;
     let (input, expr) = parse_expression(input)?;
     let (input, _) = sp(input)?;
 
-    Ok((input, Constant::new(name.to_string(), expr)))
+    Ok((
+        input,
+        Constant::new(name.to_string(), expr)
+            .with_span(Span::new(start, input))
+            .with_lint_levels(lint_levels),
+    ))
 }
 
+/// Parse a function, which can be defined by clauses that each match its arguments with patterns.
+/// The clauses of a function are written one after another, and are tried from top to bottom.
+///
+/// let fact 0 = 1
+/// let fact n = n * fact (n - 1)
 pub fn parse_function_declaration(input: &str) -> IResult<&str, Function> {
-    let (input, _) = tuple((sp, tag("let"), sp))(input)?;
-    let (input, name) = parse_identifier(input)?;
-    let (input, args) = many1(parse_identifier)(input)?;
-    let (input, _) = tuple((sp, tag("="), sp))(input)?;
-    let (input, body) = parse_expression(input)?;
+    let (input, lint_levels) = parse_attributes(input)?;
+    let (start, _) = sp(input)?;
+    let (input, (name, first)) = parse_clause(start)?;
+    let (input, rest) = many0(verify(parse_clause, |(other, clause)| {
+        *other == name && clause.0.len() == first.0.len()
+    }))(input)?;
     let (input, _) = sp(input)?;
+
+    let (parameters, mut body) = first;
+    let is_simple = parameters
+        .iter()
+        .all(|(pattern, _)| matches!(pattern, Pattern::Variable(_) | Pattern::Wildcard));
+    let parameters = if rest.is_empty() && is_simple {
+        parameters
+    } else {
+        // let fact 0 = 1
+        // let fact n = n * fact (n - 1)
+        //   ==>  let fact $parameter0 = case $parameter0 of | 0 => 1 | n => n * fact (n - 1)
+        let mut arms = Vec::new();
+        let rest = rest.into_iter().map(|(_, clause)| clause);
+        for (parameters, body) in iter::once((parameters.clone(), body)).chain(rest) {
+            let patterns = parameters.into_iter().map(|(pattern, _)| pattern).collect();
+            arms.push((patterns, None, Rc::new(body)));
+        }
+        body = Expression::Match {
+            values: (0..parameters.len())
+                .map(|i| Rc::new(Expression::Identifier(parameter_name(i))))
+                .collect(),
+            arms,
+        };
+        // Parameters are annotated by the first clause
+        parameters
+            .into_iter()
+            .enumerate()
+            .map(|(i, (_, annotation))| {
+                (
+                    Pattern::Variable(Binder::new(parameter_name(i))),
+                    annotation,
+                )
+            })
+            .collect()
+    };
+
+    // Parameters with type annotations are annotated lambdas
+    for (i, parameter) in parameters.into_iter().enumerate().rev() {
+        body = annotated_lambda(parameter, i, body);
+    }
+
     Ok((
         input,
-        Function::new(
-            name.to_string(),
-            args.iter()
-                .map(ToString::to_string)
-                .collect::<Vec<String>>(),
-            body,
-        ),
+        Function::new(name.to_string(), Vec::new(), body)
+            .with_span(Span::new(start, input))
+            .with_lint_levels(lint_levels),
     ))
 }
 
+/// The parameters and body of one clause of a function
+type Clause = (Vec<(Pattern, Option<Type>)>, Expression);
+
+/// Parse one clause of a function, with the helpers of its `where` clause bound around its body
+///
+/// let area s = w * h where w = s.width, h = s.height
+fn parse_clause(input: &str) -> IResult<&str, (&str, Clause)> {
+    let (input, _) = tuple((sp, tag("let"), sp))(input)?;
+    let (input, name) = parse_function_name(input)?;
+    let (input, parameters) = many1(parse_clause_parameter)(input)?;
+    let (input, _) = tuple((sp, tag("="), sp))(input)?;
+    let (input, mut body) = parse_expression(input)?;
+    let (input, helpers) = opt(preceded(
+        tuple((sp, tag("where"), sp)),
+        separated_nonempty_list(tuple((sp, tag(","), sp)), parse_binding),
+    ))(input)?;
+
+    // let area s = w * h where w = width s, h = height s
+    //   ==>  let area s = let w = width s in let h = height s in w * h
+    for (name, value) in order_helpers(helpers.unwrap_or_default()).into_iter().rev() {
+        body = Expression::Let {
+            name,
+            value: Rc::new(value),
+            body: Rc::new(body),
+        };
+    }
+    Ok((input, (name, (parameters, body))))
+}
+
+/// Order the helpers of a `where` clause so that each one is bound after the helpers it uses,
+/// which lets them be written in any order
+fn order_helpers(mut helpers: Vec<(Binder, Expression)>) -> Vec<(Binder, Expression)> {
+    let mut ordered = Vec::new();
+    while !helpers.is_empty() {
+        let ready = helpers
+            .iter()
+            .position(|(_, value)| !helpers.iter().any(|(other, _)| value.has_binding(other)))
+            // Helpers that use each other keep the order they were written in
+            .unwrap_or(0);
+        ordered.push(helpers.remove(ready));
+    }
+    ordered
+}
+
+/// Parse a constructor, and the types of its members
+///
+/// Cons(a, List a)
 pub fn parse_constructor_declaration(input: &str) -> IResult<&str, Constructor> {
-    let (input, _) = sp(input)?;
-    let (input, name) = parse_identifier(input)?;
+    let (start, _) = sp(input)?;
+    let (input, name) = parse_identifier(start)?;
     let (input, opt_args) = opt(delimited(
         tuple((sp, tag("("), sp)),
-        separated_list(tuple((sp, tag(","), sp)), parse_identifier),
+        separated_list(tuple((sp, tag(","), sp)), parse_type),
         tuple((sp, tag(")"), sp)),
     ))(input)?;
     let (input, _) = sp(input)?;
@@ -152,11 +551,117 @@ pub fn parse_constructor_declaration(input: &str) -> IResult<&str, Constructor>
 
     Ok((
         input,
-        Constructor::new(
-            String::from(name),
-            args.iter()
-                .map(ToString::to_string)
-                .collect::<Vec<String>>(),
-        ),
+        Constructor::positional(String::from(name), args).with_span(Span::new(start, input)),
     ))
 }
+
+/// Parse a constructor with named fields. A field without a type is named after the
+/// parameter of its data type that it holds.
+///
+/// Point { x : Num, y : Num }
+/// Pair { a, b }
+pub fn parse_record_declaration(input: &str) -> IResult<&str, Constructor> {
+    let field = |input| {
+        let (input, name) = parse_identifier(input)?;
+        let (input, t) = opt(preceded(tuple((sp, tag(":"), sp)), parse_type))(input)?;
+        let t = t.unwrap_or_else(|| Type::Named(name.to_string(), Vec::new()));
+        Ok((input, (name.to_string(), t)))
+    };
+    let (start, _) = sp(input)?;
+    let (input, name) = parse_identifier(start)?;
+    let (input, fields) = delimited(
+        tuple((sp, tag("{"), sp)),
+        separated_nonempty_list(tuple((sp, tag(","), sp)), field),
+        tuple((sp, tag("}"), sp)),
+    )(input)?;
+
+    Ok((
+        input,
+        Constructor::record(String::from(name), fields).with_span(Span::new(start, input)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::check::testing::{compile, parse};
+
+    #[test]
+    fn where_clauses_see_the_parameters() {
+        let (result, codes) = compile(
+            "let area w h = size where size = w * h\n\
+             let main _ = print (area 3 4)",
+        );
+        assert_eq!(result.as_deref(), Some("K(print(12.0))"));
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
+
+    #[test]
+    fn helpers_can_be_written_in_any_order() {
+        let (result, codes) = compile(
+            "let scaled n k = grow n\n\
+             \x20   where grow m = m * factor,\n\
+             \x20         factor = k + 1\n\
+             let main _ = print (scaled 2 1)",
+        );
+        assert_eq!(result.as_deref(), Some("K(print(4.0))"));
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
+
+    #[test]
+    fn helpers_are_scoped_to_their_function() {
+        let (result, codes) = compile(
+            "let area w h = size where size = w * h\n\
+             let main _ = print (area 3 4) size",
+        );
+        assert_eq!(result, None);
+        assert_eq!(codes, ["R0001"]);
+    }
+
+    #[test]
+    fn clauses_are_grouped_into_one_function() {
+        let ast = parse(
+            "let fact 0 = 1\n\
+             let fact n = n * fact (n - 1)\n\
+             let main _ = print (fact 5)",
+        );
+        let names = ast
+            .get_functions()
+            .iter()
+            .map(|function| function.get_name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["fact", "main"]);
+    }
+
+    #[test]
+    fn clauses_are_tried_from_top_to_bottom() {
+        let (result, codes) = compile(
+            "let sign 0 = \"zero\"\n\
+             let sign n = if n < 0 then \"negative\" else \"positive\"\n\
+             let main _ = print (sign 0) (sign 3)",
+        );
+        assert_eq!(result.as_deref(), Some("K(print(\"zero\")(\"positive\"))"));
+        assert!(codes.is_empty(), "{:?}", codes);
+    }
+
+    #[test]
+    fn clauses_with_other_arities_are_duplicates() {
+        let (result, codes) = compile(
+            "let add a b = a + b\n\
+             let add a = a\n\
+             let main _ = print (add 1 2)",
+        );
+        assert_eq!(result, None);
+        assert_eq!(codes.first(), Some(&"R0003"));
+    }
+
+    #[test]
+    fn each_clause_has_its_own_where_clause() {
+        let (result, codes) = compile(
+            "let sign 0 = zero where zero = 0\n\
+             let sign n = n - zero\n\
+             let main _ = print (sign 2)",
+        );
+        assert_eq!(result, None);
+        assert_eq!(codes, ["R0001"]);
+    }
+}
@@ -0,0 +1,60 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{map, not, opt},
+    multi::many0,
+    sequence::{delimited, preceded, terminated, tuple},
+    IResult,
+};
+
+use crate::{
+    parse::basic::{parse_identifier, sp},
+    types::Type,
+};
+use alloc::{string::ToString, vec::Vec};
+
+/// Parse a type annotation
+///
+/// Num -> Maybe a -> (a -> b) -> Bool
+pub(crate) fn parse_type(input: &str) -> IResult<&str, Type> {
+    let (input, argument) = parse_type_application(input)?;
+    let (input, result) = opt(preceded(tuple((sp, tag("->"), sp)), parse_type))(input)?;
+
+    Ok((
+        input,
+        match result {
+            Some(result) => Type::function(argument, result),
+            None => argument,
+        },
+    ))
+}
+
+/// Parse a named type applied to its parameters, such as `Result a String`
+fn parse_type_application(input: &str) -> IResult<&str, Type> {
+    alt((
+        map(
+            tuple((
+                terminated(parse_identifier, not(tuple((sp, tag(":"))))),
+                many0(parse_type_atom),
+            )),
+            |(name, parameters)| Type::Named(name.to_string(), parameters),
+        ),
+        parse_type_atom,
+    ))(input)
+}
+
+/// Parse a type name or a parenthesized type.
+/// A name followed by `:` starts the next signature, so it is not part of the type.
+pub(crate) fn parse_type_atom(input: &str) -> IResult<&str, Type> {
+    delimited(
+        sp,
+        alt((
+            map(
+                terminated(parse_identifier, not(tuple((sp, tag(":"))))),
+                |name| Type::Named(name.to_string(), Vec::new()),
+            ),
+            delimited(tuple((tag("("), sp)), parse_type, tuple((sp, tag(")")))),
+        )),
+        sp,
+    )(input)
+}
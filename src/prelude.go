@@ -1,6 +1,9 @@
 package main
 
-import "fmt"
+import (
+	"fmt"
+	"math"
+)
 
 type Combinator interface {
 	apply(Combinator) Combinator
@@ -36,6 +39,64 @@ func (s Substitution) String() string {
 	}
 }
 
+type Bluebird struct {
+	x *Combinator
+	y *Combinator
+}
+
+func (b Bluebird) data() interface{} { return nil }
+
+func (b Bluebird) apply(arg Combinator) Combinator {
+	if b.x == nil {
+		b.x = &arg
+	} else if b.y == nil {
+		b.y = &arg
+	} else {
+		return (*b.x).apply((*b.y).apply(arg))
+	}
+
+	return b
+}
+
+func (b Bluebird) String() string {
+	if b.x == nil {
+		return "B"
+	} else if b.y == nil {
+		return fmt.Sprintf("B(%v)", *b.x)
+	} else {
+		return fmt.Sprintf("B(%v)(%v)", *b.x, *b.y)
+	}
+}
+
+type Cardinal struct {
+	x *Combinator
+	y *Combinator
+}
+
+func (c Cardinal) data() interface{} { return nil }
+
+func (c Cardinal) apply(arg Combinator) Combinator {
+	if c.x == nil {
+		c.x = &arg
+	} else if c.y == nil {
+		c.y = &arg
+	} else {
+		return (*c.x).apply(arg).apply(*c.y)
+	}
+
+	return c
+}
+
+func (c Cardinal) String() string {
+	if c.x == nil {
+		return "C"
+	} else if c.y == nil {
+		return fmt.Sprintf("C(%v)", *c.x)
+	} else {
+		return fmt.Sprintf("C(%v)(%v)", *c.x, *c.y)
+	}
+}
+
 type Constant struct {
 	x *Combinator
 }
@@ -194,6 +255,8 @@ func (f Foreign) String() string {
 }
 
 var S = Substitution{nil, nil}
+var B = Bluebird{nil, nil}
+var C = Cardinal{nil, nil}
 var K = Constant{nil}
 var I = Identity{}
 
@@ -202,68 +265,162 @@ func print_f(arg Combinator) Combinator {
 	return make_foreign("print", print_f)
 }
 
-func recurse_f(x Combinator) Combinator {
-	// return make_foreign("recurse", func(x Combinator) Combinator {
-	// 	return make_foreign("recurse", func(v Combinator) Combinator {
-	// 		return x.apply(x).apply(v)
-	// 	})
-	// }).apply(make_foreign("recurse", func(x Combinator) Combinator {
-	// 		return make_foreign("recurse", func(v Combinator) Combinator {
-	// 			return x.apply(x).apply(v)
-	// 		})
-	// 	}))
-	return make_foreign("y.y(x x y)", func(y Combinator) Combinator {
-		return y.apply(x.apply(x).apply(y))
-	}).apply(make_foreign("recurse", recurse_f))
+// Recursion left to run at runtime, which unfolds like `Y`: `recurse f x = f (recurse f) x`
+func recurse_f(f Combinator) Combinator {
+	return make_foreign("recurse", func(x Combinator) Combinator {
+		return f.apply(recurse_f(f)).apply(x)
+	})
+}
+
+func hole_f(name Combinator) Combinator {
+	panic(fmt.Sprintf("reached the hole `%v`", name))
 }
 
 var print = make_foreign("print", print_f)
 var recurse = make_foreign("recurse", recurse_f)
+var hole = make_foreign("hole", hole_f)
 
 
-type Sub struct {}
-
-func (f Sub) data() interface{} { return nil }
+var True Combinator = K
+var False Combinator = K.apply(I)
 
-func (f Sub) apply(x Combinator) Combinator {
-	return make_foreign("sub", func(y Combinator) Combinator {
-		return make_f64(x.data().(float64) - y.data().(float64))
-	})
+func boolean(b bool) Combinator {
+	if b {
+		return True
+	}
+	return False
 }
 
-type Mul struct {}
+var inf = math.Inf(1)
+var nan = math.NaN()
+
+func println_f(arg Combinator) Combinator {
+	fmt.Println(arg)
+	return make_foreign("println", println_f)
+}
 
-func (f Mul) data() interface{} { return nil }
+var println = make_foreign("println", println_f)
 
-func (f Mul) apply(x Combinator) Combinator {
-	return make_foreign("sub", func(y Combinator) Combinator {
-		return make_f64(x.data().(float64) * y.data().(float64))
+// The fixpoint combinator, `Y f x = f (Y f) x`
+func y_f(f Combinator) Combinator {
+	return make_foreign("Y", func(x Combinator) Combinator {
+		return f.apply(y_f(f)).apply(x)
 	})
 }
 
-type IfThen struct {}
+var Y = make_foreign("Y", y_f)
 
-func (f IfThen) data() interface{} { return nil }
-
-func (f IfThen) apply(cond Combinator) Combinator {
-	return make_foreign("if", func(x Combinator) Combinator {
-		return make_foreign("if", func(y Combinator) Combinator {
-			return cond.apply(I).apply(x).apply(y).apply(I)
+func binary(name string, f func(Combinator, Combinator) Combinator) Foreign {
+	return make_foreign(name, func(x Combinator) Combinator {
+		return make_foreign(name, func(y Combinator) Combinator {
+			return f(x, y)
 		})
 	})
 }
 
-var sub = Sub {}
-var mul = Mul {}
-var If = IfThen {}
-var greater = make_foreign("greater", func(x Combinator) Combinator {
-	return make_foreign("greater", func(y Combinator) Combinator {
-		if x.data().(float64) > y.data().(float64) {
-			return K
-		} else {
-			return K.apply(I)
+func number(x Combinator) float64 {
+	n, ok := x.data().(float64)
+	if !ok {
+		panic(fmt.Sprintf("expected a number, found `%v`", x))
+	}
+	return n
+}
+
+// Compares two numbers or two strings, reporting -1, 0 or 1.
+// The second result is false when the values cannot be ordered, as with NaN.
+func compare(x Combinator, y Combinator) (int, bool) {
+	if a, ok := x.data().(string); ok {
+		if b, ok := y.data().(string); ok {
+			if a < b {
+				return -1, true
+			} else if a > b {
+				return 1, true
+			}
+			return 0, true
 		}
-	})
+	}
+
+	a, b := number(x), number(y)
+	if a < b {
+		return -1, true
+	} else if a > b {
+		return 1, true
+	} else if a == b {
+		return 0, true
+	}
+	return 0, false
+}
+
+func equal(x Combinator, y Combinator) bool {
+	switch a := x.data().(type) {
+	case float64:
+		b, ok := y.data().(float64)
+		return ok && a == b
+	case string:
+		b, ok := y.data().(string)
+		return ok && a == b
+	}
+	return fmt.Sprint(x) == fmt.Sprint(y)
+}
+
+var add = binary("add", func(x Combinator, y Combinator) Combinator {
+	if a, ok := x.data().(string); ok {
+		if b, ok := y.data().(string); ok {
+			return make_str(a + b)
+		}
+	}
+	return make_f64(number(x) + number(y))
+})
+
+var sub = binary("sub", func(x Combinator, y Combinator) Combinator {
+	return make_f64(number(x) - number(y))
+})
+
+var mul = binary("mul", func(x Combinator, y Combinator) Combinator {
+	return make_f64(number(x) * number(y))
+})
+
+var div = binary("div", func(x Combinator, y Combinator) Combinator {
+	return make_f64(number(x) / number(y))
+})
+
+var neg = make_foreign("neg", func(x Combinator) Combinator {
+	return make_f64(-number(x))
+})
+
+var greater = binary("greater", func(x Combinator, y Combinator) Combinator {
+	order, ok := compare(x, y)
+	return boolean(ok && order > 0)
+})
+
+var less = binary("less", func(x Combinator, y Combinator) Combinator {
+	order, ok := compare(x, y)
+	return boolean(ok && order < 0)
+})
+
+var greatereq = binary("greatereq", func(x Combinator, y Combinator) Combinator {
+	order, ok := compare(x, y)
+	return boolean(ok && order >= 0)
+})
+
+var lesseq = binary("lesseq", func(x Combinator, y Combinator) Combinator {
+	order, ok := compare(x, y)
+	return boolean(ok && order <= 0)
+})
+
+var eq = binary("eq", func(x Combinator, y Combinator) Combinator {
+	return boolean(equal(x, y))
+})
+
+var neq = binary("neq", func(x Combinator, y Combinator) Combinator {
+	return boolean(!equal(x, y))
+})
+
+var show = make_foreign("show", func(x Combinator) Combinator {
+	if s, ok := x.data().(string); ok {
+		return make_str(fmt.Sprintf("%q", s))
+	}
+	return make_str(fmt.Sprint(x))
 })
 
 func main() {
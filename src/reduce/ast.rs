@@ -1,17 +1,30 @@
 use crate::{
     ast::AST,
+    code::NO_ENTRY_POINT,
+    diagnostic::Diagnostics,
     lambda::Lambda,
     reduce::{Reduce, ReductionError},
 };
 
 impl Reduce<()> for AST {
-    fn reduce(&self, _: &()) -> Result<Lambda, ReductionError> {
-        for function in self.get_functions() {
+    fn reduce(&self, _: &(), diagnostics: &mut Diagnostics) -> Result<Lambda, ReductionError> {
+        self.check(diagnostics);
+        let mut ast = self.clone();
+        ast.lower(diagnostics);
+        diagnostics.apply_levels();
+        if diagnostics.has_errors() {
+            return Err(ReductionError::CheckFailed);
+        }
+
+        for function in ast.get_functions() {
             if function.get_name() == "main" {
-                return function.reduce(self.get_data());
+                let result = function.reduce(ast.get_data(), diagnostics);
+                diagnostics.apply_levels();
+                return result;
             }
         }
 
+        diagnostics.error(NO_ENTRY_POINT, "no `main` function is defined");
         Err(ReductionError::NoEntryPoint)
     }
 }
@@ -1,5 +1,6 @@
 use crate::{
     ast::{Constructor, Data},
+    diagnostic::Diagnostics,
     lambda::Lambda,
     reduce::{Reduce, ReductionError},
 };
@@ -9,7 +10,7 @@ impl Reduce<Data> for Constructor {
     // Ok     := x.ok.err.(ok x)
     // Err    := e.ok.err.(err e)
     // data Result = Ok(x) | Err(e)
-    fn reduce(&self, data: &Data) -> Result<Lambda, ReductionError> {
+    fn reduce(&self, data: &Data, _: &mut Diagnostics) -> Result<Lambda, ReductionError> {
         let mut members = self.get_members().clone();
         let mut cons = data.get_constructors();
         cons.reverse();
@@ -35,7 +36,7 @@ impl Reduce<Data> for Constructor {
 impl Reduce<()> for Constructor {
     // Square := color.piece.f.(f color piece)
     // type Square(color, piece)
-    fn reduce(&self, _: &()) -> Result<Lambda, ReductionError> {
+    fn reduce(&self, _: &(), _: &mut Diagnostics) -> Result<Lambda, ReductionError> {
         let mut members = self.get_members().clone();
 
         let mut result = Lambda::var("f");
@@ -1,11 +1,12 @@
 use crate::{
     ast::Data,
+    diagnostic::Diagnostics,
     lambda::Lambda,
     reduce::{Reduce, ReductionError},
 };
 
 impl Reduce<()> for Data {
-    fn reduce(&self, _: &()) -> Result<Lambda, ReductionError> {
+    fn reduce(&self, _: &(), _: &mut Diagnostics) -> Result<Lambda, ReductionError> {
         let mut cons = self.get_constructors();
 
         let mut result = Lambda::var("enum");
@@ -1,162 +1,166 @@
 use crate::{
-    ast::{Constructor, Data, Expression},
-    builtin, foreign,
+    ast::{Binder, Constructor, Data, Expression},
+    builtin,
+    code::MISMATCHED_SIGNATURES,
+    diagnostic::Diagnostics,
+    foreign,
     lambda::Lambda,
     reduce::{Reduce, ReductionError},
-    Combinator, Nil, K, S,
+    Combinator, Nil, B, K, S,
 };
 
 use alloc::{collections::BTreeMap, vec::Vec};
 
-fn y(f: Combinator) -> Combinator {
-    builtin("Y", move |x| {
-        loop {
-            let result = f.applied_to(x.clone());
-            // println!("result {:?}", result);
-            result.applied_to(builtin("Y", y).applied_to(result.clone()));
-        }
+/// The fixpoint combinator, `Y f x = f (Y f) x`.
+/// `Y f` waits for an argument before it unfolds, so it only unfolds as far as it is used.
+pub(crate) fn y() -> Combinator {
+    builtin("Y", 2, |args| {
+        let f = &args[0];
+        f.applied_to(y().applied_to(f.clone()))
+            .applied_to(args[1].clone())
     })
 }
 
 impl Reduce<Vec<Data>> for Expression {
-    fn reduce(&self, d: &Vec<Data>) -> Result<Lambda, ReductionError> {
+    fn reduce(
+        &self,
+        d: &Vec<Data>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Lambda, ReductionError> {
         Ok(match self {
-            Self::Greater(a, b) => Lambda::Combinator(builtin("greater", move |c| {
-                builtin("greater", move |d| match (c.clone(), d) {
+            Self::Greater(a, b) => {
+                Lambda::Combinator(builtin("greater", 2, |args| match (&args[0], &args[1]) {
                     (Combinator::Number(m), Combinator::Number(n)) if m > n => K,
+                    (Combinator::String(m), Combinator::String(n)) if m > n => K,
                     _ => S.applied_to(K),
-                })
-            }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
-            Self::Less(a, b) => Lambda::Combinator(builtin("less", move |c| {
-                builtin("less", move |d| match (c.clone(), d) {
+                }))
+                .applied_to(a.reduce(d, diagnostics)?)
+                .applied_to(b.reduce(d, diagnostics)?)
+            }
+            Self::Less(a, b) => {
+                Lambda::Combinator(builtin("less", 2, |args| match (&args[0], &args[1]) {
                     (Combinator::Number(m), Combinator::Number(n)) if m < n => K,
+                    (Combinator::String(m), Combinator::String(n)) if m < n => K,
                     _ => S.applied_to(K),
-                })
-            }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
-            Self::LessEqual(a, b) => Lambda::Combinator(builtin("lesseq", move |c| {
-                builtin("lesseq", move |d| match (c.clone(), d) {
+                }))
+                .applied_to(a.reduce(d, diagnostics)?)
+                .applied_to(b.reduce(d, diagnostics)?)
+            }
+            Self::LessEqual(a, b) => {
+                Lambda::Combinator(builtin("lesseq", 2, |args| match (&args[0], &args[1]) {
                     (Combinator::Number(m), Combinator::Number(n)) if m <= n => K,
+                    (Combinator::String(m), Combinator::String(n)) if m <= n => K,
                     _ => S.applied_to(K),
-                })
-            }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
-            Self::GreaterEqual(a, b) => Lambda::Combinator(builtin("greatereq", move |c| {
-                builtin("greatereq", move |d| match (c.clone(), d) {
+                }))
+                .applied_to(a.reduce(d, diagnostics)?)
+                .applied_to(b.reduce(d, diagnostics)?)
+            }
+            Self::GreaterEqual(a, b) => {
+                Lambda::Combinator(builtin("greatereq", 2, |args| match (&args[0], &args[1]) {
                     (Combinator::Number(m), Combinator::Number(n)) if m >= n => K,
+                    (Combinator::String(m), Combinator::String(n)) if m >= n => K,
                     _ => S.applied_to(K),
-                })
-            }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
-            Self::Add(a, b) => Lambda::Combinator(builtin("add", move |c| {
-                builtin("add", move |d| match (c.clone(), d) {
+                }))
+                .applied_to(a.reduce(d, diagnostics)?)
+                .applied_to(b.reduce(d, diagnostics)?)
+            }
+            Self::Add(a, b) => {
+                Lambda::Combinator(builtin("add", 2, |args| match (&args[0], &args[1]) {
                     (Combinator::Number(m), Combinator::Number(n)) => Combinator::Number(m + n),
                     (Combinator::String(m), Combinator::String(n)) => {
-                        Combinator::String(m.clone() + &n)
+                        Combinator::String(m.clone() + n)
                     }
                     _ => Nil,
-                })
-            }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
-            Self::Subtract(a, b) => Lambda::Combinator(builtin("sub", move |c| {
-                builtin("sub", move |d| match (c.clone(), d) {
+                }))
+                .applied_to(a.reduce(d, diagnostics)?)
+                .applied_to(b.reduce(d, diagnostics)?)
+            }
+            Self::Subtract(a, b) => {
+                Lambda::Combinator(builtin("sub", 2, |args| match (&args[0], &args[1]) {
                     (Combinator::Number(m), Combinator::Number(n)) => Combinator::Number(m - n),
                     _ => Nil,
-                })
-            }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
-            Self::Multiply(a, b) => Lambda::Combinator(builtin("mul", move |c| {
-                builtin("mul", move |d| match (c.clone(), d) {
+                }))
+                .applied_to(a.reduce(d, diagnostics)?)
+                .applied_to(b.reduce(d, diagnostics)?)
+            }
+            Self::Multiply(a, b) => {
+                Lambda::Combinator(builtin("mul", 2, |args| match (&args[0], &args[1]) {
                     (Combinator::Number(m), Combinator::Number(n)) => Combinator::Number(m * n),
                     _ => Nil,
-                })
-            }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
-            Self::Divide(a, b) => Lambda::Combinator(builtin("div", move |c| {
-                builtin("div", move |d| match (c.clone(), d) {
+                }))
+                .applied_to(a.reduce(d, diagnostics)?)
+                .applied_to(b.reduce(d, diagnostics)?)
+            }
+            Self::Divide(a, b) => {
+                Lambda::Combinator(builtin("div", 2, |args| match (&args[0], &args[1]) {
                     (Combinator::Number(m), Combinator::Number(n)) => Combinator::Number(m / n),
                     _ => Nil,
-                })
-            }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
+                }))
+                .applied_to(a.reduce(d, diagnostics)?)
+                .applied_to(b.reduce(d, diagnostics)?)
+            }
             Self::TailCall(args) => {
                 let mut result = Lambda::var(Expression::RECURSION_ARGUMENT);
                 for arg in args.clone() {
-                    result = result.applied_to(arg.reduce(d)?);
+                    result = result.applied_to(arg.reduce(d, diagnostics)?);
                 }
                 result
             }
             Self::And(a, b) => {
-                let a = a.reduce(d)?;
-                let b = b.reduce(d)?;
+                let a = a.reduce(d, diagnostics)?;
+                let b = b.reduce(d, diagnostics)?;
                 a.clone().applied_to(b).applied_to(a)
             }
             Self::Or(a, b) => {
-                let a = a.reduce(d)?;
-                let b = b.reduce(d)?;
+                let a = a.reduce(d, diagnostics)?;
+                let b = b.reduce(d, diagnostics)?;
                 a.clone().applied_to(a).applied_to(b)
             }
             Self::Not(a) => {
-                let a = a.reduce(d)?;
+                let a = a.reduce(d, diagnostics)?;
                 let t = Lambda::lambda("a", Lambda::lambda("b", Lambda::var("a")));
                 let f = Lambda::lambda("a", Lambda::lambda("b", Lambda::var("b")));
                 a.applied_to(f).applied_to(t)
             }
-            Self::Equal(a, b) => Lambda::Combinator(builtin("eq", |a| {
-                builtin(format!("eq({:?})", a), move |b| {
-                    if a == b {
-                        Lambda::lambda("a", Lambda::lambda("b", Lambda::var("a"))).to_combinator()
-                    } else {
-                        Lambda::lambda("a", Lambda::lambda("b", Lambda::var("b"))).to_combinator()
-                    }
-                })
-            }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
-            Self::NotEqual(a, b) => Lambda::Combinator(builtin("neq", |a| {
-                builtin(format!("neq({:?})", a), move |b| {
-                    if a == b {
-                        Lambda::lambda("a", Lambda::lambda("b", Lambda::var("b"))).to_combinator()
-                    } else {
-                        Lambda::lambda("a", Lambda::lambda("b", Lambda::var("a"))).to_combinator()
-                    }
-                })
+            Self::Equal(a, b) => Lambda::Combinator(builtin("eq", 2, |args| {
+                if args[0] == args[1] {
+                    Lambda::lambda("a", Lambda::lambda("b", Lambda::var("a"))).to_combinator()
+                } else {
+                    Lambda::lambda("a", Lambda::lambda("b", Lambda::var("b"))).to_combinator()
+                }
             }))
-            .applied_to(a.reduce(d)?)
-            .applied_to(b.reduce(d)?),
-            Self::Negate(a) => Lambda::Combinator(builtin("-", |val| {
-                if let Combinator::Number(n) = val {
-                    Combinator::Number(-n)
+            .applied_to(a.reduce(d, diagnostics)?)
+            .applied_to(b.reduce(d, diagnostics)?),
+            Self::NotEqual(a, b) => Lambda::Combinator(builtin("neq", 2, |args| {
+                if args[0] == args[1] {
+                    Lambda::lambda("a", Lambda::lambda("b", Lambda::var("b"))).to_combinator()
                 } else {
-                    val.clone()
+                    Lambda::lambda("a", Lambda::lambda("b", Lambda::var("a"))).to_combinator()
                 }
             }))
-            .applied_to(a.reduce(d)?),
+            .applied_to(a.reduce(d, diagnostics)?)
+            .applied_to(b.reduce(d, diagnostics)?),
+            Self::Negate(a) => Lambda::Combinator(builtin("neg", 1, |args| match &args[0] {
+                Combinator::Number(n) => Combinator::Number(-n),
+                val => val.clone(),
+            }))
+            .applied_to(a.reduce(d, diagnostics)?),
             Self::IfThenElse {
                 condition,
                 then_case,
                 else_case,
             } => {
                 if then_case.number_of_arguments() != else_case.number_of_arguments() {
-                    println!(
-                        "Warning: Different type signatures for branches of if expression \n\"{:?}\"",
-                        self
+                    diagnostics.warning(
+                        MISMATCHED_SIGNATURES,
+                        "different type signatures for branches of if expression",
                     );
                 }
 
                 condition
-                    .reduce(d)?
-                    .applied_to(then_case.reduce(d)?)
-                    .applied_to(else_case.reduce(d)?)
+                    .reduce(d, diagnostics)?
+                    .applied_to(then_case.reduce(d, diagnostics)?)
+                    .applied_to(else_case.reduce(d, diagnostics)?)
             }
             Self::CaseOf {
                 data_type,
@@ -164,25 +168,28 @@ impl Reduce<Vec<Data>> for Expression {
                 cases,
                 ..
             } => {
-                let data = data_type.clone().unwrap();
-                let mut result = data.reduce(&())?;
+                let data = data_type.clone().ok_or(ReductionError::UnresolvedType)?;
+                let mut result = data.reduce(&(), diagnostics)?;
                 let mut cases = cases.clone();
                 cases.sort_by(
                     |(left_name, left_members, _), (right_name, right_members, _)| {
-                        Constructor::new(left_name.clone(), left_members.clone())
-                            .cmp(&Constructor::new(right_name.clone(), right_members.clone()))
+                        let names = |members: &[Binder]| {
+                            members.iter().map(|m| m.get_name().clone()).collect()
+                        };
+                        Constructor::new(left_name.clone(), names(left_members))
+                            .cmp(&Constructor::new(right_name.clone(), names(right_members)))
                     },
                 );
-                result = result.applied_to(value.reduce(d)?);
+                result = result.applied_to(value.reduce(d, diagnostics)?);
 
                 let first_signature = cases[0].2.number_of_arguments();
                 for (_, mut members, body) in cases {
-                    let mut case_lambda = body.reduce(d)?;
+                    let mut case_lambda = body.reduce(d, diagnostics)?;
 
                     if body.number_of_arguments() != first_signature {
-                        println!(
-                            "Warning: Different type signatures for cases in expression \n\"{:?}\"",
-                            self
+                        diagnostics.warning(
+                            MISMATCHED_SIGNATURES,
+                            "different type signatures for cases of case expression",
                         );
                     }
 
@@ -198,17 +205,17 @@ impl Reduce<Vec<Data>> for Expression {
             Self::Construct {
                 data_type, members, ..
             } => {
-                let data_type = data_type.clone().unwrap();
-                let mut cons = data_type.reduce(&())?;
+                let data_type = data_type.clone().ok_or(ReductionError::UnresolvedType)?;
+                let mut cons = data_type.reduce(&(), diagnostics)?;
                 for enumeration in d {
                     if enumeration.get_constructors().contains(&data_type) {
-                        cons = data_type.reduce(enumeration)?;
+                        cons = data_type.reduce(enumeration, diagnostics)?;
                     }
                 }
 
                 let mut result = cons;
                 for member in members {
-                    result = result.applied_to(member.reduce(d)?);
+                    result = result.applied_to(member.reduce(d, diagnostics)?);
                 }
                 result
             }
@@ -218,8 +225,8 @@ impl Reduce<Vec<Data>> for Expression {
                 body,
                 ..
             } => {
-                let cons = value.reduce(d)?;
-                let mut result = body.reduce(d)?;
+                let cons = value.reduce(d, diagnostics)?;
+                let mut result = body.reduce(d, diagnostics)?;
                 let mut members = members.clone();
                 members.reverse();
                 for member in members {
@@ -227,12 +234,32 @@ impl Reduce<Vec<Data>> for Expression {
                 }
                 cons.applied_to(result)
             }
+            // Records are lowered onto constructors once their types are known
+            Self::Record(_, _) | Self::Field(_, _) | Self::Update(_, _) => {
+                return Err(ReductionError::UnresolvedType)
+            }
+            // Patterns are compiled to decision trees before reduction
+            Self::Match { .. } => return Err(ReductionError::UnresolvedType),
+            // Dotted expressions are resolved once the program is parsed
+            Self::Dotted { .. } => unreachable!(),
+            // A hole stops the program when it is reached, if it is allowed to be compiled
+            Self::Hole(name) => Lambda::Combinator(foreign("hole"))
+                .applied_to(Lambda::Combinator(Combinator::String(name.clone()))),
             Self::Identifier(i) => match i.as_str() {
                 "true" => Lambda::Combinator(K),
                 "false" => Lambda::Combinator(S.applied_to(K)),
                 "print" => Lambda::Combinator(foreign("print")),
                 "println" => Lambda::Combinator(foreign("println")),
-                "Y" => Lambda::Combinator(builtin("Y", y)),
+                "Y" => Lambda::Combinator(y()),
+                Self::COMPOSE => Lambda::Combinator(B),
+                Self::RUNTIME_RECURSION => Lambda::Combinator(foreign("recurse")),
+                Self::SHOW_PRIMITIVE => {
+                    Lambda::Combinator(builtin("show", 1, |args| match &args[0] {
+                        Combinator::Number(n) => Combinator::String(format!("{}", n)),
+                        Combinator::String(s) => Combinator::String(format!("{:?}", s)),
+                        otherwise => Combinator::String(format!("{}", otherwise)),
+                    }))
+                }
                 // "Y" => Lambda::Combinator(builtin("Y", move |f| {
                 //     builtin("Y", move |x| {
                 //         loop {
@@ -243,21 +270,32 @@ impl Reduce<Vec<Data>> for Expression {
                 // }),
                 other => Lambda::var(other),
             },
-            Self::Application(left, right) => left.reduce(d)?.applied_to(right.reduce(d)?),
-            Self::Lambda(arg, body) => Lambda::lambda(arg, body.reduce(d)?),
+            Self::Application(left, right) => left
+                .reduce(d, diagnostics)?
+                .applied_to(right.reduce(d, diagnostics)?),
+            Self::Let { name, value, body } => Lambda::lambda(name, body.reduce(d, diagnostics)?)
+                .applied_to(value.reduce(d, diagnostics)?),
+            Self::Located(span, expr) => {
+                let from = diagnostics.len();
+                let result = expr.reduce(d, diagnostics)?;
+                diagnostics.place(from, *span);
+                result
+            }
+            Self::Annotated(expr, _) => expr.reduce(d, diagnostics)?,
+            Self::Lambda(arg, body) => Lambda::lambda(arg, body.reduce(d, diagnostics)?),
             Self::Number(n) => Lambda::Combinator(Combinator::Number(*n)),
             Self::String(s) => Lambda::Combinator(Combinator::String(s.clone())),
             Self::List(list) => {
                 let mut result = Vec::new();
                 for item in list {
-                    result.push(item.reduce(d)?.to_combinator());
+                    result.push(item.reduce(d, diagnostics)?.to_combinator());
                 }
                 Lambda::Combinator(Combinator::List(result))
             }
             Self::Table(map) => {
                 let mut result = BTreeMap::new();
                 for (k, v) in map {
-                    result.insert(k.to_string(), v.reduce(d)?.to_combinator());
+                    result.insert(k.to_string(), v.reduce(d, diagnostics)?.to_combinator());
                 }
                 Lambda::Combinator(Combinator::Table(result))
             }
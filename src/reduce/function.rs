@@ -1,12 +1,17 @@
 use crate::{
     ast::{Data, Function},
+    diagnostic::Diagnostics,
     lambda::Lambda,
     reduce::{Reduce, ReductionError},
 };
 
 impl Reduce<Vec<Data>> for Function {
-    fn reduce(&self, d: &Vec<Data>) -> Result<Lambda, ReductionError> {
-        self.get_body().reduce(d)
+    fn reduce(
+        &self,
+        d: &Vec<Data>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Lambda, ReductionError> {
+        self.get_body().reduce(d, diagnostics)
     }
 }
 
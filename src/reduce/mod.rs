@@ -3,13 +3,16 @@ pub(crate) mod constructor;
 pub(crate) mod data;
 pub(crate) mod expression;
 pub(crate) mod function;
-use crate::lambda::Lambda;
+use crate::{diagnostic::Diagnostics, lambda::Lambda};
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub enum ReductionError {
     NoEntryPoint,
+    UnresolvedType,
+    /// The checks before reduction found errors, which are in the diagnostics
+    CheckFailed,
 }
 
 pub trait Reduce<T> {
-    fn reduce(&self, t: &T) -> Result<Lambda, ReductionError>;
+    fn reduce(&self, t: &T, diagnostics: &mut Diagnostics) -> Result<Lambda, ReductionError>;
 }
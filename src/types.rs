@@ -0,0 +1,370 @@
+use alloc::{
+    collections::BTreeMap,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{Display, Error, Formatter};
+
+/// The type of an expression, as found by type inference
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum Type {
+    /// A type that is not known yet, or that a scheme is generic over
+    Variable(usize),
+    /// A named type, such as `Num`, `String`, or a data type, with its parameters
+    Named(String, Vec<Self>),
+    /// A function from the first type to the second
+    Function(Rc<Self>, Rc<Self>),
+}
+
+impl Type {
+    pub const NUMBER: &'static str = "Num";
+    pub const STRING: &'static str = "String";
+    pub const LIST: &'static str = "List";
+    pub const TABLE: &'static str = "Table";
+
+    pub fn named(name: impl ToString) -> Self {
+        Self::Named(name.to_string(), Vec::new())
+    }
+
+    pub fn number() -> Self {
+        Self::named(Self::NUMBER)
+    }
+
+    pub fn string() -> Self {
+        Self::named(Self::STRING)
+    }
+
+    pub fn function(argument: Self, result: Self) -> Self {
+        Self::Function(Rc::new(argument), Rc::new(result))
+    }
+
+    /// A Church encoded boolean, which chooses between two values of any type
+    pub fn boolean(choice: Self) -> Self {
+        Self::function(choice.clone(), Self::function(choice.clone(), choice))
+    }
+
+    /// Every type variable in the type, in the order they first appear
+    pub fn variables(&self) -> Vec<usize> {
+        let mut result = Vec::new();
+        self.collect_variables(&mut result);
+        result
+    }
+
+    fn collect_variables(&self, result: &mut Vec<usize>) {
+        match self {
+            Self::Variable(v) => {
+                if !result.contains(v) {
+                    result.push(*v)
+                }
+            }
+            Self::Named(_, parameters) => {
+                for parameter in parameters {
+                    parameter.collect_variables(result);
+                }
+            }
+            Self::Function(a, b) => {
+                a.collect_variables(result);
+                b.collect_variables(result);
+            }
+        }
+    }
+
+    pub fn has_variable(&self, v: usize) -> bool {
+        match self {
+            Self::Variable(other) => *other == v,
+            Self::Named(_, parameters) => parameters.iter().any(|p| p.has_variable(v)),
+            Self::Function(a, b) => a.has_variable(v) || b.has_variable(v),
+        }
+    }
+
+    /// Replace type variables with the types they are mapped to
+    pub fn substitute(&self, substitution: &BTreeMap<usize, Self>) -> Self {
+        match self {
+            Self::Variable(v) => match substitution.get(v) {
+                Some(t) => t.clone(),
+                None => self.clone(),
+            },
+            Self::Named(name, parameters) => Self::Named(
+                name.clone(),
+                parameters
+                    .iter()
+                    .map(|p| p.substitute(substitution))
+                    .collect(),
+            ),
+            Self::Function(a, b) => {
+                Self::function(a.substitute(substitution), b.substitute(substitution))
+            }
+        }
+    }
+
+    /// Write the type, naming its variables `a`, `b`, `c`, ... in the order they appear
+    fn write(&self, names: &BTreeMap<usize, String>, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Self::Variable(v) => match names.get(v) {
+                Some(name) => write!(f, "{}", name),
+                None => write!(f, "?{}", v),
+            },
+            Self::Named(name, parameters) => {
+                write!(f, "{}", name)?;
+                for parameter in parameters {
+                    write!(f, " ")?;
+                    match parameter {
+                        Self::Named(_, p) if !p.is_empty() => {
+                            write!(f, "(")?;
+                            parameter.write(names, f)?;
+                            write!(f, ")")?;
+                        }
+                        Self::Function(_, _) => {
+                            write!(f, "(")?;
+                            parameter.write(names, f)?;
+                            write!(f, ")")?;
+                        }
+                        _ => parameter.write(names, f)?,
+                    }
+                }
+                Ok(())
+            }
+            Self::Function(a, b) => {
+                if let Self::Function(_, _) = **a {
+                    write!(f, "(")?;
+                    a.write(names, f)?;
+                    write!(f, ")")?;
+                } else {
+                    a.write(names, f)?;
+                }
+                write!(f, " -> ")?;
+                b.write(names, f)
+            }
+        }
+    }
+}
+
+/// Name type variables `a` through `z`, then `a1`, `b1`, ...
+fn variable_names(variables: &[usize]) -> BTreeMap<usize, String> {
+    let mut names = BTreeMap::new();
+    for (i, v) in variables.iter().enumerate() {
+        let letter = (b'a' + (i % 26) as u8) as char;
+        let name = if i < 26 {
+            letter.to_string()
+        } else {
+            format!("{}{}", letter, i / 26)
+        };
+        names.insert(*v, name);
+    }
+    names
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        self.write(&variable_names(&self.variables()), f)
+    }
+}
+
+/// Display two types, giving the same names to the variables they share
+pub(crate) fn display_pair(a: &Type, b: &Type) -> (String, String) {
+    let names = display_all(&[a, b]);
+    (names[0].clone(), names[1].clone())
+}
+
+/// Display several types, giving the same names to the variables they share
+pub(crate) fn display_all(types: &[&Type]) -> Vec<String> {
+    let mut variables = Vec::new();
+    for t in types {
+        for v in t.variables() {
+            if !variables.contains(&v) {
+                variables.push(v);
+            }
+        }
+    }
+
+    struct Named<'a>(&'a Type, &'a BTreeMap<usize, String>);
+    impl Display for Named<'_> {
+        fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+            self.0.write(self.1, f)
+        }
+    }
+
+    let names = variable_names(&variables);
+    types.iter().map(|t| Named(t, &names).to_string()).collect()
+}
+
+/// A type that is generic over some of its type variables
+///
+/// Every use of a top level function gets a fresh copy of these variables,
+/// so `let id x = x` can be used on both numbers and strings.
+/// A scheme can require its variables to have instances of classes,
+/// such as `Show a => a -> String`.
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct Scheme {
+    variables: Vec<usize>,
+    constraints: Vec<(String, usize)>,
+    body: Type,
+}
+
+impl Scheme {
+    pub fn new(variables: Vec<usize>, body: Type) -> Self {
+        Self {
+            variables,
+            constraints: Vec::new(),
+            body,
+        }
+    }
+
+    /// Require each variable to have an instance of its class.
+    /// Every use of the scheme is given a dictionary for each constraint, in order.
+    pub fn with_constraints(mut self, constraints: Vec<(String, usize)>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// A scheme that is not generic over any variables
+    pub fn monomorphic(body: Type) -> Self {
+        Self::new(Vec::new(), body)
+    }
+
+    /// The scheme described by a type annotation.
+    ///
+    /// Lowercase names are type variables, every `_` is a different variable,
+    /// and `Bool` is a Church encoded boolean.
+    pub fn annotation(annotation: &Type) -> Self {
+        Self::constrained_annotation(annotation, &[])
+    }
+
+    /// The scheme described by a type annotation, with constraints on its variables
+    /// written as the name of a class and a variable, like `Show a`
+    pub fn constrained_annotation(annotation: &Type, constraints: &[(String, String)]) -> Self {
+        fn is_variable(name: &str, parameters: &[Type]) -> bool {
+            parameters.is_empty() && name != "_" && name.starts_with(char::is_lowercase)
+        }
+
+        fn collect_names(t: &Type, names: &mut Vec<String>) {
+            match t {
+                Type::Named(name, parameters) if is_variable(name, parameters) => {
+                    if !names.contains(name) {
+                        names.push(name.clone())
+                    }
+                }
+                Type::Named(_, parameters) => {
+                    for parameter in parameters {
+                        collect_names(parameter, names);
+                    }
+                }
+                Type::Function(a, b) => {
+                    collect_names(a, names);
+                    collect_names(b, names);
+                }
+                Type::Variable(_) => {}
+            }
+        }
+
+        fn convert(t: &Type, names: &[String], next: &mut usize) -> Type {
+            match t {
+                Type::Named(name, parameters) if is_variable(name, parameters) => {
+                    Type::Variable(names.iter().position(|other| other == name).unwrap_or(0))
+                }
+                Type::Named(name, parameters) if parameters.is_empty() && name == "_" => {
+                    *next += 1;
+                    Type::Variable(*next - 1)
+                }
+                Type::Named(name, parameters) if parameters.is_empty() && name == "Bool" => {
+                    *next += 1;
+                    Type::boolean(Type::Variable(*next - 1))
+                }
+                Type::Named(name, parameters) => Type::Named(
+                    name.clone(),
+                    parameters.iter().map(|p| convert(p, names, next)).collect(),
+                ),
+                Type::Function(a, b) => {
+                    Type::function(convert(a, names, next), convert(b, names, next))
+                }
+                Type::Variable(_) => t.clone(),
+            }
+        }
+
+        let mut names = Vec::new();
+        collect_names(annotation, &mut names);
+        for (_, variable) in constraints {
+            if !names.contains(variable) {
+                names.push(variable.clone())
+            }
+        }
+        let mut next = names.len();
+        let body = convert(annotation, &names, &mut next);
+        let constraints = constraints
+            .iter()
+            .filter_map(|(class, variable)| {
+                let v = names.iter().position(|name| name == variable)?;
+                Some((class.clone(), v))
+            })
+            .collect();
+        Self::new((0..next).collect(), body).with_constraints(constraints)
+    }
+
+    /// The type of the scheme with each of its variables replaced by a distinct named type,
+    /// so that it cannot be unified with any other type
+    pub fn skolemize(&self) -> Type {
+        self.body.substitute(&self.skolems())
+    }
+
+    /// The constraints of the scheme, on the named types its variables are replaced by
+    /// when it is skolemized
+    pub fn skolemize_constraints(&self) -> Vec<(String, Type)> {
+        let skolems = self.skolems();
+        self.constraints
+            .iter()
+            .map(|(class, v)| (class.clone(), Type::Variable(*v).substitute(&skolems)))
+            .collect()
+    }
+
+    fn skolems(&self) -> BTreeMap<usize, Type> {
+        let names = variable_names(&self.display_variables());
+        let mut substitution = BTreeMap::new();
+        for v in &self.variables {
+            if let Some(name) = names.get(v) {
+                substitution.insert(*v, Type::named(name));
+            }
+        }
+        substitution
+    }
+
+    /// The variables of the body, followed by any that only appear in constraints
+    fn display_variables(&self) -> Vec<usize> {
+        let mut variables = self.body.variables();
+        for (_, v) in &self.constraints {
+            if !variables.contains(v) {
+                variables.push(*v)
+            }
+        }
+        variables
+    }
+
+    pub fn get_variables(&self) -> &Vec<usize> {
+        &self.variables
+    }
+
+    pub fn get_constraints(&self) -> &Vec<(String, usize)> {
+        &self.constraints
+    }
+
+    pub fn get_body(&self) -> &Type {
+        &self.body
+    }
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let names = variable_names(&self.display_variables());
+        let constraints = self
+            .constraints
+            .iter()
+            .map(|(class, v)| format!("{} {}", class, names[v]))
+            .collect::<Vec<_>>();
+        match constraints.len() {
+            0 => {}
+            1 => write!(f, "{} => ", constraints[0])?,
+            _ => write!(f, "({}) => ", constraints.join(", "))?,
+        }
+        self.body.write(&names, f)
+    }
+}